@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::PopulatedRepositoryOrReplica;
+
+/// The default maximum number of repositories held by a `RepositoryCache`.
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 1024;
+
+// Keyed by (server base URL, repository name), storing the time of insertion with the repository.
+type CacheEntries = HashMap<(String, String), (Instant, PopulatedRepositoryOrReplica)>;
+
+/// A bounded, TTL-based cache of scraped repositories.
+///
+/// When a cache is set in `ScrapeOptions`, a repository that was scraped from the same server less
+/// than `ttl` ago is returned from the cache without making any HTTP requests. The cache holds at
+/// most `max_entries` repositories; when full, expired entries are dropped first, then the
+/// oldest ones. Servers are told apart by their base URL (see `Server::base_url`), so servers on
+/// the same host with different schemes, ports, or base paths do not share entries.
+///
+/// The cache is cheap to clone, and clones share the same entries. This allows the same cache to
+/// be reused across scrapes by passing the same options (or a clone of them) each time.
+///
+/// ### Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cvmfs_server_scraper::{RepositoryCache, ScrapeOptions};
+///
+/// let options = ScrapeOptions {
+///     cache: Some(RepositoryCache::new(Duration::from_secs(60))),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct RepositoryCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Arc<Mutex<CacheEntries>>,
+}
+
+impl RepositoryCache {
+    /// Create a new cache where entries are valid for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        RepositoryCache {
+            ttl,
+            max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set the maximum number of repositories held by the cache.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Get a repository of the server with the given base URL from the cache, if it is present
+    /// and younger than the TTL.
+    pub fn get(&self, base_url: &str, repository: &str) -> Option<PopulatedRepositoryOrReplica> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&(base_url.to_string(), repository.to_string()))
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, repo)| repo.clone())
+    }
+
+    /// Insert a repository scraped from the server with the given base URL into the cache.
+    pub fn insert(&self, base_url: &str, repository: PopulatedRepositoryOrReplica) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let key = (base_url.to_string(), repository.name.to_string());
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+            while entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (inserted, _))| *inserted)
+                    .map(|(key, _)| key.clone());
+                match oldest {
+                    Some(oldest) => entries.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+        entries.insert(key, (Instant::now(), repository));
+    }
+
+    /// The number of entries in the cache, including expired entries not yet evicted.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all entries from the cache.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utilities;
+
+    const BASE_URL: &str = "http://cvmfs.example.org";
+
+    #[test]
+    fn test_cache_hit_and_expiry() {
+        let repo = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+
+        let cache = RepositoryCache::new(Duration::from_secs(60));
+        cache.insert(BASE_URL, repo.clone());
+        assert_eq!(cache.get(BASE_URL, "software.eessi.io"), Some(repo.clone()));
+        assert_eq!(cache.get(BASE_URL, "dev.eessi.io"), None);
+        assert_eq!(
+            cache.get("http://cvmfs.example.org/mirror", "software.eessi.io"),
+            None
+        );
+
+        let expired = RepositoryCache::new(Duration::ZERO);
+        expired.insert(BASE_URL, repo);
+        assert_eq!(expired.get(BASE_URL, "software.eessi.io"), None);
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let cache = RepositoryCache::new(Duration::from_secs(60)).with_max_entries(2);
+        for name in ["one.eessi.io", "two.eessi.io", "three.eessi.io"] {
+            cache.insert(
+                BASE_URL,
+                test_utilities::repository(name, 1, 1718991602, None, None),
+            );
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(BASE_URL, "one.eessi.io").is_none());
+        assert!(cache.get(BASE_URL, "three.eessi.io").is_some());
+    }
+
+    #[test]
+    fn test_cache_clones_share_entries() {
+        let cache = RepositoryCache::new(Duration::from_secs(60));
+        let clone = cache.clone();
+        clone.insert(
+            BASE_URL,
+            test_utilities::repository("software.eessi.io", 1, 1718991602, None, None),
+        );
+        assert!(!cache.is_empty());
+        cache.clear();
+        assert!(clone.is_empty());
+    }
+}
@@ -1,7 +1,39 @@
+use std::time::Duration;
+
 use lazy_static::lazy_static;
 
 use crate::models::Hostname;
 
+/// The default path of the info directory (holding repositories.json and meta.json), relative to
+/// `/cvmfs/` on the server.
+pub const DEFAULT_INFO_PATH: &str = "info/v1";
+
+/// The schema version of repositories.json that this crate supports.
+///
+/// A server advertising another schema is still scraped, with a `ScrapeWarning::UnsupportedSchema`.
+pub const SUPPORTED_REPOSITORIES_JSON_SCHEMA: u32 = 1;
+
+/// The schema version of the files written by `write_fleet_to_file`.
+///
+/// `read_fleet_from_file` rejects files with any other version.
+pub const FLEET_FILE_SCHEMA_VERSION: u32 = 1;
+
+/// The default maximum number of redirects followed.
+///
+/// This is 10 to match the default limit of reqwest, so scrapes follow the same redirects as
+/// before the limit was configurable. A lower limit can be set with
+/// `ScrapeOptions::max_redirects`.
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// The default timeout for establishing a connection to a server.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default timeout for a single HTTP request, from sending the request to reading the body.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default number of repositories scraped concurrently on a single server.
+pub const DEFAULT_REPOSITORY_CONCURRENCY: usize = 8;
+
 lazy_static! {
     pub static ref DEFAULT_GEOAPI_SERVERS: Vec<Hostname> = vec![
         "cvmfs-s1fnal.opensciencegrid.org".parse().unwrap(),
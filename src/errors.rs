@@ -17,6 +17,12 @@ pub enum ManifestError {
 
     #[error("Invalid certificate: {0}")]
     InvalidCertificate(String),
+
+    #[error("Invalid signature block: {0}")]
+    InvalidSignature(String),
+
+    #[error("Invalid JSON manifest: {0}")]
+    JsonConversionError(String),
 }
 
 #[derive(Error, Debug, Clone)]
@@ -35,6 +41,24 @@ pub enum HostnameError {
 
     #[error("Label contains consecutive dashes: {0}")]
     ConsecutiveDashes(String),
+
+    #[error("Invalid port: {0}")]
+    InvalidPort(String),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum RepositoryNameError {
+    #[error("Invalid repository name length: {0} > 255")]
+    TooLong(String),
+
+    #[error("Repository name is not fully qualified: {0}")]
+    NotFullyQualified(String),
+
+    #[error("Invalid character in repository name: {0}")]
+    InvalidChar(String),
+
+    #[error("Empty label in repository name: {0}")]
+    EmptyLabel(String),
 }
 
 #[derive(Error, Debug, Clone)]
@@ -54,14 +78,122 @@ pub enum ScrapeError {
     #[error("Server type mismatch: {0}")]
     ServerTypeMismatch(String),
 
+    #[error("No repositories published in repositories.json: {0}")]
+    NoRepositoriesPublished(String),
+
     #[error("Chrono parsing error: {0}")]
     ChronoParseError(#[from] chrono::ParseError),
 
-    #[error("Conversion error: {0}")]
-    ConversionError(String),
+    #[error("Conversion error: {error} (input: {input:?})")]
+    ConversionError { input: String, error: String },
 
     #[error("GeoAPI failure: {0}")]
     GeoAPIFailure(String),
+
+    #[error("Requested repository not found: {0}")]
+    RequestedRepositoryNotFound(String),
+
+    #[error("Failed to read local file: {0}")]
+    LocalFileError(String),
+
+    #[error("Local file not found: {0}")]
+    LocalFileNotFound(String),
+
+    #[error("Unexpected redirect from {url} to {location}")]
+    UnexpectedRedirect { url: String, location: String },
+
+    #[error("Failed to read tag history database: {0}")]
+    HistoryDatabase(String),
+
+    #[error("Failed to fetch {kind} for {context}: {source}")]
+    DocumentError {
+        kind: DocumentKind,
+        context: String,
+        source: Box<ScrapeError>,
+    },
+}
+
+impl ScrapeError {
+    /// A conversion error for the given input, with the reason it could not be converted.
+    pub fn conversion<S: Into<String>, E: std::fmt::Display>(input: S, error: E) -> Self {
+        ScrapeError::ConversionError {
+            input: input.into(),
+            error: error.to_string(),
+        }
+    }
+
+    /// Wrap the error with the kind of document being fetched and where it was fetched from.
+    pub fn in_document<S: Into<String>>(self, kind: DocumentKind, context: S) -> Self {
+        ScrapeError::DocumentError {
+            kind,
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns true if the underlying error is a failure to fetch (as opposed to parse) a document.
+    pub fn is_fetch_error(&self) -> bool {
+        match self {
+            ScrapeError::FetchError(_)
+            | ScrapeError::LocalFileError(_)
+            | ScrapeError::LocalFileNotFound(_) => true,
+            ScrapeError::DocumentError { source, .. } => source.is_fetch_error(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the underlying error is a failure to connect to the server, such as a
+    /// refused connection or a failed TLS handshake.
+    pub fn is_connect_error(&self) -> bool {
+        match self {
+            ScrapeError::FetchError(error) => error.is_connect(),
+            ScrapeError::DocumentError { source, .. } => source.is_connect_error(),
+            _ => false,
+        }
+    }
+
+    /// The location of the underlying redirect that was not followed, if any.
+    pub fn redirect_location(&self) -> Option<&str> {
+        match self {
+            ScrapeError::UnexpectedRedirect { location, .. } => Some(location),
+            ScrapeError::DocumentError { source, .. } => source.redirect_location(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the underlying error is an HTTP 404 Not Found response, or a missing local
+    /// file (see `Server::with_local_root`).
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            ScrapeError::FetchError(error) => {
+                error.status() == Some(reqwest::StatusCode::NOT_FOUND)
+            }
+            ScrapeError::LocalFileNotFound(_) => true,
+            ScrapeError::DocumentError { source, .. } => source.is_not_found(),
+            _ => false,
+        }
+    }
+}
+
+/// The kind of document fetched from a server, used to give context to errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    RepositoriesJson,
+    MetaJson,
+    StatusJson,
+    HistoryDatabase,
+}
+
+impl std::fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DocumentKind::RepositoriesJson => "repositories.json",
+            DocumentKind::MetaJson => "meta.json",
+            DocumentKind::StatusJson => ".cvmfs_status.json",
+            DocumentKind::HistoryDatabase => "tag history database",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Error, Debug, Clone)]
@@ -82,8 +214,65 @@ pub enum CVMFSScraperError {
     #[error("Hostname error: {0}")]
     HostnameError(#[from] HostnameError),
 
+    #[error("Repository name error: {0}")]
+    RepositoryNameError(#[from] RepositoryNameError),
+
     #[error("Generic error: {0}")]
     GenericError(#[from] GenericError),
+
+    /// An error read back from a saved scrape, see `read_fleet_from_file`. Only the message of
+    /// the original error is kept.
+    #[error("{0}")]
+    Restored(String),
+}
+
+impl CVMFSScraperError {
+    /// Returns true if the underlying error is an HTTP 404 Not Found response.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            CVMFSScraperError::ScrapeError(error) => error.is_not_found(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the underlying error is a failure to connect, see
+    /// `ScrapeError::is_connect_error`.
+    pub fn is_connect_error(&self) -> bool {
+        match self {
+            CVMFSScraperError::ScrapeError(error) => error.is_connect_error(),
+            _ => false,
+        }
+    }
+}
+
+/// An error reading saved scrape results, see `read_fleet_from_file`.
+///
+/// - Io: The file could not be read.
+/// - Parse: The file is not a valid saved scrape.
+/// - UnsupportedSchemaVersion: The file was written with a schema version this crate cannot
+///   read (see `FLEET_FILE_SCHEMA_VERSION`), typically by a newer version of the crate.
+#[derive(Error, Debug, Clone)]
+pub enum ReadError {
+    #[error("Failed to read saved scrape: {0}")]
+    Io(Arc<std::io::Error>),
+
+    #[error("Failed to parse saved scrape: {0}")]
+    Parse(Arc<serde_json::Error>),
+
+    #[error("Unsupported saved scrape schema version {found} (supported: {supported})")]
+    UnsupportedSchemaVersion { found: u32, supported: u32 },
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(error: std::io::Error) -> Self {
+        ReadError::Io(Arc::new(error))
+    }
+}
+
+impl From<serde_json::Error> for ReadError {
+    fn from(error: serde_json::Error) -> Self {
+        ReadError::Parse(Arc::new(error))
+    }
 }
 
 impl From<reqwest::Error> for ManifestError {
@@ -111,3 +300,65 @@ impl From<std::convert::Infallible> for HostnameError {
         unreachable!("Infallible conversions cannot fail")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_document_error_context() {
+        let fetch_error: ScrapeError = reqwest::Client::new()
+            .get("http://")
+            .send()
+            .await
+            .unwrap_err()
+            .into();
+        let error = fetch_error.in_document(
+            DocumentKind::StatusJson,
+            "repository software.eessi.io on host cvmfs.example.org",
+        );
+        assert!(error.is_fetch_error());
+        assert!(error.to_string().starts_with(
+            "Failed to fetch .cvmfs_status.json for repository software.eessi.io on host cvmfs.example.org: "
+        ));
+    }
+
+    #[test]
+    fn test_document_error_parse_failure_is_not_fetch_error() {
+        let error = ScrapeError::InvalidJson("unexpected end of input".to_string())
+            .in_document(DocumentKind::MetaJson, "host cvmfs.example.org");
+        assert!(!error.is_fetch_error());
+    }
+
+    #[test]
+    fn test_conversion_error_includes_input() {
+        let error = "stratum2".parse::<crate::models::ServerType>().unwrap_err();
+        assert!(matches!(
+            &error,
+            ScrapeError::ConversionError { input, .. } if input == "stratum2"
+        ));
+        assert_eq!(
+            error.to_string(),
+            r#"Conversion error: Invalid server type (input: "stratum2")"#
+        );
+    }
+
+    fn status_error(status: u16) -> ScrapeError {
+        let response = http::Response::builder().status(status).body("").unwrap();
+        reqwest::Response::from(response)
+            .error_for_status()
+            .unwrap_err()
+            .into()
+    }
+
+    #[test]
+    fn test_is_not_found() {
+        let not_found = status_error(404).in_document(DocumentKind::StatusJson, "host example.org");
+        assert!(not_found.is_not_found());
+        assert!(CVMFSScraperError::from(not_found).is_not_found());
+        assert!(!status_error(503).is_not_found());
+        assert!(!ScrapeError::InvalidJson("404".to_string()).is_not_found());
+        assert!(ScrapeError::LocalFileNotFound("/missing".to_string()).is_not_found());
+        assert!(!ScrapeError::LocalFileError("/unreadable".to_string()).is_not_found());
+    }
+}
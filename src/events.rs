@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use crate::models::Hostname;
+
+/// An event emitted while scraping, for observability.
+///
+/// Events are sent to the broadcast channel in `ScrapeOptions::event_tx`, if set. Sending never
+/// blocks the scrape: if there are no receivers, the event is dropped, and a receiver that falls
+/// behind will see `RecvError::Lagged` rather than slowing down the scrape.
+///
+/// - ScrapeStarted: The scrape of a server has started.
+/// - RepositoryScraped: A repository was scraped (or returned from the cache).
+/// - RepositoryFailed: A repository could not be scraped. This fails the scrape of the server.
+/// - ScrapeCompleted: The scrape of a server completed, with the number of repositories scraped.
+/// - ScrapeFailed: The scrape of a server failed.
+///
+/// Errors are given as strings, as events must be cloneable to be broadcast.
+///
+/// ### Example
+///
+/// ```rust
+/// use cvmfs_server_scraper::{ScrapeEvent, ScrapeOptions};
+///
+/// let (tx, mut rx) = tokio::sync::broadcast::channel::<ScrapeEvent>(128);
+/// let options = ScrapeOptions {
+///     event_tx: Some(tx),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrapeEvent {
+    ScrapeStarted {
+        hostname: Hostname,
+    },
+    RepositoryScraped {
+        hostname: Hostname,
+        repo_name: String,
+        revision: u64,
+    },
+    RepositoryFailed {
+        hostname: Hostname,
+        repo_name: String,
+        error: String,
+    },
+    ScrapeCompleted {
+        hostname: Hostname,
+        duration: Duration,
+        repo_count: usize,
+    },
+    ScrapeFailed {
+        hostname: Hostname,
+        error: String,
+    },
+}
@@ -0,0 +1,1484 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use futures::future::join_all;
+
+use crate::errors::{CVMFSScraperError, DocumentKind, ScrapeError};
+use crate::models::{
+    servers_from_urls, FailedServer, Hostname, Manifest, PopulatedRepositoryOrReplica,
+    PopulatedServer, RepositoryName, ScrapedServer, Server, ServerIdentity, ServerType, StatusJSON,
+    CSV_HEADER,
+};
+use crate::options::ScrapeOptions;
+use crate::scraper::scrape_servers;
+use crate::telemetry::ScrapeSpan;
+use crate::utilities::{fetch_document, fetch_text};
+
+/// A collection of servers that are scraped together.
+///
+/// A fleet holds the servers, the repositories to scrape on every server (required for servers
+/// using the S3 backend), and the options to use for the scrape. It behaves like a collection of
+/// servers: it can be built from a `Vec<Server>` or an iterator, extended with more servers, and
+/// dereferences to a slice of servers.
+///
+/// ### Example
+///
+/// ```rust
+/// use cvmfs_server_scraper::{Fleet, Hostname, Server, ServerBackendType, ServerType};
+///
+/// let mut fleet: Fleet = vec![Server::new(
+///     ServerType::Stratum1,
+///     ServerBackendType::CVMFS,
+///     Hostname::try_from("azure-us-east-s1.eessi.science").unwrap(),
+/// )]
+/// .into();
+///
+/// fleet.extend(vec![Server::new(
+///     ServerType::Stratum1,
+///     ServerBackendType::AutoDetect,
+///     Hostname::try_from("aws-eu-central-s1.eessi.science").unwrap(),
+/// )]);
+///
+/// assert_eq!(fleet.len(), 2);
+/// for server in fleet.iter() {
+///     println!("{}", server.hostname);
+/// }
+/// ```
+///
+/// ### Scraped and operational
+///
+/// A server is *scraped* if the scrape succeeded, i.e. the server was reachable and every
+/// document could be fetched and parsed (`ScrapedServer::Populated`). Whether a scraped server
+/// is also *operational* is a policy decision, e.g. "at least one repository and a snapshot
+/// within the last six hours". The policy is set with `with_operational_check`, and applied by
+/// `is_operational` and `scrape_all_with_status`. A server that failed to scrape is never
+/// operational. Without a check, every scraped server is operational.
+#[derive(Clone, Default)]
+pub struct Fleet {
+    servers: Vec<Server>,
+    repositories: Vec<String>,
+    options: ScrapeOptions,
+    operational_check: Option<OperationalCheck>,
+}
+
+/// A predicate deciding whether a scraped server is operational, see `Fleet`.
+pub type OperationalCheck = Arc<dyn Fn(&PopulatedServer) -> bool + Send + Sync>;
+
+impl std::fmt::Debug for Fleet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fleet")
+            .field("servers", &self.servers)
+            .field("repositories", &self.repositories)
+            .field("options", &self.options)
+            .field("operational_check", &self.operational_check.is_some())
+            .finish()
+    }
+}
+
+/// The result of scraping a server in a fleet, with whether it is operational.
+///
+/// See `Fleet` for the distinction between scraped and operational.
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub scraped: ScrapedServer,
+    pub operational: bool,
+}
+
+/// The changes to the repositories of a server between two scrapes, see `ServerDiff::between`.
+///
+/// Fields:
+///
+/// - added_repositories: The repositories scraped only in the later scrape, sorted by name.
+/// - removed_repositories: The repositories scraped only in the earlier scrape, sorted by name.
+/// - revision_changes: The repositories whose revision changed, sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServerDiff {
+    pub added_repositories: Vec<String>,
+    pub removed_repositories: Vec<String>,
+    pub revision_changes: Vec<RevisionChange>,
+}
+
+/// A change of the revision of a repository, see `ServerDiff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevisionChange {
+    pub repository: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl ServerDiff {
+    /// The changes to the repositories of a server from `before` to `after`.
+    pub fn between(before: &PopulatedServer, after: &PopulatedServer) -> Self {
+        let revisions = |server: &PopulatedServer| {
+            server
+                .repositories
+                .iter()
+                .map(|repo| (repo.name.to_string(), repo.revision()))
+                .collect::<std::collections::BTreeMap<_, _>>()
+        };
+        let before = revisions(before);
+        let after = revisions(after);
+        ServerDiff {
+            added_repositories: after
+                .keys()
+                .filter(|name| !before.contains_key(*name))
+                .cloned()
+                .collect(),
+            removed_repositories: before
+                .keys()
+                .filter(|name| !after.contains_key(*name))
+                .cloned()
+                .collect(),
+            revision_changes: before
+                .iter()
+                .filter_map(|(name, before)| {
+                    let after = *after.get(name)?;
+                    (after != *before).then(|| RevisionChange {
+                        repository: name.clone(),
+                        before: *before,
+                        after,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_repositories.is_empty()
+            && self.removed_repositories.is_empty()
+            && self.revision_changes.is_empty()
+    }
+}
+
+/// The changes to a fleet between two scrapes, see `Fleet::diff`.
+///
+/// Servers are matched by hostname, port, base path and server type (see `ServerIdentity`).
+///
+/// Fields:
+///
+/// - new_servers: Servers scraped in the later scrape that were not in the earlier one.
+/// - removed_servers: Servers in the earlier scrape that are not in the later one.
+/// - newly_failed: Servers that failed in the later scrape, but not in the earlier one
+///   (including servers that were not in it).
+/// - recovered: Servers that failed in the earlier scrape and were scraped in the later one.
+/// - changed_repositories: The changes to the repositories of servers scraped both times, for
+///   the servers where anything changed.
+///
+/// Every list is in the order of the scrape it is taken from.
+#[derive(Debug, Clone, Default)]
+pub struct FleetDiff<'a> {
+    pub new_servers: Vec<&'a PopulatedServer>,
+    pub removed_servers: Vec<Hostname>,
+    pub newly_failed: Vec<&'a FailedServer>,
+    pub recovered: Vec<&'a PopulatedServer>,
+    pub changed_repositories: Vec<(Hostname, ServerDiff)>,
+}
+
+impl FleetDiff<'_> {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.new_servers.is_empty()
+            && self.removed_servers.is_empty()
+            && self.newly_failed.is_empty()
+            && self.recovered.is_empty()
+            && self.changed_repositories.is_empty()
+    }
+}
+
+fn identity(scraped: &ScrapedServer) -> ServerIdentity {
+    match scraped {
+        ScrapedServer::Populated(server) => server.identity(),
+        ScrapedServer::Failed(server) => ServerIdentity::from(server),
+    }
+}
+
+impl Fleet {
+    /// Create a new fleet from a list of servers, using the default options.
+    pub fn new(servers: Vec<Server>) -> Self {
+        Fleet {
+            servers,
+            repositories: Vec::new(),
+            options: ScrapeOptions::default(),
+            operational_check: None,
+        }
+    }
+
+    /// Set the repositories to scrape on every server in the fleet.
+    ///
+    /// These are merged with any repositories discovered via repositories.json, see `Server::scrape`.
+    pub fn with_repositories<I, S>(mut self, repositories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.repositories = repositories.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the options used when scraping the fleet.
+    pub fn with_options(mut self, options: ScrapeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the check deciding whether a scraped server is operational.
+    ///
+    /// The check is only called for servers that scraped successfully.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use cvmfs_server_scraper::Fleet;
+    ///
+    /// // Operational: at least one repository, all snapshotted within the last six hours.
+    /// let fleet = Fleet::default().with_operational_check(|server| {
+    ///     !server.repositories.is_empty()
+    ///         && server.repositories.iter().all(|repo| {
+    ///             repo.snapshot_age()
+    ///                 .is_some_and(|age| age < chrono::Duration::hours(6))
+    ///         })
+    /// });
+    /// ```
+    pub fn with_operational_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&PopulatedServer) -> bool + Send + Sync + 'static,
+    {
+        self.operational_check = Some(Arc::new(check));
+        self
+    }
+
+    /// Returns true if the server was scraped and passes the operational check (if any).
+    pub fn is_operational(&self, scraped: &ScrapedServer) -> bool {
+        match scraped {
+            ScrapedServer::Populated(server) => self
+                .operational_check
+                .as_ref()
+                .is_none_or(|check| check(server)),
+            ScrapedServer::Failed(_) => false,
+        }
+    }
+
+    pub fn servers(&self) -> &[Server] {
+        &self.servers
+    }
+
+    pub fn repositories(&self) -> &[String] {
+        &self.repositories
+    }
+
+    pub fn options(&self) -> &ScrapeOptions {
+        &self.options
+    }
+
+    /// Scrape every server in the fleet in parallel.
+    ///
+    /// The results are returned in the same order as the servers in the fleet.
+    pub async fn scrape_all(&self) -> Vec<ScrapedServer> {
+        scrape_servers(
+            self.servers.clone(),
+            self.repositories.clone(),
+            &self.options,
+        )
+        .await
+    }
+
+    /// As `scrape_all`, with whether each server is operational, see `is_operational`.
+    pub async fn scrape_all_with_status(&self) -> Vec<ServerStatus> {
+        self.scrape_all()
+            .await
+            .into_iter()
+            .map(|scraped| ServerStatus {
+                operational: self.is_operational(&scraped),
+                scraped,
+            })
+            .collect()
+    }
+
+    /// Compare two scrapes of a fleet, e.g. the results of consecutive `scrape_all` calls.
+    ///
+    /// See `FleetDiff` for what is compared.
+    pub fn diff<'a>(before: &[ScrapedServer], after: &'a [ScrapedServer]) -> FleetDiff<'a> {
+        let earlier = before
+            .iter()
+            .map(|scraped| (identity(scraped), scraped))
+            .collect::<HashMap<_, _>>();
+        let later = after.iter().map(identity).collect::<HashSet<_>>();
+
+        let mut diff = FleetDiff {
+            removed_servers: before
+                .iter()
+                .map(identity)
+                .filter(|identity| !later.contains(identity))
+                .map(|identity| identity.hostname)
+                .collect(),
+            ..Default::default()
+        };
+        for scraped in after {
+            match (earlier.get(&identity(scraped)), scraped) {
+                (None, ScrapedServer::Populated(server)) => diff.new_servers.push(server),
+                (Some(ScrapedServer::Failed(_)), ScrapedServer::Populated(server)) => {
+                    diff.recovered.push(server)
+                }
+                (Some(ScrapedServer::Populated(previous)), ScrapedServer::Populated(server)) => {
+                    let changes = ServerDiff::between(previous, server);
+                    if !changes.is_empty() {
+                        diff.changed_repositories
+                            .push((server.hostname.clone(), changes));
+                    }
+                }
+                (Some(ScrapedServer::Failed(_)), ScrapedServer::Failed(_)) => {}
+                (_, ScrapedServer::Failed(server)) => diff.newly_failed.push(server),
+            }
+        }
+        diff
+    }
+}
+
+impl From<Vec<Server>> for Fleet {
+    fn from(servers: Vec<Server>) -> Self {
+        Fleet::new(servers)
+    }
+}
+
+impl FromIterator<Server> for Fleet {
+    fn from_iter<I: IntoIterator<Item = Server>>(iter: I) -> Self {
+        Fleet::new(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Server> for Fleet {
+    fn extend<I: IntoIterator<Item = Server>>(&mut self, iter: I) {
+        self.servers.extend(iter);
+    }
+}
+
+impl Deref for Fleet {
+    type Target = [Server];
+
+    fn deref(&self) -> &Self::Target {
+        &self.servers
+    }
+}
+
+/// Split a list of scrape results into populated and failed servers.
+///
+/// The relative order of the servers is preserved within each of the returned lists.
+pub fn partition_scraped(results: Vec<ScrapedServer>) -> (Vec<PopulatedServer>, Vec<FailedServer>) {
+    let mut populated = Vec::new();
+    let mut failed = Vec::new();
+    for result in results {
+        match result {
+            ScrapedServer::Populated(server) => populated.push(server),
+            ScrapedServer::Failed(server) => failed.push(server),
+        }
+    }
+    (populated, failed)
+}
+
+/// The populated servers of a list of scrape results, in order. Failed servers are dropped.
+pub fn into_populated(results: Vec<ScrapedServer>) -> Vec<PopulatedServer> {
+    let mut populated = Vec::new();
+    populated.extend(results);
+    populated
+}
+
+/// The failed servers of a list of scrape results, in order. Populated servers are dropped.
+pub fn into_failed(results: Vec<ScrapedServer>) -> Vec<FailedServer> {
+    let mut failed = Vec::new();
+    failed.extend(results);
+    failed
+}
+
+/// Iterate over the populated servers of a list of scrape results, in order.
+pub fn populated_iter(results: &[ScrapedServer]) -> impl Iterator<Item = &PopulatedServer> {
+    results.iter().filter_map(|scraped| match scraped {
+        ScrapedServer::Populated(server) => Some(server),
+        ScrapedServer::Failed(_) => None,
+    })
+}
+
+/// Iterate over the failed servers of a list of scrape results, in order.
+pub fn failed_iter(results: &[ScrapedServer]) -> impl Iterator<Item = &FailedServer> {
+    results.iter().filter_map(|scraped| match scraped {
+        ScrapedServer::Failed(failed) => Some(failed),
+        ScrapedServer::Populated(_) => None,
+    })
+}
+
+/// Scrape results grouped by server type, see `group_by_server_type`.
+///
+/// Server types without any servers have no entry. Within each group, servers are sorted by
+/// hostname.
+#[derive(Debug, Clone, Default)]
+pub struct GroupedResults {
+    pub populated: std::collections::BTreeMap<ServerType, Vec<PopulatedServer>>,
+    pub failed: std::collections::BTreeMap<ServerType, Vec<FailedServer>>,
+}
+
+/// Group a list of scrape results by server type, e.g. for reports per stratum.
+///
+/// The grouping is deterministic: groups are ordered by server type, and servers within a group
+/// by hostname (servers with the same hostname keep their relative order).
+pub fn group_by_server_type(results: Vec<ScrapedServer>) -> GroupedResults {
+    let mut grouped = GroupedResults::default();
+    for result in results {
+        match result {
+            ScrapedServer::Populated(server) => grouped
+                .populated
+                .entry(server.server_type)
+                .or_default()
+                .push(server),
+            ScrapedServer::Failed(server) => grouped
+                .failed
+                .entry(server.server_type)
+                .or_default()
+                .push(server),
+        }
+    }
+    for servers in grouped.populated.values_mut() {
+        servers.sort_by(|a, b| a.hostname.to_str().cmp(b.hostname.to_str()));
+    }
+    for servers in grouped.failed.values_mut() {
+        servers.sort_by(|a, b| a.hostname.to_str().cmp(b.hostname.to_str()));
+    }
+    grouped
+}
+
+/// Collect the populated servers of scrape results, ignoring failed servers.
+impl Extend<ScrapedServer> for Vec<PopulatedServer> {
+    fn extend<I: IntoIterator<Item = ScrapedServer>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().filter_map(|scraped| match scraped {
+            ScrapedServer::Populated(server) => Some(server),
+            ScrapedServer::Failed(_) => None,
+        }));
+    }
+}
+
+/// Collect the failed servers of scrape results, ignoring populated servers.
+impl Extend<ScrapedServer> for Vec<FailedServer> {
+    fn extend<I: IntoIterator<Item = ScrapedServer>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().filter_map(|scraped| match scraped {
+            ScrapedServer::Failed(failed) => Some(failed),
+            ScrapedServer::Populated(_) => None,
+        }));
+    }
+}
+
+/// Find repositories where garbage collection appears to have stalled.
+///
+/// Returns the hostname and repository for every repository on the given servers where
+/// garbage collection has not run for longer than `max_gc_age`, while new revisions have been
+/// published since it last ran. See `PopulatedRepositoryOrReplica::gc_stalled`.
+pub fn stalled_gc_repositories(
+    servers: &[PopulatedServer],
+    max_gc_age: chrono::Duration,
+) -> Vec<(&Hostname, &PopulatedRepositoryOrReplica)> {
+    servers
+        .iter()
+        .flat_map(|server| {
+            server
+                .repositories
+                .iter()
+                .filter(move |repo| repo.gc_stalled(max_gc_age))
+                .map(move |repo| (&server.hostname, repo))
+        })
+        .collect()
+}
+
+/// The key used for servers that do not report a value, see `VersionDistribution`.
+pub const UNKNOWN_VERSION: &str = "unknown";
+
+/// The CVMFS versions and operating systems running across a fleet, see `version_distribution`.
+///
+/// - cvmfs_versions: The servers running each CVMFS version.
+/// - operating_systems: The servers running each operating system (by its pretty name).
+///
+/// Servers that do not report a value (S3 backends, or servers withholding it for privacy
+/// reasons) are grouped under `UNKNOWN_VERSION`. A CVMFS version that could not be parsed is
+/// grouped under the version as reported.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VersionDistribution {
+    pub cvmfs_versions: HashMap<String, Vec<Hostname>>,
+    pub operating_systems: HashMap<String, Vec<Hostname>>,
+}
+
+/// Group the servers of a fleet by CVMFS version and operating system.
+pub fn version_distribution(servers: &[PopulatedServer]) -> VersionDistribution {
+    let mut distribution = VersionDistribution::default();
+    for server in servers {
+        let metadata = &server.metadata;
+        let version = metadata
+            .cvmfs_version
+            .as_ref()
+            .map(|version| version.to_string())
+            .or_else(|| metadata.cvmfs_version_raw.clone())
+            .unwrap_or_else(|| UNKNOWN_VERSION.to_string());
+        let os = metadata
+            .os_pretty_name
+            .clone()
+            .unwrap_or_else(|| UNKNOWN_VERSION.to_string());
+        distribution
+            .cvmfs_versions
+            .entry(version)
+            .or_default()
+            .push(server.hostname.clone());
+        distribution
+            .operating_systems
+            .entry(os)
+            .or_default()
+            .push(server.hostname.clone());
+    }
+    distribution
+}
+
+/// The repositories of every server as CSV, under a single header line.
+///
+/// Each line ends with a newline. See `PopulatedServer::to_csv_rows` for the columns.
+pub fn fleet_to_csv(servers: &[PopulatedServer]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for row in servers.iter().flat_map(|server| server.to_csv_rows(false)) {
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Check every server against a CVMFS version requirement.
+///
+/// Returns each server with whether it satisfies the requirement, in the order given. A server
+/// with an unknown version does not satisfy it. See `ServerMetadata::meets_minimum_version`.
+pub fn all_servers_meet_version<'a>(
+    servers: &'a [PopulatedServer],
+    min: &semver::VersionReq,
+) -> Vec<(&'a PopulatedServer, bool)> {
+    servers
+        .iter()
+        .map(|server| {
+            (
+                server,
+                server.metadata.meets_minimum_version(min).unwrap_or(false),
+            )
+        })
+        .collect()
+}
+
+/// Fetch the repository names of every server in parallel, keyed by hostname.
+///
+/// See `Server::fetch_repository_names`. Servers that fail to respond are left out, so a
+/// failing server is not mistaken for a server without repositories. Use
+/// `fleet_repository_names_with_options` to get their errors.
+pub async fn fleet_repository_names(servers: &[Server]) -> HashMap<String, Vec<String>> {
+    fleet_repository_names_with_options(servers, &[], &ScrapeOptions::default())
+        .await
+        .into_iter()
+        .filter_map(|(hostname, names)| Some((hostname, names.ok()?)))
+        .collect()
+}
+
+/// Fetch the repository names of every server in parallel with the given options, keyed by
+/// hostname.
+///
+/// See `Server::fetch_repository_names_with_options`. The given repositories are used for
+/// servers using the S3 backend. A server that fails to respond maps to the error. The client is
+/// built from the options, which also give the info path and the default scheme and port.
+pub async fn fleet_repository_names_with_options(
+    servers: &[Server],
+    repositories: &[String],
+    options: &ScrapeOptions,
+) -> HashMap<String, Result<Vec<String>, ScrapeError>> {
+    let client = match options.build_client() {
+        Ok(client) => client,
+        Err(error) => {
+            return servers
+                .iter()
+                .map(|server| (server.hostname.to_string(), Err(error.clone())))
+                .collect()
+        }
+    };
+    let futures = servers.iter().map(|server| {
+        let client = &client;
+        async move {
+            (
+                server.hostname.to_string(),
+                server
+                    .fetch_repository_names_with_options(client, repositories, options)
+                    .await,
+            )
+        }
+    });
+    join_all(futures).await.into_iter().collect()
+}
+
+/// Discover the Stratum1 replicas of a scraped Stratum0, from its `recommended_stratum1_list`.
+///
+/// This builds a Stratum1 server for each recommended Stratum1 as `Server::discover_stratum1s`
+/// does, but uses the list from the repositories.json of the scrape if it was fetched. Otherwise
+/// (e.g. for a server scraped without its repositories.json) repositories.json is fetched again
+/// from `server`, the server the scrape came from, with the given options (see
+/// `Server::discover_stratum1s`). As with `Server::rescrape`, the scheme the scrape settled on is
+/// reused unless the server sets one. Fails with `ScrapeError::ServerTypeMismatch` if the scrape
+/// is not of a Stratum0.
+///
+/// Together with `Fleet`, this discovers a whole fleet from a single Stratum0.
+pub async fn discover_replicas(
+    stratum0: &PopulatedServer,
+    server: &Server,
+    client: &reqwest::Client,
+    options: &ScrapeOptions,
+) -> Result<Vec<Server>, ScrapeError> {
+    if stratum0.server_type != ServerType::Stratum0 {
+        return Err(ScrapeError::ServerTypeMismatch(format!(
+            "{} is a {}, replicas can only be discovered from a stratum0",
+            stratum0.hostname, stratum0.server_type
+        )));
+    }
+    let urls = &stratum0.metadata.recommended_stratum1_list;
+    let fetched = stratum0
+        .repositories_json_timing
+        .is_some_and(|timing| timing.bytes.is_some());
+    if fetched || !urls.is_empty() {
+        return Ok(servers_from_urls(urls, ServerType::Stratum1));
+    }
+    Server {
+        scheme: server.scheme.or(Some(stratum0.scheme)),
+        ..server.clone()
+    }
+    .discover_stratum1s(client, options)
+    .await
+}
+
+/// Scrape a single repository from the URLs of its `.cvmfspublished` and `.cvmfs_status.json`.
+///
+/// This does not need a `Server`, and works for repositories not listed in a server's
+/// repositories.json. The name of the repository is taken from the path of the manifest URL (the
+/// segment before `.cvmfspublished`, e.g. `software.eessi.io` in
+/// `http://host/cvmfs/software.eessi.io/.cvmfspublished`). If the path does not contain a valid
+/// repository name, the name in the manifest is used. Use `scrape_url_with_name` to give the name
+/// explicitly.
+///
+/// As with `Server::scrape`, `file://` URLs are read from the local filesystem.
+pub async fn scrape_url(
+    manifest_url: &str,
+    status_json_url: &str,
+    client: &reqwest::Client,
+) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
+    scrape_url_inner(manifest_url, status_json_url, None, client).await
+}
+
+/// As `scrape_url`, with the name of the repository given explicitly.
+///
+/// The name is validated as a `RepositoryName` before anything is fetched.
+pub async fn scrape_url_with_name(
+    name: &str,
+    manifest_url: &str,
+    status_json_url: &str,
+    client: &reqwest::Client,
+) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
+    let name = RepositoryName::try_from(name)?;
+    scrape_url_inner(manifest_url, status_json_url, Some(name), client).await
+}
+
+async fn scrape_url_inner(
+    manifest_url: &str,
+    status_json_url: &str,
+    name: Option<RepositoryName>,
+    client: &reqwest::Client,
+) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
+    let name = name.or_else(|| repository_name_from_url(manifest_url));
+    let span = ScrapeSpan::repository(name.as_ref().map_or(manifest_url, |name| name.to_str()));
+    let result: Result<PopulatedRepositoryOrReplica, CVMFSScraperError> = async {
+        let repo_status: StatusJSON = fetch_document(
+            client,
+            status_json_url,
+            DocumentKind::StatusJson,
+            &format!("repository at {}", status_json_url),
+        )
+        .await?;
+        let manifest = fetch_text(client, manifest_url)
+            .await?
+            .parse::<Manifest>()?;
+        let name = match name {
+            Some(name) => name,
+            None => RepositoryName::try_from(manifest.n.as_str())?,
+        };
+        Ok(PopulatedRepositoryOrReplica::from_parts(
+            name,
+            manifest,
+            repo_status,
+        ))
+    }
+    .await;
+    match &result {
+        Ok(populated) => {
+            span.set_revision(populated.revision());
+            span.finish(None);
+        }
+        Err(error) => {
+            span.finish(Some(error.to_string()));
+        }
+    }
+    result
+}
+
+/// The repository name in the path of a repository URL, i.e. the segment before the file name.
+fn repository_name_from_url(url: &str) -> Option<RepositoryName> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let mut segments = url.path_segments()?.rev();
+    segments.next()?;
+    RepositoryName::try_from(segments.next()?).ok()
+}
+
+/// A repository found on a replica that the Stratum0 no longer publishes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanRepository {
+    pub hostname: Hostname,
+    pub repository: String,
+}
+
+/// The result of looking for orphan repositories, see `orphan_repositories`.
+///
+/// If the designated Stratum0 is missing from the scan or failed to scrape, the set of published
+/// repositories is unknown and the analysis is skipped. The reason is given in `Skipped`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrphanReport {
+    Orphans(Vec<OrphanRepository>),
+    Skipped(String),
+}
+
+impl OrphanReport {
+    pub fn is_skipped(&self) -> bool {
+        matches!(self, OrphanReport::Skipped(_))
+    }
+
+    /// The orphan repositories found, empty if the analysis was skipped.
+    pub fn orphans(&self) -> &[OrphanRepository] {
+        match self {
+            OrphanReport::Orphans(orphans) => orphans,
+            OrphanReport::Skipped(_) => &[],
+        }
+    }
+}
+
+/// Find repositories present on replicas but not published by the given Stratum0.
+///
+/// Every successfully scraped server other than the Stratum0 is compared against the list of
+/// repositories on the Stratum0, keyed on repository name. Servers that failed to scrape are
+/// not considered. This is typically used to find repositories lingering on Stratum1 servers
+/// after being decommissioned on the Stratum0.
+pub fn orphan_repositories(results: &[ScrapedServer], stratum0: &Hostname) -> OrphanReport {
+    let published = match scraped_stratum0(results, stratum0) {
+        Ok(server) => server,
+        Err(reason) => return OrphanReport::Skipped(reason),
+    };
+
+    let orphans = replicas(results, stratum0)
+        .flat_map(|server| {
+            server
+                .repositories
+                .iter()
+                .filter(|repo| !published.has_repository(repo.name.to_str()))
+                .map(|repo| OrphanRepository {
+                    hostname: server.hostname.clone(),
+                    repository: repo.name.to_string(),
+                })
+        })
+        .collect();
+
+    OrphanReport::Orphans(orphans)
+}
+
+/// A replica that has not yet snapshotted the revision published by its Stratum0.
+///
+/// - hostname: The hostname of the replica.
+/// - repository: The name of the repository.
+/// - revision: The revision on the replica.
+/// - upstream_revision: The revision published by the Stratum0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSnapshot {
+    pub hostname: Hostname,
+    pub repository: String,
+    pub revision: u64,
+    pub upstream_revision: u64,
+}
+
+/// Find replicas that are behind the given Stratum0, i.e. with a snapshot pending or stuck.
+///
+/// Every repository on the successfully scraped servers other than the Stratum0 is compared with
+/// the same repository on the Stratum0, see `PopulatedRepositoryOrReplica::pending_snapshot`.
+/// Repositories the Stratum0 does not publish are skipped (see `orphan_repositories`). If the
+/// Stratum0 is missing from the scan or failed to scrape, there is nothing to compare with, and
+/// the reason is returned as the error.
+pub fn pending_snapshots(
+    results: &[ScrapedServer],
+    stratum0: &Hostname,
+) -> Result<Vec<PendingSnapshot>, String> {
+    let published = scraped_stratum0(results, stratum0)?;
+    Ok(replicas(results, stratum0)
+        .flat_map(|server| {
+            server.repositories.iter().filter_map(|repo| {
+                let upstream = published
+                    .repositories
+                    .iter()
+                    .find(|upstream| upstream.name == repo.name)?;
+                repo.pending_snapshot(upstream.revision())
+                    .then(|| PendingSnapshot {
+                        hostname: server.hostname.clone(),
+                        repository: repo.name.to_string(),
+                        revision: repo.revision(),
+                        upstream_revision: upstream.revision(),
+                    })
+            })
+        })
+        .collect())
+}
+
+/// The scrape of the given Stratum0, or the reason it cannot be used.
+fn scraped_stratum0<'a>(
+    results: &'a [ScrapedServer],
+    stratum0: &Hostname,
+) -> Result<&'a PopulatedServer, String> {
+    let authoritative = results.iter().find(|result| match result {
+        ScrapedServer::Populated(server) => &server.hostname == stratum0,
+        ScrapedServer::Failed(server) => &server.hostname == stratum0,
+    });
+    match authoritative {
+        Some(ScrapedServer::Populated(server)) => Ok(server),
+        Some(ScrapedServer::Failed(server)) => Err(format!(
+            "Stratum0 {} failed to scrape: {}",
+            stratum0, server.error
+        )),
+        None => Err(format!("Stratum0 {} is not part of the scan", stratum0)),
+    }
+}
+
+/// The successfully scraped servers other than the given Stratum0.
+fn replicas<'a>(
+    results: &'a [ScrapedServer],
+    stratum0: &'a Hostname,
+) -> impl Iterator<Item = &'a PopulatedServer> {
+    results.iter().filter_map(move |result| match result {
+        ScrapedServer::Populated(server) if &server.hostname != stratum0 => Some(server),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::GenericError;
+    use crate::models::{RepositoriesJsonTiming, ServerBackendType};
+    use crate::test_utilities::{self, ok_response};
+    use crate::utilities::parse_version_requirement;
+    use yare::parameterized;
+
+    fn server(hostname: &str) -> Server {
+        Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from(hostname).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_fleet_from_vec() {
+        let fleet = Fleet::from(vec![server("one.example.org"), server("two.example.org")]);
+        assert_eq!(fleet.len(), 2);
+        assert!(fleet.repositories().is_empty());
+    }
+
+    #[test]
+    fn test_fleet_operational_check() {
+        let populated = ScrapedServer::Populated(test_utilities::server(
+            "one.example.org",
+            ServerType::Stratum1,
+            vec![],
+        ));
+        let failed = ScrapedServer::Failed(
+            server("two.example.org")
+                .to_failed_server(ScrapeError::EmptyRepositoryList("two".to_string()).into()),
+        );
+
+        let fleet = Fleet::default();
+        assert!(fleet.is_operational(&populated));
+        assert!(!fleet.is_operational(&failed));
+
+        let fleet = fleet.with_operational_check(|server| !server.repositories.is_empty());
+        assert!(!fleet.is_operational(&populated));
+        assert!(!fleet.is_operational(&failed));
+        assert!(format!("{:?}", fleet).contains("operational_check: true"));
+    }
+
+    #[test]
+    fn test_fleet_diff() {
+        let populated = |hostname: &str, revision: u64| {
+            ScrapedServer::Populated(test_utilities::server(
+                hostname,
+                ServerType::Stratum1,
+                vec![
+                    test_utilities::repository("software.eessi.io", revision, 0, None, None),
+                    test_utilities::repository("dev.eessi.io", 1, 0, None, None),
+                ],
+            ))
+        };
+        let failed =
+            |hostname: &str| {
+                ScrapedServer::Failed(server(hostname).to_failed_server(
+                    ScrapeError::EmptyRepositoryList(hostname.to_string()).into(),
+                ))
+            };
+        let before = vec![
+            populated("unchanged.example.org", 1),
+            populated("updated.example.org", 1),
+            populated("failing.example.org", 1),
+            failed("recovering.example.org"),
+            failed("still-failing.example.org"),
+            populated("removed.example.org", 1),
+        ];
+        let after = vec![
+            populated("unchanged.example.org", 1),
+            populated("updated.example.org", 2),
+            failed("failing.example.org"),
+            populated("recovering.example.org", 1),
+            failed("still-failing.example.org"),
+            populated("new.example.org", 1),
+        ];
+
+        let diff = Fleet::diff(&before, &after);
+        let hostnames = |servers: &[&PopulatedServer]| {
+            servers
+                .iter()
+                .map(|server| server.hostname.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hostnames(&diff.new_servers), vec!["new.example.org"]);
+        assert_eq!(hostnames(&diff.recovered), vec!["recovering.example.org"]);
+        assert_eq!(diff.removed_servers.len(), 1);
+        assert_eq!(diff.removed_servers[0].to_str(), "removed.example.org");
+        assert_eq!(diff.newly_failed.len(), 1);
+        assert_eq!(
+            diff.newly_failed[0].hostname.to_str(),
+            "failing.example.org"
+        );
+
+        assert_eq!(diff.changed_repositories.len(), 1);
+        let (hostname, changes) = &diff.changed_repositories[0];
+        assert_eq!(hostname.to_str(), "updated.example.org");
+        assert_eq!(
+            changes.revision_changes,
+            vec![RevisionChange {
+                repository: "software.eessi.io".to_string(),
+                before: 1,
+                after: 2,
+            }]
+        );
+        assert!(changes.added_repositories.is_empty());
+
+        assert!(Fleet::diff(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_server_diff_between() {
+        let before = test_utilities::server(
+            "one.example.org",
+            ServerType::Stratum1,
+            vec![
+                test_utilities::repository("software.eessi.io", 1, 0, None, None),
+                test_utilities::repository("old.eessi.io", 1, 0, None, None),
+            ],
+        );
+        let after = test_utilities::server(
+            "one.example.org",
+            ServerType::Stratum1,
+            vec![
+                test_utilities::repository("software.eessi.io", 1, 0, None, None),
+                test_utilities::repository("new.eessi.io", 1, 0, None, None),
+            ],
+        );
+        let diff = ServerDiff::between(&before, &after);
+        assert_eq!(diff.added_repositories, vec!["new.eessi.io"]);
+        assert_eq!(diff.removed_repositories, vec!["old.eessi.io"]);
+        assert!(diff.revision_changes.is_empty());
+        assert!(ServerDiff::between(&after, &after).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fleet_scrape_all_with_status() {
+        let fleet = Fleet::new(vec![Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        )])
+        .with_repositories(vec!["software.eessi.io"])
+        .with_operational_check(|_| true);
+        let statuses = fleet.scrape_all_with_status().await;
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].scraped.is_failed());
+        assert!(!statuses[0].operational);
+    }
+
+    #[test]
+    fn test_fleet_collect_and_extend() {
+        let mut fleet: Fleet = vec![server("one.example.org")].into_iter().collect();
+        fleet.extend(vec![server("two.example.org"), server("three.example.org")]);
+        let hostnames: Vec<&str> = fleet.iter().map(|s| s.hostname.to_str()).collect();
+        assert_eq!(
+            hostnames,
+            vec!["one.example.org", "two.example.org", "three.example.org"]
+        );
+    }
+
+    #[test]
+    fn test_fleet_with_repositories() {
+        let fleet = Fleet::new(vec![server("one.example.org")])
+            .with_repositories(vec!["software.eessi.io", "dev.eessi.io"]);
+        assert_eq!(fleet.repositories(), &["software.eessi.io", "dev.eessi.io"]);
+    }
+
+    #[test]
+    fn test_stalled_gc_repositories() {
+        let gc = Some("Sun Jun 16 00:00:59 UTC 2024");
+        let servers = vec![
+            test_utilities::server(
+                "one.example.org",
+                ServerType::Stratum0,
+                vec![
+                    test_utilities::repository("stalled.eessi.io", 2, 1718991602, None, gc),
+                    test_utilities::repository("idle.eessi.io", 1, 1718000000, None, gc),
+                    test_utilities::repository("nogc.eessi.io", 5, 1718991602, None, None),
+                ],
+            ),
+            test_utilities::server(
+                "two.example.org",
+                ServerType::Stratum1,
+                vec![test_utilities::repository(
+                    "stalled.eessi.io",
+                    2,
+                    1718991602,
+                    None,
+                    gc,
+                )],
+            ),
+        ];
+
+        let stalled = stalled_gc_repositories(&servers, chrono::Duration::days(7));
+        let stalled: Vec<(&str, &str)> = stalled
+            .iter()
+            .map(|(hostname, repo)| (hostname.to_str(), repo.name.to_str()))
+            .collect();
+        assert_eq!(
+            stalled,
+            vec![
+                ("one.example.org", "stalled.eessi.io"),
+                ("two.example.org", "stalled.eessi.io"),
+            ]
+        );
+    }
+
+    fn orphan_scan() -> Vec<ScrapedServer> {
+        let repo = |name| test_utilities::repository(name, 1, 1718991602, None, None);
+        vec![
+            ScrapedServer::Populated(test_utilities::server(
+                "stratum0.example.org",
+                ServerType::Stratum0,
+                vec![repo("software.eessi.io")],
+            )),
+            ScrapedServer::Populated(test_utilities::server(
+                "stratum1.example.org",
+                ServerType::Stratum1,
+                vec![repo("software.eessi.io"), repo("old.eessi.io")],
+            )),
+        ]
+    }
+
+    #[test]
+    fn test_pending_snapshots() {
+        let stratum0 = Hostname::try_from("stratum0.example.org").unwrap();
+        let mut results = orphan_scan();
+        results.push(ScrapedServer::Populated(test_utilities::server(
+            "behind.example.org",
+            ServerType::Stratum1,
+            vec![test_utilities::repository(
+                "software.eessi.io",
+                0,
+                1718991602,
+                None,
+                None,
+            )],
+        )));
+        assert_eq!(
+            pending_snapshots(&results, &stratum0).unwrap(),
+            vec![PendingSnapshot {
+                hostname: Hostname::try_from("behind.example.org").unwrap(),
+                repository: "software.eessi.io".to_string(),
+                revision: 0,
+                upstream_revision: 1,
+            }]
+        );
+        let missing = Hostname::try_from("missing.example.org").unwrap();
+        assert!(pending_snapshots(&results, &missing).is_err());
+    }
+
+    #[test]
+    fn test_orphan_repositories() {
+        let stratum0 = Hostname::try_from("stratum0.example.org").unwrap();
+        let report = orphan_repositories(&orphan_scan(), &stratum0);
+        assert_eq!(
+            report.orphans(),
+            &[OrphanRepository {
+                hostname: Hostname::try_from("stratum1.example.org").unwrap(),
+                repository: "old.eessi.io".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_orphan_repositories_stratum0_failed() {
+        let stratum0 = Hostname::try_from("stratum0.example.org").unwrap();
+        let mut results = orphan_scan();
+        results[0] = ScrapedServer::Failed(
+            Server::new(
+                ServerType::Stratum0,
+                ServerBackendType::CVMFS,
+                stratum0.clone(),
+            )
+            .to_failed_server(GenericError::TypeError("unreachable".to_string()).into()),
+        );
+        let report = orphan_repositories(&results, &stratum0);
+        assert!(report.is_skipped());
+        assert!(report.orphans().is_empty());
+    }
+
+    #[test]
+    fn test_orphan_repositories_stratum0_missing() {
+        let stratum0 = Hostname::try_from("missing.example.org").unwrap();
+        let report = orphan_repositories(&orphan_scan(), &stratum0);
+        assert!(report.is_skipped());
+    }
+
+    #[tokio::test]
+    async fn test_fleet_repository_names_s3_uses_given_repositories() {
+        let servers = vec![Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("s3.example.org").unwrap(),
+        )];
+        let repositories = vec!["software.eessi.io".to_string(), "dev.eessi.io".to_string()];
+        let names =
+            fleet_repository_names_with_options(&servers, &repositories, &ScrapeOptions::default())
+                .await;
+        assert_eq!(
+            names["s3.example.org"].as_ref().unwrap(),
+            &vec!["dev.eessi.io".to_string(), "software.eessi.io".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fleet_repository_names_reports_errors() {
+        let servers = vec![Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )];
+        let names =
+            fleet_repository_names_with_options(&servers, &[], &ScrapeOptions::default()).await;
+        assert!(names["localhost"].is_err());
+        assert!(fleet_repository_names(&servers).await.is_empty());
+    }
+
+    #[test]
+    fn test_all_servers_meet_version() {
+        let mut old = test_utilities::server("old.example.org", ServerType::Stratum1, vec![]);
+        old.metadata.cvmfs_version = Some(semver::Version::parse("2.10.1-1").unwrap());
+        let mut new = test_utilities::server("new.example.org", ServerType::Stratum1, vec![]);
+        new.metadata.cvmfs_version = Some(semver::Version::parse("2.11.3-1").unwrap());
+        let unknown = test_utilities::server("s3.example.org", ServerType::Stratum1, vec![]);
+
+        let min = parse_version_requirement(">=2.11").unwrap();
+        assert_eq!(old.metadata.meets_minimum_version(&min), Some(false));
+        assert_eq!(new.metadata.meets_minimum_version(&min), Some(true));
+        assert_eq!(unknown.metadata.meets_minimum_version(&min), None);
+
+        let servers = vec![old, new, unknown];
+        let compliance = all_servers_meet_version(&servers, &min)
+            .into_iter()
+            .map(|(server, ok)| (server.hostname.to_str(), ok))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            compliance,
+            vec![
+                ("old.example.org", false),
+                ("new.example.org", true),
+                ("s3.example.org", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fleet_to_csv() {
+        let servers = vec![
+            test_utilities::server(
+                "one.example.org",
+                ServerType::Stratum1,
+                vec![test_utilities::repository(
+                    "software.eessi.io",
+                    1,
+                    0,
+                    None,
+                    None,
+                )],
+            ),
+            test_utilities::server("two.example.org", ServerType::Stratum1, vec![]),
+            test_utilities::server(
+                "three.example.org",
+                ServerType::Stratum0,
+                vec![
+                    test_utilities::repository("software.eessi.io", 2, 0, None, None),
+                    test_utilities::repository("dev.eessi.io", 3, 0, None, None),
+                ],
+            ),
+        ];
+        let csv = fleet_to_csv(&servers);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("one.example.org,stratum1,software.eessi.io,1,"));
+        assert!(lines[3].starts_with("three.example.org,stratum0,dev.eessi.io,3,"));
+        assert!(csv.ends_with('\n'));
+
+        assert_eq!(fleet_to_csv(&[]), format!("{}\n", CSV_HEADER));
+    }
+
+    #[test]
+    fn test_version_distribution() {
+        let mut a = test_utilities::server("a.example.org", ServerType::Stratum1, vec![]);
+        a.metadata.cvmfs_version = Some(semver::Version::parse("2.11.3-1").unwrap());
+        a.metadata.os_pretty_name = Some("Rocky Linux 9".to_string());
+        let mut b = a.clone();
+        b.hostname = Hostname::try_from("b.example.org").unwrap();
+        let mut c = test_utilities::server("c.example.org", ServerType::Stratum1, vec![]);
+        c.metadata.cvmfs_version_raw = Some("2.12.0.0".to_string());
+        let d = test_utilities::server("d.example.org", ServerType::Stratum1, vec![]);
+
+        let distribution = version_distribution(&[a, b, c, d]);
+        let hosts = |hosts: &Vec<Hostname>| hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(distribution.cvmfs_versions.len(), 3);
+        assert_eq!(
+            hosts(&distribution.cvmfs_versions["2.11.3-1"]),
+            vec!["a.example.org", "b.example.org"]
+        );
+        assert_eq!(
+            hosts(&distribution.cvmfs_versions["2.12.0.0"]),
+            vec!["c.example.org"]
+        );
+        assert_eq!(
+            hosts(&distribution.cvmfs_versions[UNKNOWN_VERSION]),
+            vec!["d.example.org"]
+        );
+        assert_eq!(distribution.operating_systems["Rocky Linux 9"].len(), 2);
+        assert_eq!(distribution.operating_systems[UNKNOWN_VERSION].len(), 2);
+    }
+
+    fn failed_result(hostname: &str) -> ScrapedServer {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from(hostname).unwrap(),
+        );
+        ScrapedServer::Failed(
+            server.to_failed_server(ScrapeError::EmptyRepositoryList(hostname.to_string()).into()),
+        )
+    }
+
+    #[test]
+    fn test_partition_scraped_preserves_order() {
+        let results = vec![
+            failed_result("one.example.org"),
+            failed_result("two.example.org"),
+        ];
+        let (populated, failed) = partition_scraped(results);
+        assert!(populated.is_empty());
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].hostname.to_str(), "one.example.org");
+        assert_eq!(failed[1].hostname.to_str(), "two.example.org");
+    }
+
+    #[test]
+    fn test_populated_and_failed_helpers() {
+        let results = vec![
+            failed_result("one.example.org"),
+            ScrapedServer::Populated(test_utilities::server(
+                "two.example.org",
+                ServerType::Stratum1,
+                vec![],
+            )),
+            failed_result("three.example.org"),
+        ];
+
+        let populated: Vec<&str> = populated_iter(&results)
+            .map(|server| server.hostname.to_str())
+            .collect();
+        assert_eq!(populated, vec!["two.example.org"]);
+        let failed: Vec<&str> = failed_iter(&results)
+            .map(|failed| failed.hostname.to_str())
+            .collect();
+        assert_eq!(failed, vec!["one.example.org", "three.example.org"]);
+
+        let mut ok: Vec<PopulatedServer> = Vec::new();
+        ok.extend(results.clone());
+        assert_eq!(ok.len(), 1);
+        let mut errors: Vec<FailedServer> = Vec::new();
+        errors.extend(results.clone());
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(into_populated(results.clone()), ok);
+        let failed = into_failed(results);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[1].hostname.to_str(), "three.example.org");
+    }
+
+    #[test]
+    fn test_group_by_server_type() {
+        let populated = |hostname: &str, server_type: ServerType| {
+            ScrapedServer::Populated(test_utilities::server(hostname, server_type, vec![]))
+        };
+        let results = vec![
+            populated("s1-b.example.org", ServerType::Stratum1),
+            failed_result("s1-c.example.org"),
+            populated("s0.example.org", ServerType::Stratum0),
+            populated("s1-a.example.org", ServerType::Stratum1),
+            failed_result("s1-a.example.org"),
+        ];
+        let grouped = group_by_server_type(results);
+
+        let hostnames = |servers: &[PopulatedServer]| {
+            servers
+                .iter()
+                .map(|server| server.hostname.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            grouped.populated.keys().collect::<Vec<_>>(),
+            vec![&ServerType::Stratum0, &ServerType::Stratum1]
+        );
+        assert_eq!(
+            hostnames(&grouped.populated[&ServerType::Stratum1]),
+            vec!["s1-a.example.org", "s1-b.example.org"]
+        );
+        assert!(!grouped.populated.contains_key(&ServerType::SyncServer));
+
+        let failed = &grouped.failed[&ServerType::Stratum1];
+        assert_eq!(failed[0].hostname.to_str(), "s1-a.example.org");
+        assert_eq!(failed[1].hostname.to_str(), "s1-c.example.org");
+        assert_eq!(grouped.failed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_discover_replicas() {
+        let client = reqwest::Client::new();
+        let options = ScrapeOptions::default();
+        let mut stratum0 = test_utilities::server("s0.example.org", ServerType::Stratum0, vec![]);
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            stratum0.hostname.clone(),
+        );
+        stratum0.metadata.recommended_stratum1_list = vec![
+            "http://s1.example.org/cvmfs/@fqrn@".to_string(),
+            "https://s2.example.org:8443/cvmfs/@fqrn@".to_string(),
+        ];
+        let replicas = discover_replicas(&stratum0, &server, &client, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            replicas
+                .iter()
+                .map(|server| (server.hostname.to_str(), server.server_type, server.port))
+                .collect::<Vec<_>>(),
+            vec![
+                ("s1.example.org", ServerType::Stratum1, None),
+                ("s2.example.org", ServerType::Stratum1, Some(8443)),
+            ]
+        );
+
+        // A fetched repositories.json without recommendations gives no replicas.
+        stratum0.metadata.recommended_stratum1_list.clear();
+        stratum0.repositories_json_timing = Some(RepositoriesJsonTiming {
+            duration: std::time::Duration::from_millis(10),
+            bytes: Some(100),
+        });
+        assert!(discover_replicas(&stratum0, &server, &client, &options)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let stratum1 = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![]);
+        assert!(matches!(
+            discover_replicas(&stratum1, &server, &client, &options).await,
+            Err(ScrapeError::ServerTypeMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_discover_replicas_refetches_from_the_server() {
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/mirror/cvmfs/custom/info/repositories.json") {
+                ok_response(
+                    r#"{"schema": 1, "replicas": [], "repositories": [],
+                        "recommended_stratum1_list": ["http://s1.example.org/cvmfs/@fqrn@"]}"#,
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port)
+        .with_base_path("mirror");
+        let options = ScrapeOptions {
+            info_path: "custom/info".to_string(),
+            ..Default::default()
+        };
+        // A scrape without repositories.json, e.g. a Stratum0 with its repositories given.
+        let stratum0 = PopulatedServer {
+            port: Some(port),
+            base_path: Some("mirror".to_string()),
+            ..test_utilities::server("localhost", ServerType::Stratum0, vec![])
+        };
+        assert!(stratum0.repositories_json_timing.is_none());
+
+        let replicas = discover_replicas(&stratum0, &server, &reqwest::Client::new(), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            replicas
+                .iter()
+                .map(|server| server.hostname.to_str())
+                .collect::<Vec<_>>(),
+            vec!["s1.example.org"]
+        );
+    }
+
+    #[parameterized(
+        cvmfs = { "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished", Some("software.eessi.io") },
+        s3 = { "http://bucket.example.org/software.eessi.io/.cvmfspublished", Some("software.eessi.io") },
+        file = { "file:///srv/cvmfs/dev.eessi.io/.cvmfspublished", Some("dev.eessi.io") },
+        not_a_name = { "http://cvmfs.example.org/cvmfs/.cvmfspublished", None },
+        no_path = { "http://cvmfs.example.org", None },
+        invalid = { "not a url", None },
+    )]
+    fn test_repository_name_from_url(url: &str, expected: Option<&str>) {
+        assert_eq!(
+            repository_name_from_url(url)
+                .as_ref()
+                .map(|name| name.to_str()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,93 @@
+/// The health of a repository, see `PopulatedRepositoryOrReplica::health_status`.
+///
+/// - Healthy: No issues were found.
+/// - Warning: Issues that need looking into, but not immediately.
+/// - Critical: Issues that need immediate attention. Any warnings are listed after them.
+///
+/// The issues are human-readable descriptions, e.g. for alerts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoHealth {
+    Healthy,
+    Warning(Vec<String>),
+    Critical(Vec<String>),
+}
+
+impl RepoHealth {
+    /// The health given the critical issues and the warnings found.
+    pub(crate) fn from_issues(critical: Vec<String>, warnings: Vec<String>) -> Self {
+        match (critical.is_empty(), warnings.is_empty()) {
+            (true, true) => RepoHealth::Healthy,
+            (true, false) => RepoHealth::Warning(warnings),
+            (false, _) => RepoHealth::Critical(critical.into_iter().chain(warnings).collect()),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, RepoHealth::Healthy)
+    }
+
+    pub fn is_critical(&self) -> bool {
+        matches!(self, RepoHealth::Critical(_))
+    }
+
+    /// The issues found, empty if healthy.
+    pub fn issues(&self) -> &[String] {
+        match self {
+            RepoHealth::Healthy => &[],
+            RepoHealth::Warning(issues) | RepoHealth::Critical(issues) => issues,
+        }
+    }
+}
+
+/// The thresholds used by `PopulatedRepositoryOrReplica::health_status`.
+///
+/// Fields:
+///
+/// - stale_warning: The age of the last snapshot (see `snapshot_age`) above which a warning is
+///   given. Defaults to 4 hours.
+/// - stale_critical: The age of the last snapshot above which the repository is critical.
+///   Defaults to 24 hours.
+/// - max_publish_duration: The publish duration (see `publish_duration`) above which a warning is
+///   given. Defaults to 1 hour.
+/// - max_gc_age: The time without garbage collection after which stalled garbage collection (see
+///   `gc_stalled`) gives a warning. Defaults to 7 days.
+/// - whitelist_warning: The time left before the whitelist expires (see `whitelist_remaining`)
+///   below which a warning is given. An expired whitelist is always critical. Defaults to 3 days.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthConfig {
+    pub stale_warning: chrono::Duration,
+    pub stale_critical: chrono::Duration,
+    pub max_publish_duration: chrono::Duration,
+    pub max_gc_age: chrono::Duration,
+    pub whitelist_warning: chrono::Duration,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            stale_warning: chrono::Duration::hours(4),
+            stale_critical: chrono::Duration::hours(24),
+            max_publish_duration: chrono::Duration::hours(1),
+            max_gc_age: chrono::Duration::days(7),
+            whitelist_warning: chrono::Duration::days(3),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_health_from_issues() {
+        assert_eq!(RepoHealth::from_issues(vec![], vec![]), RepoHealth::Healthy);
+        assert_eq!(
+            RepoHealth::from_issues(vec![], vec!["slow".to_string()]),
+            RepoHealth::Warning(vec!["slow".to_string()])
+        );
+        let critical = RepoHealth::from_issues(vec!["stale".to_string()], vec!["slow".to_string()]);
+        assert!(critical.is_critical());
+        assert_eq!(critical.issues(), ["stale", "slow"]);
+        assert!(RepoHealth::Healthy.issues().is_empty());
+    }
+}
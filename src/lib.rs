@@ -70,20 +70,75 @@
 //! }
 //! ```
 
+// The tests predate the lints below and are kept as written.
+#![cfg_attr(
+    test,
+    allow(
+        clippy::single_component_path_imports,
+        clippy::unnecessary_literal_unwrap,
+        clippy::useless_vec
+    )
+)]
+
+mod cache;
 mod constants;
 mod errors;
+mod events;
+mod fleet;
+mod health;
+mod metrics;
 mod models;
+mod options;
+mod persistence;
+mod redirects;
+mod resolver;
 mod scraper;
+mod telemetry;
+#[cfg(test)]
+mod test_utilities;
 mod utilities;
+mod warnings;
 
-pub use constants::DEFAULT_GEOAPI_SERVERS;
-pub use errors::{CVMFSScraperError, HostnameError, ManifestError, ScrapeError};
+pub use cache::{RepositoryCache, DEFAULT_CACHE_MAX_ENTRIES};
+pub use constants::{
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_GEOAPI_SERVERS, DEFAULT_INFO_PATH, DEFAULT_MAX_REDIRECTS,
+    DEFAULT_REPOSITORY_CONCURRENCY, DEFAULT_REQUEST_TIMEOUT, FLEET_FILE_SCHEMA_VERSION,
+    SUPPORTED_REPOSITORIES_JSON_SCHEMA,
+};
+pub use errors::{
+    CVMFSScraperError, DocumentKind, HostnameError, ManifestError, ReadError, RepositoryNameError,
+    ScrapeError,
+};
+pub use events::ScrapeEvent;
+pub use fleet::{
+    all_servers_meet_version, discover_replicas, failed_iter, fleet_repository_names,
+    fleet_repository_names_with_options, fleet_to_csv, group_by_server_type, into_failed,
+    into_populated, orphan_repositories, partition_scraped, pending_snapshots, populated_iter,
+    scrape_url, scrape_url_with_name, stalled_gc_repositories, version_distribution, Fleet,
+    FleetDiff, GroupedResults, OperationalCheck, OrphanReport, OrphanRepository, PendingSnapshot,
+    RevisionChange, ServerDiff, ServerStatus, VersionDistribution, UNKNOWN_VERSION,
+};
+pub use health::{HealthConfig, RepoHealth};
+pub use metrics::ScrapeMetrics;
 pub use models::{
-    FailedServer, GeoapiServerQuery, Hostname, Manifest, MaybeRfc2822DateTime,
-    PopulatedRepositoryOrReplica, PopulatedServer, ScrapedServer, Server, ServerBackendType,
-    ServerMetadata, ServerType,
+    BackendDetection, CacheValidators, DataEq, FailedServer, GeoapiServerQuery, HexString,
+    Hostname, Manifest, ManifestBuilder, MaybeRfc2822DateTime, MetaJSON, PlannedRequest,
+    PlannedRequestKind, PopulatedRepositoryOrReplica, PopulatedServer, RepositoriesJSON,
+    RepositoriesJSONRepo, RepositoriesJsonTiming, RepositoryName, RepositoryOrReplica,
+    RepositoryScrapes, Scheme, ScrapedServer, Server, ServerBackendType, ServerIdentity,
+    ServerMetadata, ServerType, StatusJSON, Tag, Whitelist, REVISION_TAG_HASH_LENGTH,
+};
+pub use options::{
+    cvmfs_proxy_chain, cvmfs_proxy_url, EmptyRepositoriesPolicy, IpPreference, RedirectPolicy,
+    RepositoryOrder, ScrapeMode, ScrapeOptions, VersionParsing,
 };
-pub use scraper::{Scraper, ScraperCommon};
+pub use persistence::{read_fleet_from_file, write_fleet_to_file};
+pub use redirects::RedirectHop;
+pub use scraper::{Scraper, ScraperBuilder, ScraperCommon};
+#[cfg(feature = "opentelemetry")]
+pub use telemetry::install_otlp_exporter;
+pub use utilities::parse_version_requirement;
+pub use warnings::ScrapeWarning;
 
 #[cfg(test)]
 mod tests {
@@ -299,6 +354,9 @@ mod tests {
         assert!(popserver.has_repository("software.eessi.io"));
         assert!(popserver.has_repository("dev.eessi.io"));
         assert!(!popserver.has_repository("riscv.eessi.io"));
+        assert!(popserver
+            .discovered_but_skipped
+            .contains(&"riscv.eessi.io".to_string()));
     }
 
     #[tokio::test]
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Timing of the parts of a scrape of a server, see `Server::scrape_with_metrics`.
+///
+/// Fields:
+///
+/// - total_duration: The duration of the whole scrape.
+/// - repos_json_fetch_duration: The duration of fetching repositories.json (including backend
+///   detection), if it was fetched.
+/// - meta_json_fetch_duration: The duration of fetching meta.json, if it was fetched.
+/// - per_repo_durations: The duration of scraping each repository (its manifest and status), in
+///   the order of the repositories. Repositories taken from the cache are not included.
+/// - slowest_repo: The repository that took the longest to scrape, if any were scraped.
+///
+/// A failed scrape has the timings of the parts that were done before it failed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrapeMetrics {
+    pub total_duration: Duration,
+    pub repos_json_fetch_duration: Option<Duration>,
+    pub meta_json_fetch_duration: Option<Duration>,
+    pub per_repo_durations: Vec<(String, Duration)>,
+    pub slowest_repo: Option<(String, Duration)>,
+}
+
+/// The timings recorded during a scrape, shared by the parts of the scrape.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MetricsLog(Arc<Mutex<ScrapeMetrics>>);
+
+impl MetricsLog {
+    fn update(&self, f: impl FnOnce(&mut ScrapeMetrics)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub(crate) fn record_repos_json(&self, duration: Duration) {
+        self.update(|metrics| metrics.repos_json_fetch_duration = Some(duration));
+    }
+
+    pub(crate) fn record_meta_json(&self, duration: Duration) {
+        self.update(|metrics| metrics.meta_json_fetch_duration = Some(duration));
+    }
+
+    pub(crate) fn record_repository(&self, name: &str, duration: Duration) {
+        self.update(|metrics| {
+            metrics
+                .per_repo_durations
+                .push((name.to_string(), duration))
+        });
+    }
+
+    /// The metrics recorded so far, with the total duration of the scrape.
+    pub(crate) fn finish(&self, total_duration: Duration) -> ScrapeMetrics {
+        let mut metrics = self.0.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        metrics.total_duration = total_duration;
+        // The first of several equally slow repositories is the slowest.
+        metrics.slowest_repo = metrics
+            .per_repo_durations
+            .iter()
+            .rev()
+            .max_by_key(|(_, duration)| *duration)
+            .cloned();
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_log_finish() {
+        let log = MetricsLog::default();
+        assert_eq!(log.finish(Duration::from_secs(1)).slowest_repo, None);
+
+        log.record_meta_json(Duration::from_millis(5));
+        log.record_repository("a.example.org", Duration::from_millis(10));
+        log.record_repository("b.example.org", Duration::from_millis(30));
+        log.record_repository("c.example.org", Duration::from_millis(30));
+        let metrics = log.finish(Duration::from_secs(2));
+        assert_eq!(metrics.total_duration, Duration::from_secs(2));
+        assert_eq!(metrics.repos_json_fetch_duration, None);
+        assert_eq!(
+            metrics.meta_json_fetch_duration,
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(metrics.per_repo_durations.len(), 3);
+        assert_eq!(
+            metrics.slowest_repo,
+            Some(("b.example.org".to_string(), Duration::from_millis(30)))
+        );
+    }
+}
@@ -1,5 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use crate::errors::ManifestError;
 use crate::models::generic::HexString;
@@ -15,7 +16,7 @@ use crate::utilities::{parse_boolean_field, parse_hex_field, parse_number_field}
 /// - x: Cryptographic hash of the signing certificate
 /// - g: true if the repository is garbage-collectable
 /// - h: Cryptographic hash of the repository’s named tag history database
-/// - t: Unix timestamp of this particular revision
+/// - t: Unix timestamp of this particular revision, see `publish_timestamp`
 /// - d: Time To Live (TTL) of the root catalog
 /// - s: Revision number of this published revision
 /// - n: The full name of the manifested repository
@@ -23,14 +24,18 @@ use crate::utilities::{parse_boolean_field, parse_hex_field, parse_number_field}
 /// - y: Cryptographic hash of the reflog checksum
 /// - l: currently unused (reserved for micro catalogs)
 /// - signature: In order to provide authoritative information about a repository publisher, the
-///              repository manifest is signed by an X.509 certificate together with its private key.
-///              This field is not validated by this library.
+///   repository manifest is signed by an X.509 certificate together with its private key.
+///   This field is not validated by this library.
 ///
 /// Note that the field names are lowercase, but the field names in the manifest itself are uppercase.
 ///
+/// Which fields were present in the parsed manifest is kept, see `has_field`. Manifests vary by
+/// CVMFS version and configuration, so this helps tell old and new formats apart. This is not
+/// part of the data of the manifest, so it is neither compared nor serialized.
+///
 /// See https://cvmfs.readthedocs.io/en/stable/cpt-details.html#repository-manifest-cvmfspublished for
 /// more information.
-#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct Manifest {
     pub c: HexString,
     pub b: i64,
@@ -41,12 +46,55 @@ pub struct Manifest {
     pub h: HexString,
     pub t: i64,
     pub d: i32,
-    pub s: i32,
+    pub s: u64,
     pub n: String,
     pub m: HexString,
     pub y: HexString,
     pub l: String, // Currently unused
     pub signature: String,
+    /// The keys present in the manifest, see `has_field`.
+    #[serde(skip)]
+    fields: BTreeSet<char>,
+}
+
+/// Compares every field but the keys present, see `Manifest::has_field`.
+impl PartialEq for Manifest {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructured in full, so that new fields are not forgotten here.
+        let Manifest {
+            c,
+            b,
+            a,
+            r,
+            x,
+            g,
+            h,
+            t,
+            d,
+            s,
+            n,
+            m,
+            y,
+            l,
+            signature,
+            fields: _,
+        } = self;
+        *c == other.c
+            && *b == other.b
+            && *a == other.a
+            && *r == other.r
+            && *x == other.x
+            && *g == other.g
+            && *h == other.h
+            && *t == other.t
+            && *d == other.d
+            && *s == other.s
+            && *n == other.n
+            && *m == other.m
+            && *y == other.y
+            && *l == other.l
+            && *signature == other.signature
+    }
 }
 
 /// Debug implementation for Manifest
@@ -70,6 +118,7 @@ impl std::fmt::Debug for Manifest {
             .field("m", &self.m)
             .field("y", &self.y)
             .field("l", &self.l)
+            .field("fields", &self.fields)
             .finish()
     }
 }
@@ -78,23 +127,7 @@ impl std::str::FromStr for Manifest {
     type Err = ManifestError;
 
     fn from_str(content: &str) -> Result<Self, Self::Err> {
-        let mut data: HashMap<char, String> = HashMap::new();
-        let mut signature: String = String::new();
-        let mut is_signature = false;
-
-        for line in content.lines() {
-            if line == "--" {
-                is_signature = true;
-                continue;
-            }
-            if is_signature {
-                signature.push_str(line);
-            } else {
-                let key = line.chars().next().unwrap();
-                let value = &line[1..];
-                data.insert(key, value.to_string());
-            }
-        }
+        let (data, signature, _) = split_manifest(content);
 
         let manifest = Manifest {
             c: parse_hex_field(&data, 'C')?,
@@ -115,13 +148,160 @@ impl std::str::FromStr for Manifest {
             y: parse_hex_field(&data, 'Y')?,
             l: data.get(&'L').cloned().unwrap_or_default(),
             signature,
+            fields: data.keys().copied().collect(),
         };
 
         Ok(manifest)
     }
 }
 
+/// Split the content of a .cvmfspublished into its fields and the signature block.
+///
+/// Returns the fields keyed by their letter, the signature, and whether the signature separator
+/// (`--`) was present.
+fn split_manifest(content: &str) -> (HashMap<char, String>, String, bool) {
+    let mut data: HashMap<char, String> = HashMap::new();
+    let mut signature: String = String::new();
+    let mut is_signature = false;
+
+    // Tolerate quirks seen on real servers: a leading UTF-8 BOM, CRLF (or mixed) line
+    // endings, and trailing whitespace or blank lines. lines() handles the CRLF endings.
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    for line in content.lines() {
+        if is_signature {
+            signature.push_str(line);
+            continue;
+        }
+        let line = line.trim_end();
+        if line == "--" {
+            is_signature = true;
+            continue;
+        }
+        let mut chars = line.chars();
+        if let Some(key) = chars.next() {
+            data.insert(key, chars.as_str().to_string());
+        }
+    }
+    (data, signature, is_signature)
+}
+
 impl Manifest {
+    /// Parse and validate the content of a .cvmfspublished, reporting every problem found.
+    ///
+    /// This is a stricter alternative to `from_str`, intended for validating generated manifests
+    /// (e.g. in pre-publish hooks) without a server. Rather than stopping at the first problem,
+    /// all of them are returned:
+    ///
+    /// - Every field is checked for presence and format (numbers, booleans, and hashes), so a
+    ///   negative revision or a malformed hash are reported per field.
+    /// - The repository name must not be empty.
+    /// - The signature block must be present (after a `--` line) and not be empty.
+    ///
+    /// The signature itself is not verified.
+    pub fn validate(content: &str) -> Result<Manifest, Vec<ManifestError>> {
+        fn check<T>(
+            errors: &mut Vec<ManifestError>,
+            result: Result<T, ManifestError>,
+        ) -> Option<T> {
+            result.map_err(|error| errors.push(error)).ok()
+        }
+
+        let (data, signature, has_separator) = split_manifest(content);
+        let mut errors = vec![];
+        let c = check(&mut errors, parse_hex_field(&data, 'C'));
+        let b = check(&mut errors, parse_number_field(&data, 'B'));
+        let a = check(&mut errors, parse_boolean_field(&data, 'A'));
+        let r = check(&mut errors, parse_hex_field(&data, 'R'));
+        let x = check(&mut errors, parse_hex_field(&data, 'X'));
+        let g = check(&mut errors, parse_boolean_field(&data, 'G'));
+        let h = check(&mut errors, parse_hex_field(&data, 'H'));
+        let t = check(&mut errors, parse_number_field(&data, 'T'));
+        let d = check(&mut errors, parse_number_field(&data, 'D'));
+        let s = check(&mut errors, parse_number_field(&data, 'S'));
+        let n = check(
+            &mut errors,
+            match data.get(&'N') {
+                None => Err(ManifestError::MissingField('N')),
+                Some(name) if name.is_empty() => Err(ManifestError::ParseError(
+                    'N',
+                    "empty repository name".to_string(),
+                )),
+                Some(name) => Ok(name.clone()),
+            },
+        );
+        let m = check(&mut errors, parse_hex_field(&data, 'M'));
+        let y = check(&mut errors, parse_hex_field(&data, 'Y'));
+        if !has_separator {
+            errors.push(ManifestError::InvalidSignature(
+                "missing signature block (no -- separator)".to_string(),
+            ));
+        } else if signature.trim().is_empty() {
+            errors.push(ManifestError::InvalidSignature(
+                "truncated signature block".to_string(),
+            ));
+        }
+
+        match (c, b, a, r, x, g, h, t, d, s, n, m, y) {
+            (
+                Some(c),
+                Some(b),
+                Some(a),
+                Some(r),
+                Some(x),
+                Some(g),
+                Some(h),
+                Some(t),
+                Some(d),
+                Some(s),
+                Some(n),
+                Some(m),
+                Some(y),
+            ) if errors.is_empty() => Ok(Manifest {
+                c,
+                b,
+                a,
+                r,
+                x,
+                g,
+                h,
+                t,
+                d,
+                s,
+                n,
+                m,
+                y,
+                l: data.get(&'L').cloned().unwrap_or_default(),
+                signature,
+                fields: data.keys().copied().collect(),
+            }),
+            _ => Err(errors),
+        }
+    }
+
+    /// The publication time of this revision (T), as a UTC timestamp.
+    ///
+    /// Returns None if the timestamp is unset (zero or negative) or out of range.
+    pub fn publish_timestamp(&self) -> Option<DateTime<Utc>> {
+        match self.t {
+            t if t > 0 => DateTime::from_timestamp(t, 0),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the given key (e.g. 'G') was present in the manifest.
+    ///
+    /// Keys are case sensitive and uppercase, as in the manifest itself. Unknown keys are
+    /// included. A manifest created by `ManifestBuilder` has every key it sets, i.e. all but L.
+    /// The keys are not serialized, so manifests deserialized from JSON have no keys.
+    pub fn has_field(&self, key: char) -> bool {
+        self.fields.contains(&key)
+    }
+
+    /// The number of keys present in the manifest, see `has_field`.
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
     pub fn output(&self) {
         println!("  Manifest for repository: {}", self.n);
         println!("    Root catalog hash: {}", self.c);
@@ -131,7 +311,10 @@ impl Manifest {
         println!("    Signing certificate hash: {}", self.x);
         println!("    Garbage-collectable: {}", self.g);
         println!("    Tag history hash: {}", self.h);
-        println!("    Revision timestamp: {}", self.t);
+        match self.publish_timestamp() {
+            Some(timestamp) => println!("    Revision timestamp: {} ({})", self.t, timestamp),
+            None => println!("    Revision timestamp: {}", self.t),
+        }
         println!("    Root catalog TTL: {}", self.d);
         println!("    Revision number: {}", self.s);
         println!("    Metadata hash: {}", self.m);
@@ -139,3 +322,513 @@ impl Manifest {
         // println!("  Signature: {}", self.signature);
     }
 }
+
+impl Manifest {
+    /// Create a builder for a manifest, see `ManifestBuilder`.
+    pub fn builder() -> ManifestBuilder {
+        ManifestBuilder::default()
+    }
+}
+
+/// The default root path hash, the MD5 hash of the empty string.
+const DEFAULT_ROOT_PATH_HASH: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+/// The default TTL of the root catalog, in seconds.
+const DEFAULT_ROOT_CATALOG_TTL: i32 = 240;
+
+/// The keys of a manifest created by `ManifestBuilder`, every field but the unused L.
+const BUILT_FIELDS: &[char] = &[
+    'C', 'B', 'A', 'R', 'X', 'G', 'H', 'T', 'D', 'S', 'N', 'M', 'Y',
+];
+
+/// A builder for `Manifest` objects.
+///
+/// This is mostly useful for tests, where it avoids having to write out a `.cvmfspublished`
+/// file. The repository name, root catalog hash, revision, and timestamp are required; the
+/// remaining fields default to empty hashes, `false`, zero, or the CVMFS defaults (the root path
+/// hash and a root catalog TTL of 240 seconds).
+///
+/// ### Example
+///
+/// ```rust
+/// use chrono::{TimeZone, Utc};
+/// use cvmfs_server_scraper::{HexString, Manifest};
+///
+/// let manifest = Manifest::builder()
+///     .with_name("software.eessi.io")
+///     .with_root_catalog_hash(HexString::new("600230b0ba7620426f2e898f1e1f43c5466efe59").unwrap())
+///     .with_revision(42)
+///     .with_timestamp(Utc.timestamp_opt(1718991602, 0).unwrap())
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(manifest.s, 42);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManifestBuilder {
+    c: Option<HexString>,
+    b: i64,
+    a: bool,
+    r: Option<HexString>,
+    x: Option<HexString>,
+    g: bool,
+    h: Option<HexString>,
+    t: Option<i64>,
+    d: Option<i32>,
+    s: Option<u64>,
+    n: Option<String>,
+    m: Option<HexString>,
+    y: Option<HexString>,
+    signature: String,
+}
+
+impl ManifestBuilder {
+    /// Set the hash of the root catalog (C).
+    pub fn with_root_catalog_hash(mut self, hash: HexString) -> Self {
+        self.c = Some(hash);
+        self
+    }
+
+    /// Set the size of the root catalog in bytes (B).
+    pub fn with_root_catalog_size(mut self, size: i64) -> Self {
+        self.b = size;
+        self
+    }
+
+    /// Set whether the catalog should be fetched under its alternative name (A).
+    pub fn with_alternative_name(mut self, alternative_name: bool) -> Self {
+        self.a = alternative_name;
+        self
+    }
+
+    /// Set the hash of the root path (R).
+    pub fn with_root_path_hash(mut self, hash: HexString) -> Self {
+        self.r = Some(hash);
+        self
+    }
+
+    /// Set the hash of the signing certificate (X).
+    pub fn with_certificate_hash(mut self, hash: HexString) -> Self {
+        self.x = Some(hash);
+        self
+    }
+
+    /// Set whether the repository is garbage-collectable (G).
+    pub fn with_garbage_collectable(mut self, garbage_collectable: bool) -> Self {
+        self.g = garbage_collectable;
+        self
+    }
+
+    /// Set the hash of the tag history database (H).
+    pub fn with_tag_history_hash(mut self, hash: HexString) -> Self {
+        self.h = Some(hash);
+        self
+    }
+
+    /// Set the timestamp of the revision (T).
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.t = Some(timestamp.timestamp());
+        self
+    }
+
+    /// Set the TTL of the root catalog in seconds (D).
+    pub fn with_ttl(mut self, ttl: i32) -> Self {
+        self.d = Some(ttl);
+        self
+    }
+
+    /// Set the revision number (S).
+    pub fn with_revision(mut self, revision: u64) -> Self {
+        self.s = Some(revision);
+        self
+    }
+
+    /// Set the full name of the repository (N).
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.n = Some(name.into());
+        self
+    }
+
+    /// Set the hash of the repository JSON metadata (M).
+    pub fn with_metadata_hash(mut self, hash: HexString) -> Self {
+        self.m = Some(hash);
+        self
+    }
+
+    /// Set the hash of the reflog checksum (Y).
+    pub fn with_reflog_hash(mut self, hash: HexString) -> Self {
+        self.y = Some(hash);
+        self
+    }
+
+    /// Set the signature.
+    pub fn with_signature<S: Into<String>>(mut self, signature: S) -> Self {
+        self.signature = signature.into();
+        self
+    }
+
+    /// Build the manifest.
+    ///
+    /// Returns `ManifestError::MissingField` if the name (N), root catalog hash (C), revision (S),
+    /// or timestamp (T) has not been set.
+    pub fn build(self) -> Result<Manifest, ManifestError> {
+        let empty_hash = || HexString::new("").unwrap();
+        Ok(Manifest {
+            c: self.c.ok_or(ManifestError::MissingField('C'))?,
+            b: self.b,
+            a: self.a,
+            r: self
+                .r
+                .unwrap_or_else(|| HexString::new(DEFAULT_ROOT_PATH_HASH).unwrap()),
+            x: self.x.unwrap_or_else(empty_hash),
+            g: self.g,
+            h: self.h.unwrap_or_else(empty_hash),
+            t: self.t.ok_or(ManifestError::MissingField('T'))?,
+            d: self.d.unwrap_or(DEFAULT_ROOT_CATALOG_TTL),
+            s: self.s.ok_or(ManifestError::MissingField('S'))?,
+            n: self.n.ok_or(ManifestError::MissingField('N'))?,
+            m: self.m.unwrap_or_else(empty_hash),
+            y: self.y.unwrap_or_else(empty_hash),
+            l: String::new(),
+            signature: self.signature,
+            fields: BUILT_FIELDS.iter().copied().collect(),
+        })
+    }
+}
+
+/// Build a manifest from a JSON object, e.g. as received from a proxy or cache.
+///
+/// The keys are descriptive names for the manifest fields, matching the `ManifestBuilder`
+/// setters:
+///
+/// - root_hash (C), root_catalog_size (B), alternative_name (A), root_path_hash (R),
+///   certificate_hash (X), garbage_collectable (G), tag_history_hash (H), timestamp (T, as a Unix
+///   timestamp), ttl (D), revision (S), name (N), metadata_hash (M), reflog_hash (Y), signature.
+///
+/// name, root_hash, revision, and timestamp are required, the other fields default as in
+/// `ManifestBuilder::build`. Any missing required field, or a field of the wrong type, results
+/// in a `ManifestError::JsonConversionError`.
+impl TryFrom<&serde_json::Value> for Manifest {
+    type Error = ManifestError;
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        let object = value.as_object().ok_or_else(|| {
+            ManifestError::JsonConversionError("expected a JSON object".to_string())
+        })?;
+        let invalid = |key: &str, expected: &str| {
+            ManifestError::JsonConversionError(format!("{} must be {}", key, expected))
+        };
+        let string = |key: &str| -> Result<Option<&str>, ManifestError> {
+            object
+                .get(key)
+                .map(|v| v.as_str().ok_or_else(|| invalid(key, "a string")))
+                .transpose()
+        };
+        let hash = |key: &str| -> Result<Option<HexString>, ManifestError> {
+            string(key)?
+                .map(|v| {
+                    HexString::new(v)
+                        .map_err(|e| ManifestError::JsonConversionError(format!("{}: {}", key, e)))
+                })
+                .transpose()
+        };
+        let integer = |key: &str| -> Result<Option<i64>, ManifestError> {
+            object
+                .get(key)
+                .map(|v| v.as_i64().ok_or_else(|| invalid(key, "an integer")))
+                .transpose()
+        };
+        let boolean = |key: &str| -> Result<Option<bool>, ManifestError> {
+            object
+                .get(key)
+                .map(|v| v.as_bool().ok_or_else(|| invalid(key, "a boolean")))
+                .transpose()
+        };
+        let required = |key: &str| {
+            ManifestError::JsonConversionError(format!("missing required field {}", key))
+        };
+
+        let revision = object
+            .get("revision")
+            .ok_or_else(|| required("revision"))?
+            .as_u64()
+            .ok_or_else(|| invalid("revision", "a non-negative integer"))?;
+        let timestamp = integer("timestamp")?.ok_or_else(|| required("timestamp"))?;
+        let timestamp = DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| invalid("timestamp", "a valid Unix timestamp"))?;
+
+        let mut builder = Manifest::builder()
+            .with_name(string("name")?.ok_or_else(|| required("name"))?)
+            .with_root_catalog_hash(hash("root_hash")?.ok_or_else(|| required("root_hash"))?)
+            .with_revision(revision)
+            .with_timestamp(timestamp);
+        if let Some(size) = integer("root_catalog_size")? {
+            builder = builder.with_root_catalog_size(size);
+        }
+        if let Some(alternative_name) = boolean("alternative_name")? {
+            builder = builder.with_alternative_name(alternative_name);
+        }
+        if let Some(hash) = hash("root_path_hash")? {
+            builder = builder.with_root_path_hash(hash);
+        }
+        if let Some(hash) = hash("certificate_hash")? {
+            builder = builder.with_certificate_hash(hash);
+        }
+        if let Some(garbage_collectable) = boolean("garbage_collectable")? {
+            builder = builder.with_garbage_collectable(garbage_collectable);
+        }
+        if let Some(hash) = hash("tag_history_hash")? {
+            builder = builder.with_tag_history_hash(hash);
+        }
+        if let Some(ttl) = integer("ttl")? {
+            let ttl = i32::try_from(ttl).map_err(|_| invalid("ttl", "a 32-bit integer"))?;
+            builder = builder.with_ttl(ttl);
+        }
+        if let Some(hash) = hash("metadata_hash")? {
+            builder = builder.with_metadata_hash(hash);
+        }
+        if let Some(hash) = hash("reflog_hash")? {
+            builder = builder.with_reflog_hash(hash);
+        }
+        if let Some(signature) = string("signature")? {
+            builder = builder.with_signature(signature);
+        }
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use yare::parameterized;
+
+    fn hash() -> HexString {
+        HexString::new("600230b0ba7620426f2e898f1e1f43c5466efe59").unwrap()
+    }
+
+    fn timestamp() -> DateTime<Utc> {
+        Utc.timestamp_opt(1718991602, 0).unwrap()
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let manifest = Manifest::builder()
+            .with_name("software.eessi.io")
+            .with_root_catalog_hash(hash())
+            .with_revision(7)
+            .with_timestamp(timestamp())
+            .build()
+            .unwrap();
+        assert_eq!(manifest.n, "software.eessi.io");
+        assert_eq!(manifest.s, 7);
+        assert_eq!(manifest.t, 1718991602);
+        assert_eq!(manifest.publish_timestamp(), Some(timestamp()));
+        assert_eq!(manifest.d, DEFAULT_ROOT_CATALOG_TTL);
+        assert_eq!(manifest.r.to_string(), DEFAULT_ROOT_PATH_HASH);
+        assert!(!manifest.g);
+    }
+
+    #[parameterized(
+        zero = { "0", None },
+        negative = { "-1", None },
+        out_of_range = { "9223372036854775807", None },
+        valid = { "1718991602", Some(1718991602) },
+    )]
+    fn test_publish_timestamp(t: &str, expected: Option<i64>) {
+        let mut manifest = Manifest::builder()
+            .with_name("software.eessi.io")
+            .with_root_catalog_hash(hash())
+            .with_revision(7)
+            .with_timestamp(timestamp())
+            .build()
+            .unwrap();
+        manifest.t = t.parse().unwrap();
+        assert_eq!(
+            manifest.publish_timestamp(),
+            expected.map(|t| Utc.timestamp_opt(t, 0).unwrap())
+        );
+    }
+
+    #[parameterized(
+        missing_name = { 'N' },
+        missing_root_catalog_hash = { 'C' },
+        missing_revision = { 'S' },
+        missing_timestamp = { 'T' },
+    )]
+    fn test_builder_missing_required_field(missing: char) {
+        let mut builder = Manifest::builder();
+        if missing != 'N' {
+            builder = builder.with_name("software.eessi.io");
+        }
+        if missing != 'C' {
+            builder = builder.with_root_catalog_hash(hash());
+        }
+        if missing != 'S' {
+            builder = builder.with_revision(1);
+        }
+        if missing != 'T' {
+            builder = builder.with_timestamp(timestamp());
+        }
+        match builder.build() {
+            Err(ManifestError::MissingField(field)) => assert_eq!(field, missing),
+            other => panic!("Expected missing field {}, got {:?}", missing, other),
+        }
+    }
+
+    fn manifest_with_revision(revision: &str) -> String {
+        format!(
+            "C600230b0ba7620426f2e898f1e1f43c5466efe59\n\
+             B4096\n\
+             Ano\n\
+             Rd41d8cd98f00b204e9800998ecf8427e\n\
+             Xd2ff3e2b1d6a3b2e0b5f7f4e1c8a9d0e3b4c5d6e\n\
+             Gyes\n\
+             Hc1a43b5e7e0c5a8e4d2a1b0f9e8d7c6b5a4f3e2d\n\
+             T1718991602\n\
+             D240\n\
+             S{}\n\
+             Nsoftware.eessi.io\n\
+             Mb2c4d6e8f0a1b3c5d7e9f1a3b5c7d9e1f3a5b7c9\n\
+             Ya1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0\n\
+             --\n\
+             signature",
+            revision
+        )
+    }
+
+    #[test]
+    fn test_revision_beyond_i32() {
+        let manifest: Manifest = manifest_with_revision("4294967296").parse().unwrap();
+        assert_eq!(manifest.s, 4294967296);
+    }
+
+    #[test]
+    fn test_negative_revision_is_rejected() {
+        let result = manifest_with_revision("-1").parse::<Manifest>();
+        assert!(matches!(result, Err(ManifestError::ParseError('S', _))));
+    }
+
+    #[test]
+    fn test_has_field() {
+        let manifest: Manifest = manifest_with_revision("42").parse().unwrap();
+        assert!(manifest.has_field('G'));
+        assert!(manifest.has_field('S'));
+        assert!(!manifest.has_field('g'));
+        assert!(!manifest.has_field('Z'));
+        assert_eq!(manifest.field_count(), 13);
+
+        let with_unknown: Manifest = manifest_with_revision("42")
+            .replace("\n--\n", "\nZfuture\n--\n")
+            .parse()
+            .unwrap();
+        assert!(with_unknown.has_field('Z'));
+        assert_eq!(with_unknown.field_count(), manifest.field_count() + 1);
+
+        // The keys present are neither compared nor serialized.
+        assert_eq!(with_unknown, manifest);
+        let json = serde_json::to_value(&with_unknown).unwrap();
+        assert!(json.get("fields").is_none());
+        let deserialized: Manifest = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.field_count(), 0);
+        assert_eq!(deserialized, manifest);
+    }
+
+    #[test]
+    fn test_builder_fields() {
+        let manifest = Manifest::builder()
+            .with_name("software.eessi.io")
+            .with_root_catalog_hash(hash())
+            .with_revision(7)
+            .with_timestamp(timestamp())
+            .build()
+            .unwrap();
+        assert_eq!(manifest.field_count(), BUILT_FIELDS.len());
+        assert!(manifest.has_field('G'));
+        assert!(!manifest.has_field('L'));
+    }
+
+    #[test]
+    fn test_blank_lines_before_signature_are_ignored() {
+        let content = manifest_with_revision("42").replace("\n--\n", "\n\n  \n--\n");
+        let manifest: Manifest = content.parse().unwrap();
+        assert_eq!(manifest.s, 42);
+        assert_eq!(manifest.signature, "signature");
+    }
+
+    #[test]
+    fn test_manifest_from_json() {
+        let value = serde_json::json!({
+            "name": "software.eessi.io",
+            "root_hash": "600230b0ba7620426f2e898f1e1f43c5466efe59",
+            "revision": 42,
+            "timestamp": 1718991602,
+            "ttl": 120,
+            "garbage_collectable": true,
+        });
+        let manifest = Manifest::try_from(&value).unwrap();
+        let expected = Manifest::builder()
+            .with_name("software.eessi.io")
+            .with_root_catalog_hash(hash())
+            .with_revision(42)
+            .with_timestamp(timestamp())
+            .with_ttl(120)
+            .with_garbage_collectable(true)
+            .build()
+            .unwrap();
+        assert_eq!(manifest, expected);
+    }
+
+    #[parameterized(
+        not_an_object = { serde_json::json!([1, 2]) },
+        missing_revision = { serde_json::json!({"name": "a", "root_hash": "ab", "timestamp": 1}) },
+        negative_revision = { serde_json::json!({"name": "a", "root_hash": "ab", "revision": -1, "timestamp": 1}) },
+        invalid_hash = { serde_json::json!({"name": "a", "root_hash": "xyz", "revision": 1, "timestamp": 1}) },
+        wrong_type = { serde_json::json!({"name": "a", "root_hash": "ab", "revision": 1, "timestamp": "now"}) },
+        ttl_overflow = { serde_json::json!({"name": "a", "root_hash": "ab", "revision": 1, "timestamp": 1, "ttl": 4294967296_i64}) },
+    )]
+    fn test_manifest_from_invalid_json(value: serde_json::Value) {
+        assert!(matches!(
+            Manifest::try_from(&value),
+            Err(ManifestError::JsonConversionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_valid_manifest() {
+        let manifest = Manifest::validate(&manifest_with_revision("42")).unwrap();
+        assert_eq!(manifest, manifest_with_revision("42").parse().unwrap());
+    }
+
+    #[parameterized(
+        missing_revision = { manifest_with_revision("42").replace("S42\n", ""), vec![ManifestError::MissingField('S')] },
+        negative_revision = { manifest_with_revision("-1"), vec![ManifestError::ParseError('S', String::new())] },
+        bad_hash = { manifest_with_revision("42").replace("C600230b", "Cxyz0230b"), vec![ManifestError::InvalidHex(String::new())] },
+        empty_name = { manifest_with_revision("42").replace("Nsoftware.eessi.io", "N"), vec![ManifestError::ParseError('N', String::new())] },
+        truncated_signature = { manifest_with_revision("42").replace("\nsignature", ""), vec![ManifestError::InvalidSignature(String::new())] },
+        no_signature_block = { manifest_with_revision("42").replace("\n--\nsignature", ""), vec![ManifestError::InvalidSignature(String::new())] },
+        several_problems = {
+            manifest_with_revision("-1").replace("T1718991602\n", "").replace("\nsignature", ""),
+            vec![
+                ManifestError::MissingField('T'),
+                ManifestError::ParseError('S', String::new()),
+                ManifestError::InvalidSignature(String::new()),
+            ]
+        },
+    )]
+    fn test_validate_reports_every_problem(content: String, expected: Vec<ManifestError>) {
+        let errors = Manifest::validate(&content).unwrap_err();
+        // Compare the kinds of errors, not their messages.
+        let kind = |error: &ManifestError| match error {
+            ManifestError::MissingField(field) => format!("missing {}", field),
+            ManifestError::ParseError(field, _) => format!("parse {}", field),
+            ManifestError::InvalidHex(_) => "hex".to_string(),
+            ManifestError::InvalidSignature(_) => "signature".to_string(),
+            other => other.to_string(),
+        };
+        assert_eq!(
+            errors.iter().map(kind).collect::<Vec<_>>(),
+            expected.iter().map(kind).collect::<Vec<_>>()
+        );
+    }
+}
@@ -2,12 +2,29 @@ use serde::{Deserialize, Serialize};
 
 use crate::models::generic::MaybeRfc2822DateTime;
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The status of a repository or replica, from .cvmfs_status.json.
+///
+/// Every field is optional: a Stratum0 typically only reports last_gc, while replicas on
+/// Stratum1 servers also report last_snapshot (and, with recent versions of CVMFS,
+/// last_snapshot_start). Servers that run `cvmfs_server check` also report when it last ran
+/// (last_check) and its outcome (check_status, e.g. "succeeded" or "failed").
+///
+/// Fields not modeled here are kept in `extra`, as the file gains fields with new versions of
+/// CVMFS.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
 pub struct StatusJSON {
     #[serde(default)]
     pub last_snapshot: Option<MaybeRfc2822DateTime>,
     #[serde(default)]
     pub last_gc: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub last_snapshot_start: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub last_check: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub check_status: Option<String>,
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[cfg(test)]
@@ -45,6 +62,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_status_json_deserialization_last_snapshot_start() {
+        let json_data = r#"
+        {
+            "last_snapshot": "Fri Jun 21 17:40:02 UTC 2024",
+            "last_snapshot_start": "Fri Jun 21 17:35:00 UTC 2024"
+        }
+        "#;
+
+        let status: StatusJSON = serde_json::from_str(json_data).unwrap();
+        assert_eq!(
+            status
+                .last_snapshot_start
+                .unwrap()
+                .try_into_datetime()
+                .unwrap(),
+            Some(
+                Rfc2822DateTime::from("Fri, 21 Jun 2024 17:35:00 +0000")
+                    .try_into()
+                    .unwrap()
+            )
+        );
+    }
+
     #[test]
     fn test_status_json_deserialization_missing_last_gc() {
         let json_data = r#"
@@ -0,0 +1,134 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ManifestError;
+
+/// The format of the timestamps in a whitelist, e.g. `20240618134004` (UTC).
+const WHITELIST_TIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// The whitelist of a repository, its .cvmfswhitelist.
+///
+/// The whitelist lists the certificates allowed to sign the manifest, and expires. Once it has
+/// expired, clients refuse the repository until it is signed again, typically by a cron job on
+/// the Stratum0 that replicas then pick up with their snapshots.
+///
+/// The fields are:
+/// - created: When the whitelist was signed (the first line)
+/// - expires: When the whitelist expires (the `E` line)
+/// - repository: The name of the repository (the `N` line)
+/// - fingerprints: The fingerprints of the certificates allowed to sign the manifest
+///
+/// The signature after the `--` line is not kept or validated. The file shares its line format
+/// (and thus its error type) with the manifest, see `Manifest`.
+///
+/// See https://cvmfs.readthedocs.io/en/stable/cpt-details.html#repository-whitelist for more
+/// information.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Whitelist {
+    pub created: Option<DateTime<Utc>>,
+    pub expires: DateTime<Utc>,
+    pub repository: Option<String>,
+    pub fingerprints: Vec<String>,
+}
+
+impl Whitelist {
+    /// Returns true if the whitelist has expired at the given time.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires <= now
+    }
+
+    /// The time left until the whitelist expires at the given time, negative once it has expired.
+    pub fn remaining_at(&self, now: DateTime<Utc>) -> chrono::Duration {
+        self.expires - now
+    }
+}
+
+fn parse_time(field: char, value: &str) -> Result<DateTime<Utc>, ManifestError> {
+    NaiveDateTime::parse_from_str(value.trim(), WHITELIST_TIME_FORMAT)
+        .map(|time| time.and_utc())
+        .map_err(|e| ManifestError::ParseError(field, e.to_string()))
+}
+
+impl std::str::FromStr for Whitelist {
+    type Err = ManifestError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let mut created = None;
+        let mut expires = None;
+        let mut repository = None;
+        let mut fingerprints = vec![];
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim_end();
+            if line == "--" {
+                break;
+            }
+            match line.chars().next() {
+                None => continue,
+                Some('E') => expires = Some(parse_time('E', &line[1..])?),
+                Some('N') => repository = Some(line[1..].to_string()),
+                // The creation time is the first line, with no key. It is informational only, so
+                // it is not required to parse.
+                Some(c) if index == 0 && c.is_ascii_digit() => {
+                    created = parse_time('0', line).ok();
+                }
+                // Fingerprints may be followed by a comment, e.g. `AB:CD:... # key name`.
+                Some(_) => {
+                    if let Some(fingerprint) = line.split_whitespace().next() {
+                        fingerprints.push(fingerprint.to_string());
+                    }
+                }
+            }
+        }
+        Ok(Whitelist {
+            created,
+            expires: expires.ok_or(ManifestError::MissingField('E'))?,
+            repository,
+            fingerprints,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const WHITELIST: &str = "20240618134004\nE20240718134004\nNsoftware.eessi.io\n\
+        AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01 # release key\n\
+        --\n5d0c0d4c8d0e3f3b1a2b\n\u{1}binary signature";
+
+    #[test]
+    fn test_parse_whitelist() {
+        let whitelist = WHITELIST.parse::<Whitelist>().unwrap();
+        assert_eq!(
+            whitelist.created,
+            Some(Utc.with_ymd_and_hms(2024, 6, 18, 13, 40, 4).unwrap())
+        );
+        let expires = Utc.with_ymd_and_hms(2024, 7, 18, 13, 40, 4).unwrap();
+        assert_eq!(whitelist.expires, expires);
+        assert_eq!(whitelist.repository.as_deref(), Some("software.eessi.io"));
+        assert_eq!(
+            whitelist.fingerprints,
+            vec!["AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01"]
+        );
+        assert!(whitelist.is_expired_at(expires));
+        assert!(!whitelist.is_expired_at(expires - chrono::Duration::seconds(1)));
+        assert_eq!(
+            whitelist.remaining_at(expires - chrono::Duration::days(2)),
+            chrono::Duration::days(2)
+        );
+    }
+
+    #[test]
+    fn test_parse_whitelist_errors() {
+        assert!(matches!(
+            "20240618134004\nNsoftware.eessi.io\n--\n".parse::<Whitelist>(),
+            Err(ManifestError::MissingField('E'))
+        ));
+        assert!(matches!(
+            "20240618134004\nEnot a date\n".parse::<Whitelist>(),
+            Err(ManifestError::ParseError('E', _))
+        ));
+    }
+}
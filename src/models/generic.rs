@@ -1,52 +1,121 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::errors::{HostnameError, ManifestError, ScrapeError};
+use crate::errors::{HostnameError, ManifestError, RepositoryNameError, ScrapeError};
 
-/// A hostname string.
+/// A hostname string, optionally with a port.
 ///
 /// This type is used to represent a hostname string. It is a wrapper around a `String` and
-/// provides validation for hostnames.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct Hostname(String);
+/// provides validation for hostnames. IPv6 address literals are also accepted, with or without
+/// brackets, and are stored without them. Use `as_url_authority` when building URLs, which adds
+/// the brackets back.
+///
+/// A port may follow the hostname, e.g. `cvmfs-s1.example.org:8080` or `[2001:db8::1]:8080`
+/// (IPv6 addresses need brackets to be given a port). The port is kept, see `port`, and is used
+/// when building URLs for a server without a port of its own (see `Server::with_port`).
+/// `to_str` is the hostname without the port, while the string representation (Display and
+/// serde) includes it. Two hostnames with different ports are different hostnames.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hostname(String, Option<u16>);
 
 impl std::str::FromStr for Hostname {
     type Err = HostnameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() > 255 {
-            return Err(HostnameError::TooLong(s.to_string()));
-        }
+        let (host, port) = split_port(s)?;
+        let mut hostname = parse_host(host)?;
+        hostname.1 = port;
+        Ok(hostname)
+    }
+}
 
-        let labels: Vec<&str> = s.split('.').collect();
-        for label in &labels {
-            if label.len() > 63 {
-                return Err(HostnameError::LabelTooLong(label.to_string()));
-            }
-            if !label.chars().all(|c| c.is_alphanumeric() || c == '-') {
-                return Err(HostnameError::InvalidChar(label.to_string()));
-            }
-            // This will also catch empty labels
-            if !label.chars().next().unwrap_or_default().is_alphanumeric()
-                || !label.chars().last().unwrap_or_default().is_alphanumeric()
-            {
-                return Err(HostnameError::InvalidLabelFormat(format!(
-                    "First and last character of '{}' is not alphanumeric.",
-                    label
-                )));
-            }
-            if label.contains("--") {
-                return Err(HostnameError::ConsecutiveDashes(label.to_string()));
-            }
+/// Split a hostname into the host and the port, if any.
+///
+/// A port is only split off an IPv6 address in brackets, as the address contains colons.
+fn split_port(s: &str) -> Result<(&str, Option<u16>), HostnameError> {
+    let parse_port = |port: &str| match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(Some(port)),
+        _ => Err(HostnameError::InvalidPort(s.to_string())),
+    };
+    if let Some((address, rest)) = s.strip_prefix('[').and_then(|s| s.split_once(']')) {
+        if address.parse::<std::net::Ipv6Addr>().is_err() {
+            return Err(HostnameError::InvalidChar(s.to_string()));
         }
+        return match rest.strip_prefix(':') {
+            Some(port) => Ok((address, parse_port(port)?)),
+            None if rest.is_empty() => Ok((address, None)),
+            None => Err(HostnameError::InvalidPort(s.to_string())),
+        };
+    }
+    if s.parse::<std::net::Ipv6Addr>().is_ok() {
+        return Ok((s, None));
+    }
+    match s.rsplit_once(':') {
+        Some((host, port)) => Ok((host, parse_port(port)?)),
+        None => Ok((s, None)),
+    }
+}
 
-        Ok(Hostname(s.to_string()))
+/// Parse and validate a hostname without a port.
+fn parse_host(s: &str) -> Result<Hostname, HostnameError> {
+    if let Ok(address) = s.parse::<std::net::Ipv6Addr>() {
+        return Ok(Hostname(address.to_string(), None));
     }
+
+    if s.len() > 255 {
+        return Err(HostnameError::TooLong(s.to_string()));
+    }
+
+    let labels: Vec<&str> = s.split('.').collect();
+    for label in &labels {
+        if label.len() > 63 {
+            return Err(HostnameError::LabelTooLong(label.to_string()));
+        }
+        if !label.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            return Err(HostnameError::InvalidChar(label.to_string()));
+        }
+        // This will also catch empty labels
+        if !label.chars().next().unwrap_or_default().is_alphanumeric()
+            || !label.chars().last().unwrap_or_default().is_alphanumeric()
+        {
+            return Err(HostnameError::InvalidLabelFormat(format!(
+                "First and last character of '{}' is not alphanumeric.",
+                label
+            )));
+        }
+        if label.contains("--") {
+            return Err(HostnameError::ConsecutiveDashes(label.to_string()));
+        }
+    }
+
+    Ok(Hostname(s.to_string(), None))
 }
 
 impl std::fmt::Display for Hostname {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self.1 {
+            Some(_) => write!(f, "{}", self.as_url_authority()),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Serialize for Hostname {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hostname {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -67,12 +136,182 @@ impl TryFrom<String> for Hostname {
 }
 
 impl Hostname {
+    /// The hostname, without any port.
     pub fn to_str(&self) -> &str {
         &self.0
     }
 
+    /// The hostname as a string, with the port if any, as displayed.
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
-        self.0.clone()
+        format!("{}", self)
+    }
+
+    /// The port given with the hostname, if any.
+    pub fn port(&self) -> Option<u16> {
+        self.1
+    }
+
+    /// Returns true if the hostname is an IPv6 address literal.
+    pub fn is_ipv6(&self) -> bool {
+        self.0.parse::<std::net::Ipv6Addr>().is_ok()
+    }
+
+    /// The hostname as the authority of a URL, e.g. "example.org" or "[2001:db8::1]", with the
+    /// port of the hostname if any.
+    pub fn as_url_authority(&self) -> String {
+        self.as_url_authority_with_port(None)
+    }
+
+    /// As `as_url_authority`, with the port if given, e.g. "example.org:8080" or
+    /// "[2001:db8::1]:8080". The given port takes precedence over the port of the hostname.
+    pub fn as_url_authority_with_port(&self, port: Option<u16>) -> String {
+        let host = if self.is_ipv6() {
+            format!("[{}]", self.0)
+        } else {
+            self.0.clone()
+        };
+        match port.or(self.1) {
+            Some(port) => format!("{}:{}", host, port),
+            None => host,
+        }
+    }
+}
+
+/// A fully qualified repository name (FQRN), e.g. "software.eessi.io".
+///
+/// This type is used to represent the name of a repository. It is a wrapper around a `String` and
+/// provides validation for repository names, which are used in URLs. A valid name:
+///
+/// - Is at most 255 characters long.
+/// - Consists of at least two dot-separated labels, a namespace and a domain.
+/// - Only contains alphanumeric ASCII characters, dashes, and underscores in its labels.
+///
+/// The name is kept as given, see `Server::scrape` for how requested names are normalized.
+/// Deserializing validates the name as well.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct RepositoryName(String);
+
+impl std::str::FromStr for RepositoryName {
+    type Err = RepositoryNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > 255 {
+            return Err(RepositoryNameError::TooLong(s.to_string()));
+        }
+        if !s.contains('.') {
+            return Err(RepositoryNameError::NotFullyQualified(s.to_string()));
+        }
+        for label in s.split('.') {
+            if label.is_empty() {
+                return Err(RepositoryNameError::EmptyLabel(s.to_string()));
+            }
+            if !label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(RepositoryNameError::InvalidChar(s.to_string()));
+            }
+        }
+
+        Ok(RepositoryName(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for RepositoryName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<&str> for RepositoryName {
+    type Error = RepositoryNameError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for RepositoryName {
+    type Error = RepositoryNameError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<RepositoryName> for String {
+    fn from(name: RepositoryName) -> Self {
+        name.0
+    }
+}
+
+impl AsRef<str> for RepositoryName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for RepositoryName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for RepositoryName {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for RepositoryName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for RepositoryName {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl RepositoryName {
+    pub fn to_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The first label of the name, e.g. "software" for "software.eessi.io".
+    pub fn namespace(&self) -> &str {
+        self.0
+            .split_once('.')
+            .map_or(&self.0, |(namespace, _)| namespace)
+    }
+
+    /// The name without its namespace, e.g. "eessi.io" for "software.eessi.io".
+    pub fn domain(&self) -> &str {
+        self.0.split_once('.').map_or("", |(_, domain)| domain)
+    }
+}
+
+/// The validators of a fetched document, to fetch it again only if it changed.
+///
+/// - last_modified: The Last-Modified header of the response, as sent by the server.
+/// - etag: The ETag header of the response, as sent by the server.
+///
+/// The values are opaque to the scraper, and are sent back as they are in a conditional request
+/// (`If-Modified-Since` and `If-None-Match`). See `RepositoryOrReplica::rescrape`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    pub last_modified: Option<String>,
+    pub etag: Option<String>,
+}
+
+impl CacheValidators {
+    /// Returns true if the response had neither validator, so no conditional request is possible.
+    pub fn is_empty(&self) -> bool {
+        self.last_modified.is_none() && self.etag.is_none()
     }
 }
 
@@ -90,7 +329,7 @@ pub struct HexString(String);
 
 impl HexString {
     pub fn new(s: &str) -> Result<Self, ManifestError> {
-        if s.len() % 2 == 0 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        if s.len().is_multiple_of(2) && s.chars().all(|c| c.is_ascii_hexdigit()) {
             Ok(HexString(s.to_string().to_lowercase()))
         } else {
             Err(ManifestError::InvalidHex(s.to_string()))
@@ -117,8 +356,9 @@ impl<'de> Deserialize<'de> for HexString {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
-        HexString::new(s).map_err(serde::de::Error::custom)
+        // Owned, as not every deserializer can lend out a borrowed string (e.g. serde_json::Value).
+        let s = String::deserialize(deserializer)?;
+        HexString::new(&s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -132,7 +372,11 @@ impl<'de> Deserialize<'de> for HexString {
 /// To offer both the option of a time-parsed field and the raw string, we store
 /// the string itself and provide a method (`try_into_datetime`) to attempt to
 /// parse the string into a `DateTime<Utc>`.
+///
+/// In JSON, the value is the original string exactly as reported (or null), so it
+/// survives a round-trip unchanged even if it cannot be parsed as a date.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+#[serde(transparent)]
 pub struct MaybeRfc2822DateTime(pub Option<String>);
 
 impl std::fmt::Display for MaybeRfc2822DateTime {
@@ -150,7 +394,7 @@ impl MaybeRfc2822DateTime {
             Some(date_str) => {
                 // Try parsing the date string with the format
                 let naive_dt = NaiveDateTime::parse_from_str(date_str, "%a %b %d %H:%M:%S %Z %Y")
-                    .map_err(|_| ScrapeError::ConversionError(date_str.clone()))?;
+                    .map_err(|e| ScrapeError::conversion(date_str, e))?;
                 // Convert NaiveDateTime to DateTime<Utc>
                 Ok(Some(DateTime::<Utc>::from_naive_utc_and_offset(
                     naive_dt, Utc,
@@ -190,6 +434,21 @@ mod tests {
     use super::*;
     use yare::parameterized;
 
+    #[parameterized(
+        english = { Some("Fri Jun 21 17:40:02 UTC 2024"), r#""Fri Jun 21 17:40:02 UTC 2024""# },
+        norwegian = { Some("fre. 21. juni 17:40:02 +0200 2024"), r#""fre. 21. juni 17:40:02 +0200 2024""# },
+        missing = { None, "null" },
+    )]
+    fn test_maybe_rfc2822_datetime_json(value: Option<&str>, expected: &str) {
+        let date = MaybeRfc2822DateTime(value.map(String::from));
+        let json = serde_json::to_string(&date).unwrap();
+        assert_eq!(json, expected);
+        assert_eq!(
+            serde_json::from_str::<MaybeRfc2822DateTime>(&json).unwrap(),
+            date
+        );
+    }
+
     #[parameterized(
         example_com = { "example.com" },
         foo_dash_example_com = { "foo-example.com" },
@@ -217,6 +476,42 @@ mod tests {
         assert!(hostname_str.parse::<Hostname>().is_err());
     }
 
+    #[parameterized(
+        eessi = { "software.eessi.io", "software", "eessi.io" },
+        cern = { "atlas.cern.ch", "atlas", "cern.ch" },
+        underscore = { "unpacked_images.example.org", "unpacked_images", "example.org" },
+        two_labels = { "grid.local", "grid", "local" },
+    )]
+    fn test_valid_repository_name(name: &str, namespace: &str, domain: &str) {
+        let repository: RepositoryName = name.parse().unwrap();
+        assert_eq!(repository.to_string(), name);
+        assert_eq!(repository, name);
+        assert_eq!(repository.namespace(), namespace);
+        assert_eq!(repository.domain(), domain);
+    }
+
+    #[parameterized(
+        empty = { "" },
+        too_long = { &format!("{}.org", "a".repeat(252)) },
+        not_fully_qualified = { "software" },
+        empty_label = { "software..eessi.io" },
+        trailing_dot = { "software.eessi.io." },
+        slash = { "software.eessi.io/../etc" },
+        space = { "software .eessi.io" },
+    )]
+    fn test_invalid_repository_name(name: &str) {
+        assert!(name.parse::<RepositoryName>().is_err());
+    }
+
+    #[test]
+    fn test_repository_name_json() {
+        let name = RepositoryName::try_from("software.eessi.io").unwrap();
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(json, r#""software.eessi.io""#);
+        assert_eq!(serde_json::from_str::<RepositoryName>(&json).unwrap(), name);
+        assert!(serde_json::from_str::<RepositoryName>(r#""../etc""#).is_err());
+    }
+
     #[parameterized(
         deadbeef = { "deadbeef" },
         abcdef = { "abcdef" },
@@ -271,15 +566,71 @@ mod tests {
         }
     }
 
+    #[parameterized(
+        dns = { "example.org", None, "example.org", false },
+        dns_port = { "example.org", Some(8080), "example.org:8080", false },
+        ipv4 = { "192.0.2.1", Some(80), "192.0.2.1:80", false },
+        ipv6 = { "2001:db8::1", None, "[2001:db8::1]", true },
+        ipv6_port = { "2001:db8::1", Some(8080), "[2001:db8::1]:8080", true },
+        ipv6_bracketed = { "[::1]", Some(8000), "[::1]:8000", true },
+        ipv6_normalized = { "2001:DB8:0:0:0:0:0:1", None, "[2001:db8::1]", true },
+    )]
+    fn test_hostname_url_authority(hostname: &str, port: Option<u16>, expected: &str, ipv6: bool) {
+        let hostname: Hostname = hostname.parse().unwrap();
+        assert_eq!(hostname.as_url_authority_with_port(port), expected);
+        assert_eq!(hostname.is_ipv6(), ipv6);
+        assert!(!hostname.to_str().starts_with('['));
+    }
+
     #[test]
     fn test_hostname_as_str() {
-        let hostname = Hostname("example.com".to_string());
+        let hostname = Hostname("example.com".to_string(), None);
         assert_eq!(hostname.to_str(), "example.com");
     }
 
     #[test]
     fn test_hostname_as_string() {
-        let hostname = Hostname("example.com".to_string());
+        let hostname = Hostname("example.com".to_string(), None);
         assert_eq!(hostname.to_string(), "example.com");
     }
+
+    #[parameterized(
+        dns = { "cvmfs-s1.example.org:8080", "cvmfs-s1.example.org", Some(8080), "http://cvmfs-s1.example.org:8080" },
+        ipv4 = { "192.0.2.1:8000", "192.0.2.1", Some(8000), "http://192.0.2.1:8000" },
+        ipv6 = { "[2001:db8::1]:8080", "2001:db8::1", Some(8080), "http://[2001:db8::1]:8080" },
+        ipv6_without_port = { "2001:db8::1", "2001:db8::1", None, "http://[2001:db8::1]" },
+        without_port = { "cvmfs-s1.example.org", "cvmfs-s1.example.org", None, "http://cvmfs-s1.example.org" },
+    )]
+    fn test_hostname_with_port(input: &str, host: &str, port: Option<u16>, url: &str) {
+        let hostname: Hostname = input.parse().unwrap();
+        assert_eq!(hostname.to_str(), host);
+        assert_eq!(hostname.port(), port);
+        assert_eq!(hostname.to_string(), input);
+        assert_eq!(format!("http://{}", hostname.as_url_authority()), url);
+        // An explicit port takes precedence.
+        assert!(hostname.as_url_authority_with_port(Some(1)).ends_with(":1"));
+
+        let json = serde_json::to_string(&hostname).unwrap();
+        assert_eq!(json, format!("{:?}", input));
+        assert_eq!(serde_json::from_str::<Hostname>(&json).unwrap(), hostname);
+    }
+
+    #[parameterized(
+        empty_port = { "example.org:" },
+        zero_port = { "example.org:0" },
+        large_port = { "example.org:65536" },
+        named_port = { "example.org:http" },
+        invalid_host = { "example..org:8080" },
+        after_brackets = { "[::1]8080" },
+        bracketed_name = { "[example.org]:8080" },
+        bracketed_name_without_port = { "[example.org]" },
+        deserialized = { "\"not a hostname\"" },
+    )]
+    fn test_invalid_hostname_with_port(input: &str) {
+        if input.starts_with('"') {
+            assert!(serde_json::from_str::<Hostname>(input).is_err());
+        } else {
+            assert!(input.parse::<Hostname>().is_err());
+        }
+    }
 }
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::HexString;
+
+/// A named tag (snapshot) of a repository, from its tag history database.
+///
+/// Fields:
+///
+/// - name: The name of the tag, e.g. "trunk" or the name given with `cvmfs_server tag`.
+/// - revision: The revision of the repository the tag points to.
+/// - timestamp: When the tagged revision was published, None if the database has no valid time.
+/// - root_hash: The hash of the root catalog of the tagged revision.
+///
+/// Tags are only fetched with the `tags` feature, see `RepositoryOrReplica::fetch_tag_list` and
+/// `ScrapeOptions::fetch_tags`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub revision: u64,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub root_hash: HexString,
+}
+
+/// Read the tags from a tag history database, as stored on the server (zlib compressed).
+///
+/// The tags are returned newest revision first.
+#[cfg(feature = "tags")]
+pub(crate) fn parse_history_database(
+    compressed: &[u8],
+) -> Result<Vec<Tag>, crate::errors::ScrapeError> {
+    use std::io::Read;
+
+    let invalid = |error: &dyn std::fmt::Display| {
+        crate::errors::ScrapeError::HistoryDatabase(error.to_string())
+    };
+    let mut database = Vec::new();
+    flate2::read::ZlibDecoder::new(compressed)
+        .read_to_end(&mut database)
+        .map_err(|e| invalid(&e))?;
+    read_tags(&database).map_err(|e| invalid(&e))
+}
+
+/// Read the tags from an (uncompressed) tag history database.
+///
+/// The database is loaded into memory, nothing is written to disk.
+#[cfg(feature = "tags")]
+fn read_tags(database: &[u8]) -> rusqlite::Result<Vec<Tag>> {
+    let mut connection = rusqlite::Connection::open_in_memory()?;
+    connection.deserialize_read_exact("main", database, database.len(), true)?;
+    let mut statement = connection.prepare(
+        "SELECT name, hash, revision, timestamp FROM tags ORDER BY revision DESC, name ASC",
+    )?;
+    let tags = statement.query_map([], |row| {
+        let hash: String = row.get(1)?;
+        // Hashes of algorithms other than SHA-1 carry a suffix, e.g. "-rmd160".
+        let hex = hash.split('-').next().unwrap_or_default();
+        let root_hash = HexString::new(hex).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(Tag {
+            name: row.get(0)?,
+            revision: row.get(2)?,
+            timestamp: DateTime::from_timestamp(row.get(3)?, 0),
+            root_hash,
+        })
+    })?;
+    tags.collect()
+}
+
+#[cfg(all(test, feature = "tags"))]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    /// A compressed tag history database with the given tags (name, hash, revision, timestamp).
+    fn history_database(tags: &[(&str, &str, i64, i64)]) -> Vec<u8> {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE tags (name TEXT, hash TEXT, revision INTEGER, timestamp INTEGER, \
+                 channel INTEGER, description TEXT, size INTEGER, branch TEXT);",
+            )
+            .unwrap();
+        for (name, hash, revision, timestamp) in tags {
+            connection
+                .execute(
+                    "INSERT INTO tags VALUES (?1, ?2, ?3, ?4, 0, '', 0, '')",
+                    rusqlite::params![name, hash, revision, timestamp],
+                )
+                .unwrap();
+        }
+        let database = connection.serialize("main").unwrap();
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&database).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_parse_history_database() {
+        let database = history_database(&[
+            (
+                "trunk-previous",
+                "4e4bd2c7ea45ef1d2e7efdc8a2b4ca2c2ce6a14b",
+                41,
+                1_700_000_000,
+            ),
+            (
+                "trunk",
+                "600230b0ba7620426f2e898f1e1f43c5466efe59-rmd160",
+                42,
+                1_700_003_600,
+            ),
+        ]);
+        let tags = parse_history_database(&database).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].name, "trunk");
+        assert_eq!(tags[0].revision, 42);
+        assert_eq!(
+            tags[0].timestamp,
+            DateTime::from_timestamp(1_700_003_600, 0)
+        );
+        assert_eq!(
+            tags[0].root_hash.to_string(),
+            "600230b0ba7620426f2e898f1e1f43c5466efe59"
+        );
+        assert_eq!(tags[1].name, "trunk-previous");
+
+        assert!(matches!(
+            parse_history_database(b"not a database"),
+            Err(crate::errors::ScrapeError::HistoryDatabase(_))
+        ));
+    }
+}
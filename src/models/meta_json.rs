@@ -1,12 +1,18 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The contents of cvmfs/info/v1/meta.json, contact points and metadata about the server.
+///
+/// Fields not modeled here are kept in `extra`, so that fields added to meta.json in the future
+/// are not lost (see `ServerMetadata::extra`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct MetaJSON {
     pub administrator: String,
     pub email: String,
     pub organisation: String,
     pub custom: Value,
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
 /* fn main() {
@@ -1,15 +1,25 @@
 mod cvmfs_published;
 mod cvmfs_status_json;
+mod cvmfs_whitelist;
 mod generic;
 mod geoapi;
+mod history;
 mod meta_json;
 mod repositories_json;
 mod servers;
 
-pub use cvmfs_published::Manifest;
-pub use generic::{HexString, Hostname, MaybeRfc2822DateTime};
+pub use cvmfs_published::{Manifest, ManifestBuilder};
+pub use cvmfs_status_json::StatusJSON;
+pub use cvmfs_whitelist::Whitelist;
+pub use generic::{CacheValidators, HexString, Hostname, MaybeRfc2822DateTime, RepositoryName};
 pub use geoapi::GeoapiServerQuery;
+pub use history::Tag;
+pub use meta_json::MetaJSON;
+pub use repositories_json::{RepositoriesJSON, RepositoriesJSONRepo};
+pub(crate) use servers::{servers_from_urls, CSV_HEADER};
 pub use servers::{
-    FailedServer, PopulatedRepositoryOrReplica, PopulatedServer, ScrapedServer, Server,
-    ServerBackendType, ServerMetadata, ServerType,
+    BackendDetection, DataEq, FailedServer, PlannedRequest, PlannedRequestKind,
+    PopulatedRepositoryOrReplica, PopulatedServer, RepositoriesJsonTiming, RepositoryOrReplica,
+    RepositoryScrapes, Scheme, ScrapedServer, Server, ServerBackendType, ServerIdentity,
+    ServerMetadata, ServerType, REVISION_TAG_HASH_LENGTH,
 };
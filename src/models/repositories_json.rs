@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use super::generic::MaybeRfc2822DateTime;
 
@@ -27,9 +28,24 @@ use super::generic::MaybeRfc2822DateTime;
 //       }
 //     ]
 //   }
+//
+// Servers may also recommend a client configuration, which is useful for discovering the
+// Stratum1s of a Stratum0:
+//     "recommended_stratum0" : "http://rs0.eessi.io/cvmfs/@fqrn@",
+//     "recommended_stratum1_list" : [
+//       "http://aws-eu-central-s1.eessi.science/cvmfs/@fqrn@",
+//       "http://azure-us-east-s1.eessi.science/cvmfs/@fqrn@"
+//     ]
 
+/// The contents of cvmfs/info/v1/repositories.json, see the format above.
+///
+/// Fields not modeled here are kept in `extra`, so that fields added to repositories.json in the
+/// future are not lost (see `ServerMetadata::extra`).
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RepositoriesJSON {
+    // Parsed leniently so that an unexpected schema does not make the whole document
+    // unparseable, see deserialize_schema.
+    #[serde(default, deserialize_with = "deserialize_schema")]
     pub schema: u32,
     // Default to None, Stratum0 does not have a last_geodb_update field
     #[serde(default = "MaybeRfc2822DateTime::default")]
@@ -40,6 +56,12 @@ pub struct RepositoriesJSON {
     pub os_pretty_name: Option<String>,
     pub repositories: Vec<RepositoriesJSONRepo>,
     pub replicas: Vec<RepositoriesJSONRepo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_stratum0: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recommended_stratum1_list: Vec<String>,
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl RepositoriesJSON {
@@ -52,12 +74,65 @@ impl RepositoriesJSON {
         repos.extend(self.replicas.clone());
         repos
     }
+
+    /// The repositories and replicas that are aliases of another repository, see `alias_of`.
+    pub fn aliases(&self) -> impl Iterator<Item = &RepositoriesJSONRepo> {
+        self.repositories
+            .iter()
+            .chain(self.replicas.iter())
+            .filter(|repo| repo.alias_of.is_some())
+    }
+}
+
+// The schema is a number, but accept a numeric string (e.g. "1") as well. Anything else, such as
+// a negative number or "v1", is read as 0, which is not a schema CVMFS has ever written, so that
+// the rest of the document can still be used.
+fn deserialize_schema<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let schema = match &value {
+        serde_json::Value::Number(number) => number.as_u64().and_then(|n| u32::try_from(n).ok()),
+        serde_json::Value::String(raw) => raw.trim().parse().ok(),
+        serde_json::Value::Null => Some(0),
+        _ => None,
+    };
+    Ok(schema.unwrap_or_else(|| {
+        warn!("Unable to parse repositories.json schema {}", value);
+        0
+    }))
 }
 
+// Replicas may also carry the URL of the upstream (Stratum0) they replicate from. This is not
+// written by every cvmfs_server version, so it is optional and accepted under a few names:
+//       {
+//         "name"     : "software.eessi.io",
+//         "url"      : "/cvmfs/software.eessi.io",
+//         "upstream" : "http://rs0.eessi.io/cvmfs/software.eessi.io"
+//       }
+//
+// A repository served under more than one name is listed once per name, with the aliases
+// pointing at the canonical name:
+//       {
+//         "name"     : "eessi.io",
+//         "url"      : "/cvmfs/eessi.io",
+//         "alias_of" : "software.eessi.io"
+//       }
+/// A repository or replica entry in repositories.json.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RepositoriesJSONRepo {
     pub name: String,
     pub url: String,
+    #[serde(
+        default,
+        alias = "stratum0",
+        alias = "stratum0_url",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub upstream: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
 }
 
 #[cfg(test)]
@@ -65,6 +140,56 @@ mod tests {
     use super::*;
     use crate::models::generic::Rfc2822DateTime;
 
+    #[test]
+    fn test_repositories_json_recommended_servers() {
+        let json_data = r#"
+        {
+            "schema": 1,
+            "repositories": [],
+            "replicas": [],
+            "recommended_stratum0": "http://rs0.eessi.io/cvmfs/@fqrn@",
+            "recommended_stratum1_list": [
+                "http://aws-eu-central-s1.eessi.science/cvmfs/@fqrn@",
+                "http://azure-us-east-s1.eessi.science/cvmfs/@fqrn@"
+            ]
+        }
+        "#;
+        let metadata: RepositoriesJSON = serde_json::from_str(json_data).unwrap();
+        assert_eq!(
+            metadata.recommended_stratum0.as_deref(),
+            Some("http://rs0.eessi.io/cvmfs/@fqrn@")
+        );
+        assert_eq!(metadata.recommended_stratum1_list.len(), 2);
+
+        let without: RepositoriesJSON =
+            serde_json::from_str(r#"{"schema": 1, "repositories": [], "replicas": []}"#).unwrap();
+        assert_eq!(without.recommended_stratum0, None);
+        assert!(without.recommended_stratum1_list.is_empty());
+    }
+
+    #[test]
+    fn test_repositories_json_schema_is_lenient() {
+        for (schema, expected) in [
+            ("1", 1),
+            ("\"2\"", 2),
+            ("\" 1 \"", 1),
+            ("null", 0),
+            ("\"v1\"", 0),
+            ("-1", 0),
+            ("1.5", 0),
+        ] {
+            let json_data = format!(
+                r#"{{"schema": {}, "repositories": [], "replicas": []}}"#,
+                schema
+            );
+            let metadata: RepositoriesJSON = serde_json::from_str(&json_data).unwrap();
+            assert_eq!(metadata.schema, expected, "{}", schema);
+        }
+        let without: RepositoriesJSON =
+            serde_json::from_str(r#"{"repositories": [], "replicas": []}"#).unwrap();
+        assert_eq!(without.schema, 0);
+    }
+
     #[test]
     fn test_repositories_json_deserialization() {
         let json_data = r#"
@@ -231,4 +356,72 @@ mod tests {
         let metadata: RepositoriesJSON = serde_json::from_str(json_data).unwrap();
         assert!(metadata.last_geodb_update.try_into_datetime().is_err());
     }
+
+    #[test]
+    fn test_replica_upstream_url() {
+        let json_data = r#"
+        {
+            "schema": 1,
+            "repositories": [],
+            "replicas": [
+                {
+                    "name": "software.eessi.io",
+                    "url": "/cvmfs/software.eessi.io",
+                    "upstream": "http://rs0.eessi.io/cvmfs/software.eessi.io"
+                },
+                {
+                    "name": "dev.eessi.io",
+                    "url": "/cvmfs/dev.eessi.io",
+                    "stratum0": "http://rs0.eessi.io/cvmfs/dev.eessi.io"
+                },
+                {
+                    "name": "riscv.eessi.io",
+                    "url": "/cvmfs/riscv.eessi.io"
+                }
+            ]
+        }
+        "#;
+
+        let metadata: RepositoriesJSON = serde_json::from_str(json_data).unwrap();
+        assert_eq!(
+            metadata.replicas[0].upstream,
+            Some("http://rs0.eessi.io/cvmfs/software.eessi.io".to_string())
+        );
+        assert_eq!(
+            metadata.replicas[1].upstream,
+            Some("http://rs0.eessi.io/cvmfs/dev.eessi.io".to_string())
+        );
+        assert_eq!(metadata.replicas[2].upstream, None);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let json_data = r#"
+        {
+            "schema": 1,
+            "repositories": [
+                {
+                    "name": "software.eessi.io",
+                    "url": "/cvmfs/software.eessi.io"
+                },
+                {
+                    "name": "eessi.io",
+                    "url": "/cvmfs/eessi.io",
+                    "alias_of": "software.eessi.io"
+                }
+            ],
+            "replicas": []
+        }
+        "#;
+
+        let metadata: RepositoriesJSON = serde_json::from_str(json_data).unwrap();
+        assert_eq!(metadata.repositories_and_replicas().len(), 2);
+        let aliases = metadata.aliases().collect::<Vec<_>>();
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].name, "eessi.io");
+        assert_eq!(aliases[0].alias_of.as_deref(), Some("software.eessi.io"));
+
+        let serialized = serde_json::to_value(&metadata).unwrap();
+        assert!(serialized["repositories"][0].get("alias_of").is_none());
+    }
 }
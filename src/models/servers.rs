@@ -1,27 +1,159 @@
+use chrono::{DateTime, Utc};
+use futures::stream::{FuturesUnordered, StreamExt};
 use log::{debug, error, trace, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::constants::DEFAULT_GEOAPI_SERVERS;
-use crate::errors::{CVMFSScraperError, GenericError, ManifestError, ScrapeError};
+use crate::constants::{DEFAULT_GEOAPI_SERVERS, SUPPORTED_REPOSITORIES_JSON_SCHEMA};
+use crate::errors::{CVMFSScraperError, DocumentKind, GenericError, ScrapeError};
+use crate::events::ScrapeEvent;
+use crate::health::{HealthConfig, RepoHealth};
+use crate::metrics::{MetricsLog, ScrapeMetrics};
 use crate::models::cvmfs_status_json::StatusJSON;
 use crate::models::geoapi::GeoapiServerQuery;
+#[cfg(feature = "tags")]
+use crate::models::history::parse_history_database;
 use crate::models::meta_json::MetaJSON;
 use crate::models::repositories_json::RepositoriesJSON;
-use crate::models::{Hostname, Manifest, MaybeRfc2822DateTime};
-use crate::utilities::{fetch_json, fetch_text, generate_random_string};
+#[cfg(feature = "tags")]
+use crate::models::HexString;
+use crate::models::{
+    CacheValidators, Hostname, Manifest, MaybeRfc2822DateTime, RepositoryName, Tag, Whitelist,
+};
+use crate::options::{
+    EmptyRepositoriesPolicy, RepositoryOrder, ScrapeMode, ScrapeOptions, VersionParsing,
+};
+use crate::redirects::RedirectHop;
+use crate::telemetry::ScrapeSpan;
+#[cfg(feature = "tags")]
+use crate::utilities::fetch_bytes;
+use crate::utilities::{
+    fetch_document, fetch_document_and_url, fetch_json, fetch_text, fetch_text_if_changed,
+    fetch_text_with_validators, generate_random_string, probe, Fetched,
+};
+use crate::warnings::ScrapeWarning;
 
 /// The type of server we're dealing with.
 ///
 /// Stratum0: The main server that holds the master copy of the data.
 /// Stratum1: A server that holds a copy of the data from the Stratum0 server.
 /// SyncServer: A server that holds a copy of the data from the Stratum0 server, but is not a Stratum1 server.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Copy)]
+///
+/// Server types are ordered Stratum0 < Stratum1 < SyncServer, i.e. by their distance from the
+/// master copy of the data. This ordering is stable and can be relied upon when sorting.
+///
+/// The Display implementation gives the lowercase names "stratum0", "stratum1" and "syncserver",
+/// suitable for reports and metrics labels. The serde representation is the variant name, but
+/// deserializing (like parsing) accepts the aliases users write in configuration files, see the
+/// FromStr implementation.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
 pub enum ServerType {
     Stratum0,
     Stratum1,
     SyncServer,
 }
 
+impl std::fmt::Display for ServerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ServerType::Stratum0 => "stratum0",
+            ServerType::Stratum1 => "stratum1",
+            ServerType::SyncServer => "syncserver",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Lowercase the name and drop dashes, underscores, and spaces, so that e.g. "Stratum 0",
+/// "stratum-0" and "STRATUM0" are the same.
+fn normalize_type_name(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Parse a server type, case-insensitively and ignoring dashes, underscores, and spaces.
+///
+/// Accepts "stratum0" or "s0", "stratum1" or "s1", and "syncserver" or "sync", so "Stratum0",
+/// "stratum-0", "Stratum 0", and "S0" all give `ServerType::Stratum0`.
+impl std::str::FromStr for ServerType {
+    type Err = ScrapeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_type_name(s).as_str() {
+            "stratum0" | "s0" => Ok(ServerType::Stratum0),
+            "stratum1" | "s1" => Ok(ServerType::Stratum1),
+            "syncserver" | "sync" => Ok(ServerType::SyncServer),
+            _ => Err(ScrapeError::conversion(s, "Invalid server type")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ServerType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl ServerType {
+    /// Returns true if the server holds replicas of repositories, i.e. for Stratum1s and
+    /// SyncServers.
+    pub fn is_replica_server(&self) -> bool {
+        matches!(self, ServerType::Stratum1 | ServerType::SyncServer)
+    }
+
+    /// Returns true if the server holds the master copy of repositories, i.e. for Stratum0s and
+    /// SyncServers.
+    ///
+    /// Note that a SyncServer is both a source and a replica server.
+    pub fn is_source_server(&self) -> bool {
+        matches!(self, ServerType::Stratum0 | ServerType::SyncServer)
+    }
+
+    /// Returns true if repositories.json on the server may list replicas, i.e. for Stratum1s
+    /// and SyncServers.
+    pub fn can_have_replicas(&self) -> bool {
+        matches!(self, ServerType::Stratum1 | ServerType::SyncServer)
+    }
+}
+
+/// The URL scheme used to reach a server.
+///
+/// The Display and serde representations are the lowercase scheme names, "http" and "https".
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    #[default]
+    Http,
+    Https,
+}
+
+impl std::fmt::Display for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Scheme {
+    type Err = ScrapeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "http" => Ok(Scheme::Http),
+            "https" => Ok(Scheme::Https),
+            _ => Err(ScrapeError::conversion(s, "Invalid scheme")),
+        }
+    }
+}
+
 /// The type of backend a given server is using.
 ///
 /// S3: The server is using S3 as the backend.
@@ -30,14 +162,45 @@ pub enum ServerType {
 ///
 /// The AutoDetect backend type will try to fetch the repositories.json file from the server. If it
 /// fails, it will assume the server is using S3 as the backend. If it succeeds, it will assume the
-/// server is using CVMFS as the backend.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Copy)]
+/// server is using CVMFS as the backend. Unless the server has an explicit scheme, repositories.json
+/// is first fetched over HTTPS, falling back to HTTP if the HTTPS connection cannot be made.
+///
+/// The serde representation is the variant name, but deserializing (like parsing) accepts the
+/// aliases users write in configuration files, see the FromStr implementation.
+#[derive(Debug, Serialize, Clone, PartialEq, Copy)]
 pub enum ServerBackendType {
     S3,
     CVMFS,
     AutoDetect,
 }
 
+/// Parse a backend type, case-insensitively and ignoring dashes, underscores, and spaces.
+///
+/// Accepts "s3", "cvmfs", and "autodetect" or "auto", so e.g. "CVMFS", "auto" and
+/// "auto-detect" are all valid.
+impl std::str::FromStr for ServerBackendType {
+    type Err = ScrapeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_type_name(s).as_str() {
+            "s3" => Ok(ServerBackendType::S3),
+            "cvmfs" => Ok(ServerBackendType::CVMFS),
+            "autodetect" | "auto" => Ok(ServerBackendType::AutoDetect),
+            _ => Err(ScrapeError::conversion(s, "Invalid backend type")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ServerBackendType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// A server object.
 ///
 /// This object represents a CVMFS server. It contains the server type, the backend type, and the
@@ -46,21 +209,122 @@ pub enum ServerBackendType {
 /// The server object can be used to scrape the server for information about the repositories it
 /// hosts. The scrape method will return a populated server object that contains information about
 /// the server and the repositories it hosts.
+///
+/// Servers using the S3 backend may expose their repositories under a different path than the
+/// classic `/cvmfs/<repo>` layout. Use `with_s3_path_prefix` to set the path the repositories
+/// live under, e.g. `http://bucket.example/<prefix>/<repo>/.cvmfspublished`.
+///
+/// Servers are reached over HTTP on the default port, unless a scheme or port is set with
+/// `with_scheme` or `with_port`, or the hostname has a port (e.g. `s1.example.org:8080`, see
+/// `Hostname`). The AutoDetect backend without a scheme tries HTTPS first, see
+/// `ServerBackendType`. Defaults for servers without a scheme or port can also be set in
+/// `ScrapeOptions`, but settings on the server always take precedence.
+///
+/// Servers behind a reverse proxy may serve CVMFS under a path, e.g.
+/// `https://proxy.example.org/mirror/cvmfs/...`. Use `with_base_path` to set the path, or
+/// `from_url` to give the scheme, host, port and path as a single base URL.
+///
+/// For testing, or to validate archived files offline, a server can be read from a local
+/// directory instead, see `with_local_root`.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Server {
     pub server_type: ServerType,
     #[serde(default = "default_backend_type")]
     pub backend_type: ServerBackendType,
     pub hostname: Hostname,
+    #[serde(default)]
+    pub s3_path_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<Scheme>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_root: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
 }
 
 fn default_backend_type() -> ServerBackendType {
     ServerBackendType::AutoDetect
 }
 
+/// How the backend of a populated server was determined.
+///
+/// Fields:
+///
+/// - attempted: Whether detection was performed at all (only for the AutoDetect backend type).
+/// - outcome: The backend the scrape ended up using (S3 or CVMFS), never AutoDetect.
+/// - fallback_error: If detection fell back to S3, the error from the repositories.json probe.
+///
+/// The fallback error makes it possible to tell a server that genuinely lacks repositories.json
+/// (typically a 404) from one that was misclassified due to a transient failure (e.g. a 503).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackendDetection {
+    pub attempted: bool,
+    pub outcome: ServerBackendType,
+    pub fallback_error: Option<String>,
+}
+
+impl BackendDetection {
+    /// A backend that was explicitly configured, no detection took place.
+    pub fn explicit(backend_type: ServerBackendType) -> Self {
+        BackendDetection {
+            attempted: false,
+            outcome: backend_type,
+            fallback_error: None,
+        }
+    }
+
+    /// Returns true if detection was attempted and had to fall back to S3.
+    pub fn fell_back(&self) -> bool {
+        self.attempted && self.fallback_error.is_some()
+    }
+}
+
+/// How long fetching repositories.json took, and how large it was.
+///
+/// Fields:
+///
+/// - duration: The time taken to fetch repositories.json. For the AutoDetect backend, this is the
+///   time taken by detection, including falling back from HTTPS to HTTP and following redirects.
+/// - bytes: The size of repositories.json, None if it could not be fetched.
+///
+/// Comparing this with the time spent on each repository (see the `opentelemetry` feature) tells
+/// slow detection apart from slow repository scrapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoriesJsonTiming {
+    pub duration: std::time::Duration,
+    pub bytes: Option<usize>,
+}
+
+/// What the parts of the scrape of a server share: the client, the options, and the timings.
+struct ScrapeContext<'a> {
+    client: &'a reqwest::Client,
+    options: &'a ScrapeOptions,
+    metrics: &'a MetricsLog,
+}
+
+/// A server whose repositories have been resolved, but not yet scraped.
+struct ResolvedServer {
+    /// The server, with the scheme settled by backend detection.
+    server: Server,
+    requested_repositories: std::collections::BTreeSet<String>,
+    /// The repositories to scrape, in the order of `ScrapeOptions::repository_order`.
+    repositories: Vec<RepositoryName>,
+    detection: BackendDetection,
+    repositories_json_timing: Option<RepositoriesJsonTiming>,
+    metadata: MetadataFromRepoJSON,
+    warnings: Vec<ScrapeWarning>,
+    listed: std::collections::BTreeMap<String, ListedRepository>,
+    discovered_repositories: Vec<String>,
+    unmatched_repositories: Vec<String>,
+    discovered_but_skipped: Vec<String>,
+}
+
 /// A populated server object.
 ///
-/// This type is not to be manually created, but is the result of scraping a server object.
+/// This type is not to be manually created, but is the result of scraping a server object. For
+/// tests, the `test-util` feature adds `PopulatedServer::mock`.
 ///
 /// This object represents a CVMFS server that has been scraped for information about the repositories
 /// it hosts. Note that replicas and repositories are consolidated into the attribute "repositories" as
@@ -71,35 +335,431 @@ fn default_backend_type() -> ServerBackendType {
 /// - server_type: The server type (Stratum0, Stratum1, or SyncServer)
 /// - backend_type: The backend type (S3, CVMFS, or AutoDetect)
 /// - backend_detected: The detected backend type (S3 or CVMFS), will never be AutoDetect.
+/// - detection: Details about how the backend was detected, including why AutoDetect fell back to S3.
+/// - scheme: The URL scheme the server was scraped over. For AutoDetect, the scheme detected.
+/// - mode: How the server was scraped. With `ScrapeMode::MetadataOnly`, repositories is empty
+///   and the repository names are only available from discovered_repositories.
 /// - hostname: The hostname of the server
+/// - port: The port the server was scraped on, if not the default port of the scheme.
+/// - base_path: The path CVMFS is served under, if any, see `Server::with_base_path`.
 /// - repositories: A list of populated repositories (or replicas)
+/// - requested_repositories: The repositories requested by the caller (including any that are
+///   ignored). Names are kept as given, or as repositories.json lists them if it does.
+/// - discovered_repositories: The repositories listed in repositories.json. Empty if
+///   repositories.json was not fetched (S3 backends).
+/// - unmatched_repositories: Requested repositories that are not listed in repositories.json,
+///   possibly typos. They are still scraped. Always empty if repositories.json was not fetched.
+/// - discovered_but_skipped: Repositories listed in repositories.json that were not scraped
+///   because they were filtered out (see `ScrapeOptions::ignored_repositories`), are not
+///   valid repository names (see `RepositoryName`), or are aliases of a listed repository (see
+///   `RepositoriesJSONRepo::alias_of`).
 /// - metadata: Metadata about the server (merged from repositories.json and meta.json, if found).
+/// - warnings: Non-fatal problems found during the scrape, see `ScrapeWarning`.
+/// - redirects: Every HTTP redirect seen during the scrape, in order, see `RedirectHop`. Followed
+///   redirects that change the host or scheme are also reported as
+///   `ScrapeWarning::CrossOriginRedirect`.
+/// - in_transaction: Whether a publish transaction is open on a Stratum0, if known. This is only
+///   checked if `ScrapeOptions::gateway_port` is set, and is None for other server types, if
+///   the gateway cannot be reached, or if it gives an unexpected response.
+/// - repositories_json_timing: How long fetching repositories.json (and thus AutoDetect) took
+///   and how large it was, see `RepositoriesJsonTiming`. None if repositories.json was not
+///   fetched (S3 backends). This is ignored when comparing servers, so timing jitter does not
+///   make two otherwise identical scrapes differ.
 ///
 /// Metadata is not available servers using S3 as the backend as they do not provide repositories.json
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PopulatedServer {
     pub server_type: ServerType,
     pub backend_type: ServerBackendType,
     pub backend_detected: ServerBackendType,
+    pub detection: BackendDetection,
+    pub scheme: Scheme,
+    pub mode: ScrapeMode,
     pub hostname: Hostname,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
     pub repositories: Vec<PopulatedRepositoryOrReplica>,
+    pub requested_repositories: Vec<String>,
+    pub discovered_repositories: Vec<String>,
+    pub unmatched_repositories: Vec<String>,
+    pub discovered_but_skipped: Vec<String>,
     pub metadata: ServerMetadata,
     pub geoapi: GeoapiServerQuery,
+    pub warnings: Vec<ScrapeWarning>,
+    pub redirects: Vec<RedirectHop>,
+    pub in_transaction: Option<bool>,
+    pub repositories_json_timing: Option<RepositoriesJsonTiming>,
+}
+
+/// Compares every field but repositories_json_timing, see `PopulatedServer::data_eq`.
+impl PartialEq for PopulatedServer {
+    fn eq(&self, other: &Self) -> bool {
+        self.data_eq(other)
+    }
+}
+
+/// A PopulatedServer compared by the data scraped, for use as a key in caches and sets.
+///
+/// Equality is `PopulatedServer::data_eq`. The hash covers the hostname, the server type, and
+/// the name, revision and root catalog hash of each repository, so servers with equal data
+/// always hash the same.
+///
+/// ### Example
+///
+/// ```rust
+/// use std::collections::HashSet;
+/// use cvmfs_server_scraper::{DataEq, PopulatedServer};
+///
+/// // Keep only the scrapes that found something new.
+/// fn changed(seen: &mut HashSet<DataEq>, scrapes: Vec<PopulatedServer>) -> Vec<PopulatedServer> {
+///     scrapes
+///         .into_iter()
+///         .filter(|server| seen.insert(DataEq(server.clone())))
+///         .collect()
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataEq(pub PopulatedServer);
+
+impl DataEq {
+    pub fn into_inner(self) -> PopulatedServer {
+        self.0
+    }
+}
+
+impl From<PopulatedServer> for DataEq {
+    fn from(server: PopulatedServer) -> Self {
+        DataEq(server)
+    }
+}
+
+impl PartialEq for DataEq {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.data_eq(&other.0)
+    }
+}
+
+impl Eq for DataEq {}
+
+impl std::hash::Hash for DataEq {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.identity().hash(state);
+        for repo in &self.0.repositories {
+            repo.name.hash(state);
+            repo.revision_and_root_hash().hash(state);
+        }
+    }
+}
+
+impl PopulatedServer {
+    /// Returns true if the two scrapes found the same data, ignoring when and how fast they were
+    /// made.
+    ///
+    /// This compares every field but repositories_json_timing, which differs between otherwise
+    /// identical scrapes. No scrape time is recorded on the server, so nothing else needs to be
+    /// ignored. `PartialEq` uses this comparison, and `DataEq` wraps a server to use it as a key.
+    pub fn data_eq(&self, other: &PopulatedServer) -> bool {
+        // Destructured in full, so that new fields are not forgotten here.
+        let PopulatedServer {
+            server_type,
+            backend_type,
+            backend_detected,
+            detection,
+            scheme,
+            mode,
+            hostname,
+            port,
+            base_path,
+            repositories,
+            requested_repositories,
+            discovered_repositories,
+            unmatched_repositories,
+            discovered_but_skipped,
+            metadata,
+            geoapi,
+            warnings,
+            redirects,
+            in_transaction,
+            repositories_json_timing: _,
+        } = self;
+        *server_type == other.server_type
+            && *backend_type == other.backend_type
+            && *backend_detected == other.backend_detected
+            && *detection == other.detection
+            && *scheme == other.scheme
+            && *mode == other.mode
+            && *hostname == other.hostname
+            && *port == other.port
+            && *base_path == other.base_path
+            && *repositories == other.repositories
+            && *requested_repositories == other.requested_repositories
+            && *discovered_repositories == other.discovered_repositories
+            && *unmatched_repositories == other.unmatched_repositories
+            && *discovered_but_skipped == other.discovered_but_skipped
+            && *metadata == other.metadata
+            && *geoapi == other.geoapi
+            && *warnings == other.warnings
+            && *redirects == other.redirects
+            && *in_transaction == other.in_transaction
+    }
+}
+
+/// The number of repositories listed in full by the Debug implementation of PopulatedServer.
+const DEBUG_MAX_REPOSITORIES: usize = 5;
+
+/// Debug implementation for PopulatedServer
+///
+/// A server may host many repositories, so this only shows the hostname, the server type, the
+/// detected backend, and the names of the repositories. If there are more than five repositories,
+/// only their number and the first and last names are shown. Use `debug_full` to show all fields.
+impl std::fmt::Debug for PopulatedServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct Summary<'a>(&'a [PopulatedRepositoryOrReplica]);
+
+        impl std::fmt::Debug for Summary<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.0 {
+                    [first, .., last] if self.0.len() > DEBUG_MAX_REPOSITORIES => write!(
+                        f,
+                        "[{} repos, first: {:?}, last: {:?}]",
+                        self.0.len(),
+                        first.name.to_str(),
+                        last.name.to_str()
+                    ),
+                    repos => f
+                        .debug_list()
+                        .entries(repos.iter().map(|r| r.name.to_str()))
+                        .finish(),
+                }
+            }
+        }
+
+        f.debug_struct("PopulatedServer")
+            .field("hostname", &self.hostname)
+            .field("server_type", &self.server_type)
+            .field("backend_detected", &self.backend_detected)
+            .field("repositories", &Summary(&self.repositories))
+            .finish_non_exhaustive()
+    }
+}
+
+/// The full Debug representation of a PopulatedServer, see `PopulatedServer::debug_full`.
+struct PopulatedServerDebugFull<'a>(&'a PopulatedServer);
+
+impl std::fmt::Debug for PopulatedServerDebugFull<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let server = self.0;
+        f.debug_struct("PopulatedServer")
+            .field("server_type", &server.server_type)
+            .field("backend_type", &server.backend_type)
+            .field("backend_detected", &server.backend_detected)
+            .field("detection", &server.detection)
+            .field("scheme", &server.scheme)
+            .field("mode", &server.mode)
+            .field("hostname", &server.hostname)
+            .field("port", &server.port)
+            .field("base_path", &server.base_path)
+            .field("repositories", &server.repositories)
+            .field("requested_repositories", &server.requested_repositories)
+            .field("discovered_repositories", &server.discovered_repositories)
+            .field("unmatched_repositories", &server.unmatched_repositories)
+            .field("discovered_but_skipped", &server.discovered_but_skipped)
+            .field("metadata", &server.metadata)
+            .field("geoapi", &server.geoapi)
+            .field("warnings", &server.warnings)
+            .field("redirects", &server.redirects)
+            .field("in_transaction", &server.in_transaction)
+            .field("repositories_json_timing", &server.repositories_json_timing)
+            .finish()
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl PopulatedServer {
+    /// Build a populated server without scraping, for tests of code using scrape results.
+    ///
+    /// The server uses the CVMFS backend over HTTP, has the given repositories, and has no
+    /// metadata, GeoAPI response, warnings, or redirects. All fields are public, so they can be
+    /// adjusted as needed. Only available with the `test-util` feature.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use chrono::Utc;
+    /// use cvmfs_server_scraper::{Hostname, PopulatedRepositoryOrReplica, PopulatedServer, ServerType};
+    ///
+    /// let server = PopulatedServer::mock(
+    ///     Hostname::try_from("s1.example.org").unwrap(),
+    ///     ServerType::Stratum1,
+    ///     vec![PopulatedRepositoryOrReplica::mock("software.eessi.io", 42, Utc::now())],
+    /// );
+    /// assert_eq!(server.repositories[0].revision(), 42);
+    /// ```
+    pub fn mock(
+        hostname: Hostname,
+        server_type: ServerType,
+        repositories: Vec<PopulatedRepositoryOrReplica>,
+    ) -> Self {
+        PopulatedServer {
+            server_type,
+            backend_type: ServerBackendType::CVMFS,
+            backend_detected: ServerBackendType::CVMFS,
+            detection: BackendDetection::explicit(ServerBackendType::CVMFS),
+            scheme: Scheme::Http,
+            mode: ScrapeMode::Full,
+            hostname: hostname.clone(),
+            port: None,
+            base_path: None,
+            repositories,
+            requested_repositories: vec![],
+            discovered_repositories: vec![],
+            unmatched_repositories: vec![],
+            discovered_but_skipped: vec![],
+            metadata: ServerMetadata::default(),
+            geoapi: GeoapiServerQuery {
+                hostname,
+                geoapi_hosts: vec![],
+                response: vec![],
+            },
+            warnings: vec![],
+            redirects: vec![],
+            in_transaction: None,
+            repositories_json_timing: None,
+        }
+    }
+}
+
+/// The identity of a server, its hostname, port, base path and server type.
+///
+/// The port and base path tell apart servers on the same host, e.g. behind a reverse proxy
+/// (see `Server::with_base_path`). Two scrapes of the same physical server have the same identity, regardless of the data
+/// scraped. This makes it usable as a key when tracking scrape results for a server over time,
+/// e.g. in a `HashMap<ServerIdentity, Vec<PopulatedServer>>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerIdentity {
+    pub hostname: Hostname,
+    pub port: Option<u16>,
+    pub base_path: Option<String>,
+    pub server_type: ServerType,
+}
+
+impl From<&PopulatedServer> for ServerIdentity {
+    fn from(server: &PopulatedServer) -> Self {
+        ServerIdentity {
+            hostname: server.hostname.clone(),
+            port: server.port,
+            base_path: server.base_path.clone(),
+            server_type: server.server_type,
+        }
+    }
+}
+
+impl From<&FailedServer> for ServerIdentity {
+    fn from(server: &FailedServer) -> Self {
+        ServerIdentity {
+            hostname: server.hostname.clone(),
+            port: server.port,
+            base_path: server.base_path.clone(),
+            server_type: server.server_type,
+        }
+    }
+}
+
+/// What a planned request fetches, see `Server::plan`.
+///
+/// - RepositoriesJson: The repositories.json of the server.
+/// - MetaJson: The meta.json of the server.
+/// - Manifest: The .cvmfspublished of a repository.
+/// - StatusJson: The .cvmfs_status.json of a repository.
+/// - GeoApi: The GeoAPI lookup. The URL contains a random component, written as `*`.
+/// - DiscoveredRepositories: The manifest and status (and whitelist) of any repositories discovered
+///   at runtime from repositories.json. These cannot be known in advance, so the URL is a `*`
+///   pattern covering all repositories on the server.
+/// - Whitelist: The .cvmfswhitelist of a repository, see `ScrapeOptions::fetch_whitelist`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PlannedRequestKind {
+    RepositoriesJson,
+    MetaJson,
+    Manifest,
+    StatusJson,
+    GeoApi,
+    DiscoveredRepositories,
+    Whitelist,
+}
+
+/// A request a scrape would make, see `Server::plan`.
+///
+/// - url: The URL that would be fetched.
+/// - kind: What the request fetches.
+/// - repository: The repository the request is for, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlannedRequest {
+    pub url: String,
+    pub kind: PlannedRequestKind,
+    pub repository: Option<String>,
 }
 
 /// A server that failed to scrape.
 ///
 /// This struct is used to store information about a server that failed to scrape. It contains the
-/// hostname of the server and the error that occurred.
-#[derive(Debug, Clone)]
+/// hostname of the server and the error that occurred, and the redirects seen before the failure
+/// (see `RedirectHop`). A redirect that was not followed is part of the redirects, and typically
+/// also the cause of the error (`ScrapeError::UnexpectedRedirect`).
+///
+/// The error serializes as its message, and deserializes as `CVMFSScraperError::Restored`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FailedServer {
     pub hostname: Hostname,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
     pub server_type: ServerType,
     pub backend_type: ServerBackendType,
+    #[serde(
+        serialize_with = "serialize_error_as_string",
+        deserialize_with = "deserialize_error_from_string"
+    )]
     pub error: CVMFSScraperError,
+    pub redirects: Vec<RedirectHop>,
 }
 
-#[derive(Debug, Clone)]
+impl std::fmt::Display for FailedServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to scrape {}: {}", self.hostname, self.error)
+    }
+}
+
+impl std::error::Error for FailedServer {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+fn serialize_error_as_string<S>(error: &CVMFSScraperError, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_str(error)
+}
+
+fn deserialize_error_from_string<'de, D>(deserializer: D) -> Result<CVMFSScraperError, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(CVMFSScraperError::Restored(String::deserialize(
+        deserializer,
+    )?))
+}
+
+/// The result of scraping a server.
+///
+/// Serializes with the outcome in `status`, e.g. `{"status": "failed", "hostname": ...}`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
 pub enum ScrapedServer {
     Populated(PopulatedServer),
     Failed(FailedServer),
@@ -130,6 +790,151 @@ impl ScrapedServer {
             ))),
         }
     }
+
+    /// Attach the redirects seen during the scrape, warning about cross-origin redirects.
+    fn with_redirects(mut self, redirects: Vec<RedirectHop>) -> Self {
+        match &mut self {
+            ScrapedServer::Populated(server) => {
+                for hop in redirects
+                    .iter()
+                    .filter(|hop| hop.followed && hop.is_cross_origin())
+                {
+                    warn!(
+                        "{} followed a cross-origin redirect: {}",
+                        server.hostname, hop
+                    );
+                    server.warnings.push(ScrapeWarning::CrossOriginRedirect {
+                        from: hop.from.clone(),
+                        to: hop.to.clone(),
+                    });
+                }
+                server.redirects = redirects;
+            }
+            ScrapedServer::Failed(failed) => failed.redirects = redirects,
+        }
+        self
+    }
+
+    /// Convert the result into a `Result`, with the failed server as the error.
+    #[allow(clippy::result_large_err)]
+    pub fn into_result(self) -> Result<PopulatedServer, FailedServer> {
+        match self {
+            ScrapedServer::Populated(server) => Ok(server),
+            ScrapedServer::Failed(failed) => Err(failed),
+        }
+    }
+}
+
+impl From<ScrapedServer> for Result<PopulatedServer, FailedServer> {
+    fn from(scraped: ScrapedServer) -> Self {
+        scraped.into_result()
+    }
+}
+
+/// Normalize a repository name for comparison, ignoring case and any trailing dot.
+///
+/// The normalized name is only used to match and deduplicate names. URLs and results use the
+/// name as given (see `trim_repository_name`), as the server may not accept another case.
+fn normalize_repository_name(name: &str) -> String {
+    trim_repository_name(name).to_lowercase()
+}
+
+/// A repository name without surrounding whitespace or a trailing dot, keeping its case.
+fn trim_repository_name(name: &str) -> &str {
+    name.trim().trim_end_matches('.')
+}
+
+/// Order the repositories to scrape, see `RepositoryOrder`.
+///
+/// `requested` holds the normalized names given by the caller, in the order given. Only
+/// repositories in `repositories` are returned, so ignored repositories stay filtered out.
+fn order_repositories(
+    repositories: std::collections::BTreeSet<String>,
+    requested: &[String],
+    order: RepositoryOrder,
+) -> Vec<String> {
+    match order {
+        RepositoryOrder::Sorted => repositories.into_iter().collect(),
+        RepositoryOrder::Requested => {
+            let mut remaining = repositories;
+            let mut ordered = requested
+                .iter()
+                .filter(|repo| remaining.remove(*repo))
+                .cloned()
+                .collect::<Vec<_>>();
+            ordered.extend(remaining);
+            ordered
+        }
+    }
+}
+
+/// All repositories and replicas listed in repositories.json, by normalized name, with the
+/// names as listed.
+fn discovered_repositories(
+    repo_json: &RepositoriesJSON,
+) -> std::collections::BTreeMap<String, String> {
+    repo_json
+        .repositories_and_replicas()
+        .iter()
+        .map(|r| {
+            (
+                normalize_repository_name(&r.name),
+                trim_repository_name(&r.name).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// What repositories.json lists for a repository besides its name.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ListedRepository {
+    /// The URL of the upstream (Stratum0), for replicas that list one.
+    upstream_url: Option<String>,
+    /// The canonical name, for repositories that are an alias of another.
+    canonical_name: Option<String>,
+}
+
+impl ListedRepository {
+    fn apply_to(&self, repo: &mut PopulatedRepositoryOrReplica) {
+        repo.upstream_url = self.upstream_url.clone();
+        repo.canonical_name = self.canonical_name.clone();
+    }
+}
+
+impl From<&PopulatedRepositoryOrReplica> for ListedRepository {
+    fn from(repo: &PopulatedRepositoryOrReplica) -> Self {
+        ListedRepository {
+            upstream_url: repo.upstream_url.clone(),
+            canonical_name: repo.canonical_name.clone(),
+        }
+    }
+}
+
+/// Map normalized repository names to what repositories.json lists for them, for the replicas
+/// with an upstream URL and the repositories that are aliases.
+fn listed_repositories(
+    repo_json: &RepositoriesJSON,
+) -> std::collections::BTreeMap<String, ListedRepository> {
+    let upstreams = repo_json.replicas.iter().filter_map(|r| {
+        r.upstream.clone().map(|upstream| {
+            let listed = ListedRepository {
+                upstream_url: Some(upstream),
+                ..Default::default()
+            };
+            (normalize_repository_name(&r.name), listed)
+        })
+    });
+    let mut listed = upstreams.collect::<std::collections::BTreeMap<_, _>>();
+    for alias in repo_json.aliases() {
+        listed
+            .entry(normalize_repository_name(&alias.name))
+            .or_default()
+            .canonical_name = alias
+            .alias_of
+            .as_deref()
+            .map(|name| trim_repository_name(name).to_string());
+    }
+    listed
 }
 
 impl Server {
@@ -143,67 +948,563 @@ impl Server {
             server_type,
             backend_type,
             hostname,
+            s3_path_prefix: None,
+            scheme: None,
+            port: None,
+            local_root: None,
+            base_path: None,
         }
     }
 
-    pub fn to_failed_server(&self, error: CVMFSScraperError) -> FailedServer {
-        FailedServer {
-            hostname: self.hostname.clone(),
-            server_type: self.server_type,
-            backend_type: self.backend_type,
-            error,
-        }
+    /// Create a server from a base URL, e.g. `https://stratum1.example.org:8443` or
+    /// `https://proxy.example.org/mirror`.
+    ///
+    /// The scheme of the URL (http or https) is the scheme of the server, see `with_scheme`, and
+    /// an explicit port is kept, see `with_port`. The path of the URL, up to any `cvmfs`
+    /// component, is the base path of the server (see `with_base_path`), so the entries of
+    /// `recommended_stratum1_list` (e.g. `https://stratum1.example.org/cvmfs/@fqrn@`) are also
+    /// valid URLs. The backend type is AutoDetect. Fails with `ScrapeError::ConversionError` if the
+    /// URL is invalid, has another scheme, or has no valid hostname.
+    pub fn from_url(server_type: ServerType, url: &str) -> Result<Self, ScrapeError> {
+        parse_server_url(url, server_type)
     }
 
-    /// Scrape the server for information about itself and its repos.
-    ///
-    /// This method will scrape the server for information about the repositories it hosts. It will
-    /// also fetch metadata about the server from the repositories.json and meta.json files, if they
-    /// are available.
+    /// Set the path prefix repositories are found under when using the S3 backend.
     ///
-    /// The method takes a list of repositories to scrape (which may be empty unless the backend is S3)
-    /// and a list of repositories to ignore (this may be empty). It also takes an optional list of
-    /// geoapi servers to use when fetching geoapi information.
+    /// The prefix replaces the `cvmfs` path component, so a prefix of `buckets/cvmfs-repos` makes
+    /// the manifest of `software.eessi.io` resolve to
+    /// `http://<hostname>/buckets/cvmfs-repos/software.eessi.io/.cvmfspublished`. Leading and
+    /// trailing slashes are ignored, and an empty prefix places repositories at the root of the
+    /// bucket. The prefix is used for explicit S3 backends and when AutoDetect falls back to S3.
+    pub fn with_s3_path_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.s3_path_prefix = Some(prefix.as_ref().trim_matches('/').to_string());
+        self
+    }
+
+    /// Set the path CVMFS is served under, for servers behind a reverse proxy.
     ///
-    /// The method will return a populated server object if the scrape is successful, or a failed server
-    /// object if the scrape fails.
-    pub async fn scrape<R>(
-        &self,
-        repositories: Vec<R>,
-        ignored_repositories: Vec<R>,
-        geoapi_servers: Option<Vec<Hostname>>,
-    ) -> ScrapedServer
-    where
-        R: AsRef<str> + std::fmt::Display + Clone,
-    {
-        debug!("Scraping server {}", self.hostname);
+    /// The path goes between the host and `/cvmfs`, so a base path of `mirror` makes
+    /// repositories.json resolve to `http://<hostname>/mirror/cvmfs/info/v1/repositories.json`,
+    /// and likewise for meta.json and every repository. For S3 backends, the path goes before the
+    /// S3 path prefix. Leading and trailing slashes are ignored, and an empty path is the root.
+    pub fn with_base_path<S: AsRef<str>>(mut self, path: S) -> Self {
+        let path = path.as_ref().trim_matches('/');
+        self.base_path = (!path.is_empty()).then(|| path.to_string());
+        self
+    }
 
-        let geoapi_servers = match geoapi_servers {
-            Some(servers) => servers,
-            None => DEFAULT_GEOAPI_SERVERS.clone(),
+    /// Set the URL scheme used to reach the server.
+    ///
+    /// Without an explicit scheme, servers are reached over HTTP, except that the AutoDetect
+    /// backend tries HTTPS first. Setting a scheme disables that fallback.
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    /// Set the port used to reach the server, instead of the default port of the scheme.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// A copy of the server with a different type, keeping every other setting.
+    pub fn clone_with_type(&self, server_type: ServerType) -> Server {
+        Server {
+            server_type,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the server with a different backend type, keeping every other setting.
+    pub fn clone_with_backend(&self, backend_type: ServerBackendType) -> Server {
+        Server {
+            backend_type,
+            ..self.clone()
+        }
+    }
+
+    /// Turn the server into a Stratum0, keeping every other setting.
+    pub fn into_stratum0(mut self) -> Self {
+        self.server_type = ServerType::Stratum0;
+        self
+    }
+
+    /// Turn the server into a Stratum1, keeping every other setting.
+    pub fn into_stratum1(mut self) -> Self {
+        self.server_type = ServerType::Stratum1;
+        self
+    }
+
+    /// Turn the server into a sync server, keeping every other setting.
+    pub fn into_sync_server(mut self) -> Self {
+        self.server_type = ServerType::SyncServer;
+        self
+    }
+
+    /// The server with the scheme and port defaults from the options applied, for the settings
+    /// that are not set on the server itself.
+    fn with_default_options(&self, options: &ScrapeOptions) -> Server {
+        Server {
+            scheme: self.scheme.or(options.default_scheme),
+            port: self.port.or(self.hostname.port()).or(options.default_port),
+            ..self.clone()
+        }
+    }
+
+    /// Read the server from a local directory instead of over HTTP.
+    ///
+    /// The directory takes the place of the web root of the server, so repositories.json is read
+    /// from `<root>/cvmfs/info/v1/repositories.json` and the manifest of `software.eessi.io` from
+    /// `<root>/cvmfs/software.eessi.io/.cvmfspublished`. All URLs use the `file://` scheme, and
+    /// the GeoAPI is not queried. A relative root is resolved against the current directory.
+    pub fn with_local_root<P: AsRef<std::path::Path>>(mut self, root: P) -> Self {
+        let root = root.as_ref();
+        self.local_root = Some(std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()));
+        self
+    }
+
+    pub fn to_failed_server(&self, error: CVMFSScraperError) -> FailedServer {
+        FailedServer {
+            hostname: self.hostname.clone(),
+            port: self.port,
+            base_path: self.base_path.clone(),
+            server_type: self.server_type,
+            backend_type: self.backend_type,
+            error,
+            redirects: vec![],
+        }
+    }
+
+    /// Scrape the server for information about itself and its repos.
+    ///
+    /// This method will scrape the server for information about the repositories it hosts. It will
+    /// also fetch metadata about the server from the repositories.json and meta.json files, if they
+    /// are available.
+    ///
+    /// The method takes a list of repositories to scrape (which may be empty unless the backend is S3)
+    /// and a list of repositories to ignore (this may be empty). It also takes an optional list of
+    /// geoapi servers to use when fetching geoapi information.
+    ///
+    /// The method will return a populated server object if the scrape is successful, or a failed server
+    /// object if the scrape fails.
+    pub async fn scrape<R>(
+        &self,
+        repositories: Vec<R>,
+        ignored_repositories: Vec<R>,
+        geoapi_servers: Option<Vec<Hostname>>,
+    ) -> ScrapedServer
+    where
+        R: AsRef<str>,
+    {
+        let options = ScrapeOptions {
+            ignored_repositories: ignored_repositories
+                .iter()
+                .map(|r| r.as_ref().to_string())
+                .collect(),
+            geoapi_servers: geoapi_servers.unwrap_or_else(|| DEFAULT_GEOAPI_SERVERS.clone()),
+            ..Default::default()
+        };
+        self.scrape_with_options(repositories, &options).await
+    }
+
+    /// Scrape the server with the default options, returning a `Result`.
+    ///
+    /// This is the same as `scrape_with_options` with `ScrapeOptions::default()`, but returns a
+    /// `Result` so that `?` and the usual combinators can be used. `FailedServer` implements
+    /// `std::error::Error`, with the underlying `CVMFSScraperError` as its source.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// use cvmfs_server_scraper::{Hostname, Server, ServerBackendType, ServerType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let server = Server::new(
+    ///         ServerType::Stratum1,
+    ///         ServerBackendType::CVMFS,
+    ///         Hostname::try_from("azure-us-east-s1.eessi.science")?,
+    ///     );
+    ///     let populated = server.try_scrape(vec!["software.eessi.io"]).await?;
+    ///     println!("{} repositories", populated.repositories.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn try_scrape<R>(&self, repositories: Vec<R>) -> Result<PopulatedServer, FailedServer>
+    where
+        R: AsRef<str>,
+    {
+        self.try_scrape_with_options(repositories, &ScrapeOptions::default())
+            .await
+    }
+
+    /// As `try_scrape`, using the given options.
+    pub async fn try_scrape_with_options<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+    ) -> Result<PopulatedServer, FailedServer>
+    where
+        R: AsRef<str>,
+    {
+        self.scrape_with_options(repositories, options)
+            .await
+            .into_result()
+    }
+
+    /// Scrape the server in a spawned Tokio task.
+    ///
+    /// This takes the server by value and returns the handle of the spawned task immediately,
+    /// allowing the caller to do other work while the scrape runs. Each scrape uses its own HTTP
+    /// client, so the task does not borrow anything from the caller.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn scrape_spawn(
+        self,
+        repositories: Vec<String>,
+        options: ScrapeOptions,
+    ) -> tokio::task::JoinHandle<ScrapedServer> {
+        tokio::spawn(async move { self.scrape_with_options(repositories, &options).await })
+    }
+
+    /// Scrape the server using the given options.
+    ///
+    /// This is the same as `scrape`, but takes the ignored repositories, geoapi servers, and any
+    /// other tunables from a `ScrapeOptions` object.
+    pub async fn scrape_with_options<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+    ) -> ScrapedServer
+    where
+        R: AsRef<str>,
+    {
+        self.scrape_with_metrics(repositories, options).await.0
+    }
+
+    /// Scrape the server using the given options, timing the parts of the scrape.
+    ///
+    /// This is the same as `scrape_with_options`, but also returns how long fetching
+    /// repositories.json, meta.json, and each repository took, see `ScrapeMetrics`. Use this to
+    /// find the repository that slows down the scrape of a server.
+    pub async fn scrape_with_metrics<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+    ) -> (ScrapedServer, ScrapeMetrics)
+    where
+        R: AsRef<str>,
+    {
+        options.emit(ScrapeEvent::ScrapeStarted {
+            hostname: self.hostname.clone(),
+        });
+        let span = ScrapeSpan::server(self);
+        let (scraped, metrics) = span
+            .instrument(self.scrape_server(repositories, options))
+            .await;
+        let duration = span.finish(match &scraped {
+            ScrapedServer::Populated(_) => None,
+            ScrapedServer::Failed(failed) => Some(failed.error.to_string()),
+        });
+        let metrics = metrics.finish(duration);
+        match &scraped {
+            ScrapedServer::Populated(populated) => options.emit(ScrapeEvent::ScrapeCompleted {
+                hostname: self.hostname.clone(),
+                duration: metrics.total_duration,
+                repo_count: populated.repositories.len(),
+            }),
+            ScrapedServer::Failed(failed) => options.emit(ScrapeEvent::ScrapeFailed {
+                hostname: self.hostname.clone(),
+                error: failed.error.to_string(),
+            }),
+        }
+        (scraped, metrics)
+    }
+
+    /// Resolve the repositories to scrape, without scraping them.
+    ///
+    /// This is a lower-level alternative to `scrape_with_options` for callers that want to drive
+    /// the repository scrapes with their own scheduler (e.g. a priority queue or custom
+    /// backpressure), while reusing the fetching and parsing. See `RepositoryScrapes`.
+    ///
+    /// The caller is responsible for assembling the results, there is no `PopulatedServer`.
+    /// meta.json and the GeoAPI are not queried, the cache in the options is not used, and no
+    /// events are sent. Fails if the repositories cannot be resolved, e.g. if repositories.json
+    /// cannot be fetched from a CVMFS backend.
+    pub async fn repository_scrapes<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+    ) -> Result<RepositoryScrapes, CVMFSScraperError>
+    where
+        R: AsRef<str>,
+    {
+        let client = options.build_client()?;
+        let resolved = self.resolve(repositories, options, &client).await?;
+        let backend_detected = resolved.detection.outcome;
+        let repositories = resolved
+            .repositories
+            .iter()
+            .map(|repo| options.repository(repo, &resolved.server, backend_detected))
+            .collect();
+        Ok(RepositoryScrapes {
+            server: resolved.server,
+            backend_detected,
+            repositories,
+            client,
+            listed: resolved.listed,
+        })
+    }
+
+    /// Scrape the server through each group of the CVMFS proxy chain in turn, until one can
+    /// reach it. The timings are those of the attempt whose result is returned.
+    async fn scrape_server<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+    ) -> (ScrapedServer, MetricsLog)
+    where
+        R: AsRef<str>,
+    {
+        debug!("Scraping server {}", self.hostname);
+
+        let geoapi_servers = if options.geoapi_servers.is_empty() {
+            DEFAULT_GEOAPI_SERVERS.clone()
+        } else {
+            options.geoapi_servers.clone()
+        };
+
+        // One client per group of the CVMFS proxy chain. A server that cannot be reached through
+        // a group of proxies is scraped again through the next one, as CVMFS clients do.
+        let clients = match options.build_fallback_clients_with_log() {
+            Ok(clients) => clients,
+            Err(error) => {
+                let server = self.with_default_options(options);
+                return (
+                    ScrapedServer::Failed(server.to_failed_server(error.into())),
+                    MetricsLog::default(),
+                );
+            }
+        };
+        let repositories = repositories
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<&str>>();
+        let last = clients.len() - 1;
+        for (index, (client, redirects)) in clients.into_iter().enumerate() {
+            let metrics = MetricsLog::default();
+            let scraped = self
+                .scrape_server_with_client(
+                    &client,
+                    repositories.clone(),
+                    options,
+                    geoapi_servers.clone(),
+                    &metrics,
+                )
+                .await
+                .with_redirects(redirects.hops());
+            match &scraped {
+                ScrapedServer::Failed(failed)
+                    if index < last && failed.error.is_connect_error() =>
+                {
+                    debug!(
+                        "Unable to reach {} through proxy group {}, trying the next one: {}",
+                        self.hostname,
+                        index + 1,
+                        failed.error
+                    );
+                }
+                _ => return (scraped, metrics),
+            }
+        }
+        unreachable!("There is at least one client")
+    }
+
+    async fn scrape_server_with_client<R>(
+        &self,
+        client: &reqwest::Client,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+        geoapi_servers: Vec<Hostname>,
+        metrics: &MetricsLog,
+    ) -> ScrapedServer
+    where
+        R: AsRef<str>,
+    {
+        // Failed servers record the port that was fetched from, as populated servers do.
+        let failed = |error| {
+            ScrapedServer::Failed(self.with_default_options(options).to_failed_server(error))
+        };
+        let resolved = match self.resolve(repositories, options, client).await {
+            Ok(resolved) => resolved,
+            Err(error) => return failed(error),
+        };
+        let ResolvedServer {
+            server,
+            requested_repositories,
+            repositories: all_repos,
+            detection,
+            repositories_json_timing,
+            metadata,
+            mut warnings,
+            listed,
+            discovered_repositories,
+            unmatched_repositories,
+            discovered_but_skipped,
+        } = resolved;
+        if let Some(timing) = repositories_json_timing {
+            metrics.record_repos_json(timing.duration);
+        }
+        let fetch_meta_json = async {
+            let started = std::time::Instant::now();
+            let meta_json = server.fetch_meta_json(client, &options.info_path).await;
+            metrics.record_meta_json(started.elapsed());
+            meta_json
+        };
+
+        let context = ScrapeContext {
+            client,
+            options,
+            metrics,
+        };
+        // meta.json does not depend on the repositories, so fetch it while they are scraped.
+        // It is optional, so any failure to fetch it is ignored.
+        let (populated_repos, meta_json) = match options.mode {
+            ScrapeMode::Full => {
+                tokio::join!(
+                    server.scrape_repositories(
+                        &context,
+                        all_repos,
+                        &requested_repositories,
+                        detection.outcome,
+                        &listed,
+                    ),
+                    fetch_meta_json
+                )
+            }
+            ScrapeMode::MetadataOnly => (Ok(vec![]), fetch_meta_json.await),
+        };
+        let populated_repos = match populated_repos {
+            Ok(populated_repos) => populated_repos,
+            Err(error) => return failed(error),
+        };
+        let meta_json: Option<MetaJSON> = meta_json.ok();
+        let in_transaction = match (self.server_type, options.gateway_port) {
+            (ServerType::Stratum0, Some(port)) => {
+                server.fetch_transaction_state(client, port).await
+            }
+            _ => None,
+        };
+
+        warnings.extend(self.repository_warnings(&populated_repos));
+
+        let metadata = self.merge_metadata(metadata, meta_json);
+        let backend_detected = detection.outcome;
+        let geoapi = if !populated_repos.is_empty() && self.server_type != ServerType::Stratum0 {
+            match server
+                .fetch_geoapi(
+                    client,
+                    populated_repos[0].name.to_str(),
+                    &backend_detected,
+                    geoapi_servers,
+                )
+                .await
+            {
+                Ok(geoapi) => geoapi,
+                Err(error) => return failed(error.into()),
+            }
+        } else {
+            GeoapiServerQuery {
+                hostname: self.hostname.clone(),
+                geoapi_hosts: geoapi_servers,
+                response: Vec::new(),
+            }
         };
 
-        let ignore = ignored_repositories
+        ScrapedServer::Populated(PopulatedServer {
+            server_type: self.server_type,
+            backend_type: self.backend_type,
+            backend_detected,
+            detection,
+            scheme: server.scheme.unwrap_or_default(),
+            mode: options.mode,
+            hostname: self.hostname.clone(),
+            port: server.port,
+            base_path: self.base_path.clone(),
+            repositories: populated_repos,
+            requested_repositories: requested_repositories.into_iter().collect(),
+            discovered_repositories,
+            unmatched_repositories,
+            discovered_but_skipped,
+            metadata,
+            geoapi,
+            warnings,
+            redirects: vec![],
+            in_transaction,
+            repositories_json_timing,
+        })
+    }
+
+    /// Resolve the repositories to scrape, fetching repositories.json (and detecting the backend)
+    /// as needed.
+    async fn resolve<R>(
+        &self,
+        repositories: Vec<R>,
+        options: &ScrapeOptions,
+        client: &reqwest::Client,
+    ) -> Result<ResolvedServer, CVMFSScraperError>
+    where
+        R: AsRef<str>,
+    {
+        let ignore = options
+            .ignored_repositories
             .iter()
-            .map(|r| r.to_string())
+            .map(|repo| normalize_repository_name(repo))
             .collect::<std::collections::BTreeSet<_>>();
 
-        let client = reqwest::Client::new();
-        let mut all_repos = repositories
+        // Names are matched by their normalized form, but kept as given for URLs and results.
+        // The names repositories.json lists take precedence over the names requested.
+        let mut names = std::collections::BTreeMap::new();
+        let requested_order = repositories
             .iter()
-            .map(|repo| repo.to_string())
-            .filter(|repo| !ignore.contains(repo))
+            .map(|repo| {
+                let key = normalize_repository_name(repo.as_ref());
+                names
+                    .entry(key.clone())
+                    .or_insert_with(|| trim_repository_name(repo.as_ref()).to_string());
+                key
+            })
+            .collect::<Vec<_>>();
+        // Names end up in URLs, so invalid names are refused before anything is fetched.
+        for repo in names.values() {
+            RepositoryName::try_from(repo.as_str())?;
+        }
+        let requested_repositories = requested_order
+            .iter()
+            .cloned()
             .collect::<std::collections::BTreeSet<_>>();
-        let mut populated_repos = vec![];
-        let mut backend_detected = self.backend_type;
+        let mut all_repos = requested_repositories
+            .iter()
+            .filter(|repo| !ignore.contains(*repo))
+            .cloned()
+            .collect::<std::collections::BTreeSet<_>>();
+        let mut discovered_repos = None;
+        let mut listed = std::collections::BTreeMap::new();
+        let mut detection = BackendDetection::explicit(self.backend_type);
+        let mut warnings = vec![];
 
         let mut metadata = MetadataFromRepoJSON {
             schema_version: None,
             cvmfs_version: None,
+            cvmfs_version_raw: None,
             last_geodb_update: MaybeRfc2822DateTime(None),
             os_version_id: None,
             os_pretty_name: None,
             os_id: None,
+            recommended_stratum0: None,
+            recommended_stratum1_list: vec![],
+            extra: serde_json::Map::new(),
         };
 
         // Backend type behavior when dealing with repos from http://servername/info/v1/repositories.json
@@ -213,157 +1514,901 @@ impl Server {
         // CMVFS: Fetch the repositories.json and merge it with the repositories provided. Raise an error
         //        if the fetch fails.
 
+        // AutoDetect may settle on a different scheme than the configured one, and every request
+        // after detection must use it.
+        let mut server = self.with_default_options(options);
+
+        let mut repositories_json_timing = None;
         match self.backend_type {
-            ServerBackendType::AutoDetect => match self.fetch_repos_json(&client).await {
-                Ok(repo_json) => {
-                    debug!("Detected CVMFS backend for {}", self.hostname);
-                    match self.validate_repo_json_and_server_type(&repo_json) {
-                        Ok(_) => {}
-                        Err(error) => return ScrapedServer::Failed(self.to_failed_server(error)),
-                    }
-                    metadata = match MetadataFromRepoJSON::try_from(repo_json.clone()) {
-                        Ok(meta) => meta,
-                        Err(error) => {
-                            return ScrapedServer::Failed(self.to_failed_server(error.into()))
+            ServerBackendType::AutoDetect => {
+                let started = std::time::Instant::now();
+                let result = server.detect_repos_json(client, &options.info_path).await;
+                repositories_json_timing = Some(RepositoriesJsonTiming {
+                    duration: started.elapsed(),
+                    bytes: result.as_ref().ok().map(|fetched| fetched.bytes),
+                });
+                match result.map(|fetched| fetched.document) {
+                    Ok(repo_json) => {
+                        debug!("Detected CVMFS backend for {}", self.hostname);
+                        match self.validate_repo_json(&repo_json, options) {
+                            Ok(found) => warnings.extend(found),
+                            Err(error) => return Err(error),
                         }
-                    };
-                    backend_detected = ServerBackendType::CVMFS;
-                    all_repos.extend(
-                        repo_json
-                            .repositories_and_replicas()
-                            .into_iter()
-                            .filter(|r| !ignore.contains(&r.name))
-                            .map(|r| r.name),
-                    );
-                }
-                Err(error) => match error {
-                    ScrapeError::FetchError(_) => {
-                        debug!("Detected S3 backend for {}", self.hostname);
-                        backend_detected = ServerBackendType::S3;
+                        let (meta, found) = MetadataFromRepoJSON::from_repositories_json(
+                            &repo_json,
+                            options.version_parsing,
+                        );
+                        metadata = meta;
+                        warnings.extend(found);
+                        detection = BackendDetection {
+                            attempted: true,
+                            outcome: ServerBackendType::CVMFS,
+                            fallback_error: None,
+                        };
+                        discovered_repos = Some(discovered_repositories(&repo_json));
+                        listed = listed_repositories(&repo_json);
                     }
-                    _ => return ScrapedServer::Failed(self.to_failed_server(error.into())),
-                },
-            },
+                    Err(error) if error.is_fetch_error() => {
+                        debug!("Detected S3 backend for {} ({})", self.hostname, error);
+                        detection = BackendDetection {
+                            attempted: true,
+                            outcome: ServerBackendType::S3,
+                            fallback_error: Some(error.to_string()),
+                        };
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
             ServerBackendType::S3 => {
                 if all_repos.is_empty() {
                     error!(
                         "Empty repository list with explicit S3 backend: {}",
                         self.hostname
                     );
-                    return ScrapedServer::Failed(self.to_failed_server(
-                        ScrapeError::EmptyRepositoryList(self.hostname.to_string()).into(),
-                    ));
+                    return Err(ScrapeError::EmptyRepositoryList(self.hostname.to_string()).into());
                 }
             }
             ServerBackendType::CVMFS => {
-                let repo_json = match self.fetch_repos_json(&client).await {
-                    Ok(repo_json) => repo_json,
-                    Err(error) => {
-                        return ScrapedServer::Failed(self.to_failed_server(error.into()))
-                    }
-                };
-                metadata = match MetadataFromRepoJSON::try_from(repo_json.clone()) {
-                    Ok(meta) => meta,
-                    Err(error) => {
-                        return ScrapedServer::Failed(self.to_failed_server(error.into()))
-                    }
-                };
-                match self.validate_repo_json_and_server_type(&repo_json) {
-                    Ok(_) => {}
-                    Err(error) => {
-                        return ScrapedServer::Failed(self.to_failed_server(error));
-                    }
+                let started = std::time::Instant::now();
+                let fetched = server
+                    .fetch_repos_json_and_url(client, &options.info_path)
+                    .await?;
+                repositories_json_timing = Some(RepositoriesJsonTiming {
+                    duration: started.elapsed(),
+                    bytes: Some(fetched.bytes),
+                });
+                let repo_json = fetched.document;
+                match self.validate_repo_json(&repo_json, options) {
+                    Ok(found) => warnings.extend(found),
+                    Err(error) => return Err(error),
                 }
-                all_repos.extend(
-                    repo_json
-                        .repositories_and_replicas()
-                        .into_iter()
-                        .filter(|r| !ignore.contains(&r.name))
-                        .map(|r| r.name),
+                let (meta, found) = MetadataFromRepoJSON::from_repositories_json(
+                    &repo_json,
+                    options.version_parsing,
                 );
+                metadata = meta;
+                warnings.extend(found);
+                discovered_repos = Some(discovered_repositories(&repo_json));
+                listed = listed_repositories(&repo_json);
             }
         }
 
-        for repo in all_repos {
-            let repo = RepositoryOrReplica::new(&repo, self);
-            let populated_repo = match repo.scrape(&client).await {
-                Ok(repo) => repo,
-                Err(error) => {
-                    return ScrapedServer::Failed(self.to_failed_server(error));
-                }
+        // Repositories the caller asked for that the server does not list may be typos. They
+        // are still scraped (as forced repositories), but reported on the populated server.
+        let mut unmatched_repositories = vec![];
+        let mut discovered_repositories = vec![];
+        if let Some(discovered) = discovered_repos {
+            discovered_repositories = discovered.keys().cloned().collect();
+            unmatched_repositories = all_repos
+                .iter()
+                .filter(|repo| !discovered.contains_key(*repo))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unmatched_repositories.is_empty() {
+                warn!(
+                    "Repositories not listed in repositories.json on {}: {:?}",
+                    self.hostname, unmatched_repositories
+                );
+            }
+            // An alias is the same repository as its canonical name, so it is only scraped if
+            // the canonical name is not listed (or if it is requested).
+            let is_listed_alias = |repo: &String| {
+                listed
+                    .get(repo)
+                    .and_then(|listed: &ListedRepository| listed.canonical_name.as_deref())
+                    .is_some_and(|canonical| {
+                        discovered.contains_key(&normalize_repository_name(canonical))
+                    })
             };
-            populated_repos.push(populated_repo);
+            let discovered_to_scrape = discovered
+                .iter()
+                .filter(|(repo, name)| {
+                    let valid = RepositoryName::try_from(name.as_str()).is_ok();
+                    if !valid {
+                        warn!(
+                            "Skipping invalid repository name in repositories.json on {}: {:?}",
+                            self.hostname, name
+                        );
+                    }
+                    if valid && is_listed_alias(repo) {
+                        debug!("Skipping alias {} on {}", name, self.hostname);
+                    }
+                    valid && !ignore.contains(*repo) && !is_listed_alias(repo)
+                })
+                .map(|(repo, _)| repo.clone())
+                .collect::<Vec<_>>();
+            all_repos.extend(discovered_to_scrape);
+            names.extend(discovered);
         }
+        // Repositories the server lists but that were filtered out (e.g. ignored or aliases), so
+        // callers can tell them apart from repositories that no longer exist.
+        let discovered_but_skipped = discovered_repositories
+            .iter()
+            .filter(|repo| !all_repos.contains(*repo))
+            .cloned()
+            .collect::<Vec<_>>();
 
-        let meta_json: Option<MetaJSON> = match self.fetch_meta_json(&client).await {
-            Ok(meta) => Some(meta),
-            Err(_) => None,
-        };
-
-        let metadata = self.merge_metadata(metadata, meta_json);
-        let geoapi = if populated_repos.len() > 0 && self.server_type != ServerType::Stratum0 {
-            match self
-                .fetch_geoapi(
-                    &client,
-                    &populated_repos[0].name,
-                    &backend_detected,
-                    geoapi_servers,
-                )
-                .await
-            {
-                Ok(geoapi) => geoapi,
-                Err(error) => {
-                    return ScrapedServer::Failed(self.to_failed_server(error.into()));
-                }
-            }
-        } else {
-            GeoapiServerQuery {
-                hostname: self.hostname.clone(),
-                geoapi_hosts: geoapi_servers,
-                response: Vec::new(),
-            }
-        };
-
-        ScrapedServer::Populated(PopulatedServer {
-            server_type: self.server_type,
-            backend_type: self.backend_type,
-            backend_detected,
-            hostname: self.hostname.clone(),
-            repositories: populated_repos,
+        // From here on, repositories are known by their names as given.
+        let name = |repo: String| names.get(&repo).cloned().unwrap_or(repo);
+        let named = |repos: Vec<String>| repos.into_iter().map(name).collect::<Vec<_>>();
+        Ok(ResolvedServer {
+            server,
+            requested_repositories: requested_repositories.into_iter().map(name).collect(),
+            // Every name was validated above.
+            repositories: order_repositories(all_repos, &requested_order, options.repository_order)
+                .into_iter()
+                .filter_map(|repo| RepositoryName::try_from(name(repo)).ok())
+                .collect(),
+            detection,
+            repositories_json_timing,
             metadata,
-            geoapi,
+            warnings,
+            listed: listed
+                .into_iter()
+                .map(|(repo, listed)| (name(repo), listed))
+                .collect(),
+            discovered_repositories: named(discovered_repositories),
+            unmatched_repositories: named(unmatched_repositories),
+            discovered_but_skipped: named(discovered_but_skipped),
         })
     }
 
-    async fn fetch_repos_json(
+    /// Scrape the given repositories, using the cache in the options if set.
+    async fn scrape_repositories(
         &self,
-        client: &reqwest::Client,
-    ) -> Result<RepositoriesJSON, ScrapeError> {
-        fetch_json(
+        context: &ScrapeContext<'_>,
+        repositories: Vec<RepositoryName>,
+        requested: &std::collections::BTreeSet<String>,
+        backend_type: ServerBackendType,
+        listed: &std::collections::BTreeMap<String, ListedRepository>,
+    ) -> Result<Vec<PopulatedRepositoryOrReplica>, CVMFSScraperError> {
+        let ScrapeContext {
             client,
-            format!("http://{}/cvmfs/info/v1/repositories.json", self.hostname),
+            options,
+            metrics,
+        } = context;
+        // Cached repositories are resolved up front, the rest are scraped concurrently. The
+        // slots keep the order of the repositories.
+        let mut slots = vec![];
+        let mut to_scrape = vec![];
+        for repo in repositories {
+            match options
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.get(&self.base_url(), repo.to_str()))
+            {
+                Some(cached) => {
+                    trace!("Using cached result for {} on {}", repo, self.hostname);
+                    slots.push(Some(cached));
+                }
+                None => {
+                    slots.push(None);
+                    to_scrape.push(options.repository(&repo, self, backend_type));
+                }
+            }
+        }
+
+        let names = to_scrape
+            .iter()
+            .map(|repo| repo.name.clone())
+            .collect::<Vec<_>>();
+        let mut scraped = names.into_iter().zip(
+            RepositoryOrReplica::bulk_scrape_timed(
+                to_scrape,
+                client,
+                options.repository_concurrency,
+            )
+            .await,
+        );
+
+        let mut populated_repos = vec![];
+        for slot in slots {
+            let populated_repo = match slot {
+                Some(cached) => cached,
+                None => {
+                    let (name, (result, duration)) =
+                        scraped.next().expect("a result for every repository");
+                    metrics.record_repository(name.to_str(), duration);
+                    let mut populated_repo = match result {
+                        Ok(populated_repo) => populated_repo,
+                        Err(error) => {
+                            options.emit(ScrapeEvent::RepositoryFailed {
+                                hostname: self.hostname.clone(),
+                                repo_name: name.to_string(),
+                                error: error.to_string(),
+                            });
+                            if options.require_all_requested
+                                && requested.contains(name.to_str())
+                                && error.is_not_found()
+                            {
+                                return Err(ScrapeError::RequestedRepositoryNotFound(format!(
+                                    "{} on {}",
+                                    name, self.hostname
+                                ))
+                                .into());
+                            }
+                            return Err(error);
+                        }
+                    };
+                    listed
+                        .get(name.to_str())
+                        .cloned()
+                        .unwrap_or_default()
+                        .apply_to(&mut populated_repo);
+                    if let Some(cache) = &options.cache {
+                        cache.insert(&self.base_url(), populated_repo.clone());
+                    }
+                    populated_repo
+                }
+            };
+            self.emit_repository_scraped(options, &populated_repo);
+            populated_repos.push(populated_repo);
+        }
+        Ok(populated_repos)
+    }
+
+    /// The warnings about the scraped repositories, e.g. clock skew.
+    fn repository_warnings(&self, repos: &[PopulatedRepositoryOrReplica]) -> Vec<ScrapeWarning> {
+        let mut warnings = vec![];
+        for repo in repos {
+            if let Some(skew) = repo.clock_skew() {
+                warn!(
+                    "Timestamps of {} on {} are {}s in the future",
+                    repo.name,
+                    self.hostname,
+                    skew.num_seconds()
+                );
+                warnings.push(ScrapeWarning::ClockSkew {
+                    repository: repo.name.to_string(),
+                    skew: skew.to_std().unwrap_or_default(),
+                });
+            }
+            if repo.gc_after_snapshot() == Some(true) {
+                debug!(
+                    "Garbage collection of {} on {} ran after the last snapshot",
+                    repo.name, self.hostname
+                );
+                warnings.push(ScrapeWarning::GcAfterSnapshot {
+                    repository: repo.name.to_string(),
+                });
+            }
+        }
+        warnings
+    }
+
+    fn emit_repository_scraped(
+        &self,
+        options: &ScrapeOptions,
+        repo: &PopulatedRepositoryOrReplica,
+    ) {
+        options.emit(ScrapeEvent::RepositoryScraped {
+            hostname: self.hostname.clone(),
+            repo_name: repo.name.to_string(),
+            revision: repo.revision(),
+        });
+    }
+
+    /// Scrape the repositories of a previous scrape again, reusing unchanged data.
+    ///
+    /// This is a cheap alternative to `scrape` for frequent polling. Only the repositories of the
+    /// previous scrape are scraped, using the backend detected then, and every repository is
+    /// rescraped with `RepositoryOrReplica::rescrape`, so unchanged manifests are not fetched
+    /// again. repositories.json, meta.json, and the GeoAPI are not fetched; their data is taken
+    /// from the previous scrape, as are the warnings about them. The warnings about the
+    /// repositories (e.g. clock skew) are recomputed. Use `scrape` to pick up new repositories or
+    /// metadata changes.
+    pub async fn rescrape(
+        &self,
+        previous: &PopulatedServer,
+        client: &reqwest::Client,
+    ) -> ScrapedServer {
+        debug!("Rescraping server {}", self.hostname);
+        // Reuse the scheme and port the previous scrape settled on, unless set explicitly.
+        let server = Server {
+            scheme: self.scheme.or(Some(previous.scheme)),
+            port: self.port.or(previous.port),
+            ..self.clone()
+        };
+        let repos = previous
+            .repositories
+            .iter()
+            .map(|repo| {
+                RepositoryOrReplica::for_backend(&repo.name, &server, previous.backend_detected)
+            })
+            .collect::<Vec<_>>();
+        let rescrapes = repos
+            .iter()
+            .zip(&previous.repositories)
+            .map(|(repo, previous)| repo.rescrape(client, previous));
+        let mut repositories = vec![];
+        for result in futures::future::join_all(rescrapes).await {
+            match result {
+                Ok(repo) => repositories.push(repo),
+                Err(error) => return ScrapedServer::Failed(server.to_failed_server(error)),
+            }
+        }
+        let mut warnings = previous
+            .warnings
+            .iter()
+            .filter(|warning| warning.repository().is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        warnings.extend(self.repository_warnings(&repositories));
+        ScrapedServer::Populated(PopulatedServer {
+            repositories,
+            warnings,
+            ..previous.clone()
+        })
+    }
+
+    /// Fetch the names of the repositories hosted on the server, without scraping them.
+    ///
+    /// This only fetches repositories.json, making it a cheap alternative to `scrape` for
+    /// inventory purposes. This is the same as `fetch_repository_names_with_options` with no
+    /// given repositories and the default options, so servers using the S3 backend, which have
+    /// no repositories.json, give an empty list.
+    pub async fn fetch_repository_names(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<Vec<String>, ScrapeError> {
+        self.fetch_repository_names_with_options(client, &[], &ScrapeOptions::default())
+            .await
+    }
+
+    /// Fetch the names of the repositories hosted on the server with the given options, without
+    /// scraping them.
+    ///
+    /// Servers using the S3 backend have no repositories.json, so the given repositories are
+    /// returned instead. For AutoDetect, the given repositories are returned if
+    /// repositories.json cannot be fetched. The names are sorted.
+    ///
+    /// repositories.json is fetched from `options.info_path`, and the default scheme and port
+    /// of the options apply as for a scrape.
+    pub async fn fetch_repository_names_with_options(
+        &self,
+        client: &reqwest::Client,
+        repositories: &[String],
+        options: &ScrapeOptions,
+    ) -> Result<Vec<String>, ScrapeError> {
+        let server = self.with_default_options(options);
+        let info_path = options.info_path.as_str();
+        let mut names = match self.backend_type {
+            ServerBackendType::S3 => repositories.to_vec(),
+            ServerBackendType::CVMFS => server
+                .fetch_repos_json(client, info_path)
+                .await?
+                .repositories_and_replicas()
+                .into_iter()
+                .map(|r| r.name)
+                .collect(),
+            ServerBackendType::AutoDetect => {
+                match server.fetch_repos_json(client, info_path).await {
+                    Ok(repo_json) => repo_json
+                        .repositories_and_replicas()
+                        .into_iter()
+                        .map(|r| r.name)
+                        .collect(),
+                    Err(error) if error.is_fetch_error() => repositories.to_vec(),
+                    Err(error) => return Err(error),
+                }
+            }
+        };
+        names.sort();
+        Ok(names)
+    }
+
+    /// Discover the Stratum1s recommended by this server (typically a Stratum0).
+    ///
+    /// This fetches repositories.json and builds a Stratum1 server for each entry in its
+    /// `recommended_stratum1_list`, using the AutoDetect backend and the scheme and port of the
+    /// URL. Entries that are not valid URLs with a valid hostname are skipped with a warning, and
+    /// duplicates are removed. A server that does not recommend any Stratum1s gives an empty list.
+    ///
+    /// repositories.json is fetched from `options.info_path`, and the default scheme and port
+    /// of the options apply as for a scrape.
+    pub async fn discover_stratum1s(
+        &self,
+        client: &reqwest::Client,
+        options: &ScrapeOptions,
+    ) -> Result<Vec<Server>, ScrapeError> {
+        let repo_json = self
+            .with_default_options(options)
+            .fetch_repos_json(client, &options.info_path)
+            .await?;
+        Ok(servers_from_urls(
+            &repo_json.recommended_stratum1_list,
+            ServerType::Stratum1,
+        ))
+    }
+
+    /// Check that the server is alive, returning the round-trip time of the check.
+    ///
+    /// This is a single HEAD request for repositories.json at `options.info_path`, with nothing
+    /// parsed. It is far cheaper than `scrape` for frequent liveness checks. S3 backends serve no
+    /// info documents, so for them the request goes to the `cvmfs/` directory, which buckets
+    /// often refuse to list, and any HTTP response but a server error (5xx) counts as alive.
+    /// Timeouts are those of the client, see
+    /// `ScrapeOptions::build_client`. Use `ping_path` to check another path.
+    pub async fn ping(
+        &self,
+        client: &reqwest::Client,
+        options: &ScrapeOptions,
+    ) -> Result<std::time::Duration, ScrapeError> {
+        match self.backend_type {
+            ServerBackendType::S3 => {
+                let url = format!("{}/cvmfs/", self.base_url());
+                let started = std::time::Instant::now();
+                match probe(client, &url).await {
+                    Err(ScrapeError::FetchError(error))
+                        if error
+                            .status()
+                            .is_some_and(|status| !status.is_server_error()) => {}
+                    Err(ScrapeError::UnexpectedRedirect { .. }) => {}
+                    result => result?,
+                }
+                let elapsed = started.elapsed();
+                trace!("Pinged {} in {:?}", url, elapsed);
+                Ok(elapsed)
+            }
+            _ => {
+                let url = self.repositories_json_url(&options.info_path);
+                self.ping_url(client, url).await
+            }
+        }
+    }
+
+    /// As `ping`, but checking the given path on the server, e.g. "cvmfs/info/v1/meta.json".
+    pub async fn ping_path(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+    ) -> Result<std::time::Duration, ScrapeError> {
+        let url = format!("{}/{}", self.base_url(), path.trim_start_matches('/'));
+        self.ping_url(client, url).await
+    }
+
+    async fn ping_url(
+        &self,
+        client: &reqwest::Client,
+        url: String,
+    ) -> Result<std::time::Duration, ScrapeError> {
+        let started = std::time::Instant::now();
+        probe(client, &url).await?;
+        let elapsed = started.elapsed();
+        trace!("Pinged {} in {:?}", url, elapsed);
+        Ok(elapsed)
+    }
+
+    /// List every URL a scrape of this server would fetch, without touching the network.
+    ///
+    /// This is the same as `url_inventory_with_options` with the default options.
+    pub fn url_inventory(&self, repositories: &[&str]) -> Vec<String> {
+        self.url_inventory_with_options(repositories, &ScrapeOptions::default())
+    }
+
+    /// List every URL `scrape_with_options` would fetch with the given options, without touching
+    /// the network.
+    ///
+    /// The list contains repositories.json (unless the backend is S3), meta.json, and the
+    /// .cvmfspublished and .cvmfs_status.json files for each of the given repositories, as well
+    /// as their .cvmfswhitelist if `options.fetch_whitelist` is set. It is sorted and
+    /// deduplicated, which makes it suitable for generating firewall rules.
+    ///
+    /// Note that repositories discovered at runtime via repositories.json cannot be known in
+    /// advance and are not included. GeoAPI requests are also not included, as their URLs
+    /// contain a random component. The info files are listed at `options.info_path`, and the
+    /// default scheme and port of the options apply as for a scrape. Invalid repository names
+    /// are skipped, as a scrape refuses them. See `plan_with_options` for the full plan of a
+    /// scrape.
+    pub fn url_inventory_with_options(
+        &self,
+        repositories: &[&str],
+        options: &ScrapeOptions,
+    ) -> Vec<String> {
+        let server = self.with_default_options(options);
+        let mut urls = vec![server.meta_json_url(&options.info_path)];
+        if server.backend_type != ServerBackendType::S3 {
+            urls.push(server.repositories_json_url(&options.info_path));
+        }
+        for repository in repositories {
+            let Ok(name) = RepositoryName::try_from(*repository) else {
+                continue;
+            };
+            let repo = RepositoryOrReplica::new(&name, &server);
+            urls.push(repo.manifest_url());
+            urls.push(repo.status_json_url());
+            if options.fetch_whitelist {
+                urls.push(repo.whitelist_url());
+            }
+        }
+        urls.sort();
+        urls.dedup();
+        urls
+    }
+
+    /// List the requests a scrape of this server would make, without touching the network.
+    ///
+    /// This is the same as `plan_with_options` with the default options.
+    pub fn plan<R>(&self, repositories: &[R]) -> Vec<PlannedRequest>
+    where
+        R: AsRef<str>,
+    {
+        self.plan_with_options(repositories, &ScrapeOptions::default())
+    }
+
+    /// List the requests `scrape_with_options` would make with the given options, in the order
+    /// they are made, without touching the network.
+    ///
+    /// Ignored repositories, the info path, the S3 path prefix, and the GeoAPI servers are taken
+    /// into account. Unless the backend is S3, the repositories listed in repositories.json are
+    /// also scraped. As they are only known at runtime, they are represented by a single
+    /// `PlannedRequestKind::DiscoveredRepositories` entry. This makes the plan suitable for
+    /// allowlisting in firewalls or proxies before scraping production servers. Invalid
+    /// repository names are skipped, as a scrape refuses them.
+    ///
+    /// For AutoDetect without an explicit scheme, the scrape first tries repositories.json over
+    /// HTTPS, which is planned as its own request. The rest of the plan assumes the fallback to
+    /// HTTP; if HTTPS answers, the same paths are fetched over HTTPS instead.
+    pub fn plan_with_options<R>(
+        &self,
+        repositories: &[R],
+        options: &ScrapeOptions,
+    ) -> Vec<PlannedRequest>
+    where
+        R: AsRef<str>,
+    {
+        let server = self.with_default_options(options);
+        let ignore = options
+            .ignored_repositories
+            .iter()
+            .map(|repo| normalize_repository_name(repo))
+            .collect::<std::collections::BTreeSet<_>>();
+        let repositories = repositories
+            .iter()
+            .map(|repo| (normalize_repository_name(repo.as_ref()), repo.as_ref()))
+            .filter(|(repo, _)| !ignore.contains(repo))
+            .collect::<std::collections::BTreeMap<_, _>>()
+            .into_values()
+            .filter_map(|repo| RepositoryName::try_from(trim_repository_name(repo)).ok())
+            .collect::<std::collections::BTreeSet<_>>();
+        let discovers = server.backend_type != ServerBackendType::S3;
+
+        let mut plan = vec![];
+        if server.backend_type == ServerBackendType::AutoDetect
+            && server.scheme.is_none()
+            && server.local_root.is_none()
+        {
+            plan.push(PlannedRequest {
+                url: server
+                    .clone()
+                    .with_scheme(Scheme::Https)
+                    .repositories_json_url(&options.info_path),
+                kind: PlannedRequestKind::RepositoriesJson,
+                repository: None,
+            });
+        }
+        if discovers {
+            plan.push(PlannedRequest {
+                url: server.repositories_json_url(&options.info_path),
+                kind: PlannedRequestKind::RepositoriesJson,
+                repository: None,
+            });
+        }
+        for repository in &repositories {
+            let repo = RepositoryOrReplica::new(repository, &server);
+            plan.push(PlannedRequest {
+                url: repo.manifest_url(),
+                kind: PlannedRequestKind::Manifest,
+                repository: Some(repository.to_string()),
+            });
+            plan.push(PlannedRequest {
+                url: repo.status_json_url(),
+                kind: PlannedRequestKind::StatusJson,
+                repository: Some(repository.to_string()),
+            });
+            if options.fetch_whitelist {
+                plan.push(PlannedRequest {
+                    url: repo.whitelist_url(),
+                    kind: PlannedRequestKind::Whitelist,
+                    repository: Some(repository.to_string()),
+                });
+            }
+        }
+        if discovers {
+            plan.push(PlannedRequest {
+                url: server.repository_url("*"),
+                kind: PlannedRequestKind::DiscoveredRepositories,
+                repository: None,
+            });
+        }
+        plan.push(PlannedRequest {
+            url: server.meta_json_url(&options.info_path),
+            kind: PlannedRequestKind::MetaJson,
+            repository: None,
+        });
+
+        // The GeoAPI is queried through the first repository scraped, which may be one that is
+        // only discovered at runtime. The first requested repository is the best guess.
+        if discovers && server.server_type != ServerType::Stratum0 {
+            if let Some(repository) = repositories.iter().next() {
+                let geoapi_servers = if options.geoapi_servers.is_empty() {
+                    DEFAULT_GEOAPI_SERVERS.clone()
+                } else {
+                    options.geoapi_servers.clone()
+                };
+                plan.push(PlannedRequest {
+                    url: format!(
+                        "{}/api/v1.0/geo/*/{}",
+                        server.repository_url(repository.to_str()),
+                        geoapi_servers
+                            .iter()
+                            .map(|hostname| hostname.to_str())
+                            .collect::<Vec<&str>>()
+                            .join(",")
+                    ),
+                    kind: PlannedRequestKind::GeoApi,
+                    repository: Some(repository.to_string()),
+                });
+            }
+        }
+        plan
+    }
+
+    /// The URL everything on the server is fetched under, without a trailing slash.
+    ///
+    /// This is the scheme, hostname, port and base path of the server, e.g.
+    /// `http://cvmfs.example.org:8080/mirror`, or a `file://` URL for a local root.
+    pub fn base_url(&self) -> String {
+        let root = match &self.local_root {
+            Some(root) => match reqwest::Url::from_directory_path(root) {
+                Ok(url) => url.as_str().trim_end_matches('/').to_string(),
+                Err(()) => format!("file://{}", root.display()),
+            },
+            None => format!(
+                "{}://{}",
+                self.scheme.unwrap_or_default(),
+                self.hostname.as_url_authority_with_port(self.port)
+            ),
+        };
+        match &self.base_path {
+            Some(path) => format!("{}/{}", root, path),
+            None => root,
+        }
+    }
+
+    fn info_url(&self, info_path: &str, file: &str) -> String {
+        format!(
+            "{}/cvmfs/{}/{}",
+            self.base_url(),
+            info_path.trim_matches('/'),
+            file
+        )
+    }
+
+    fn repositories_json_url(&self, info_path: &str) -> String {
+        self.info_url(info_path, "repositories.json")
+    }
+
+    fn meta_json_url(&self, info_path: &str) -> String {
+        self.info_url(info_path, "meta.json")
+    }
+
+    fn repository_url(&self, repository_name: &str) -> String {
+        format!("{}/cvmfs/{}", self.base_url(), repository_name)
+    }
+
+    /// The URL of a repository, taking the S3 path prefix into account for S3 backends.
+    fn repository_url_for_backend(
+        &self,
+        repository_name: &str,
+        backend_type: ServerBackendType,
+    ) -> String {
+        match (&self.s3_path_prefix, backend_type) {
+            (Some(prefix), ServerBackendType::S3) if prefix.is_empty() => {
+                format!("{}/{}", self.base_url(), repository_name)
+            }
+            (Some(prefix), ServerBackendType::S3) => {
+                format!("{}/{}/{}", self.base_url(), prefix, repository_name)
+            }
+            _ => self.repository_url(repository_name),
+        }
+    }
+
+    async fn fetch_repos_json(
+        &self,
+        client: &reqwest::Client,
+        info_path: &str,
+    ) -> Result<RepositoriesJSON, ScrapeError> {
+        Ok(self
+            .fetch_repos_json_and_url(client, info_path)
+            .await?
+            .document)
+    }
+
+    async fn fetch_repos_json_and_url(
+        &self,
+        client: &reqwest::Client,
+        info_path: &str,
+    ) -> Result<Fetched<RepositoriesJSON>, ScrapeError> {
+        fetch_document_and_url(
+            client,
+            self.repositories_json_url(info_path),
+            DocumentKind::RepositoriesJson,
+            &format!("host {}", self.hostname),
         )
         .await
     }
 
-    async fn fetch_meta_json(&self, client: &reqwest::Client) -> Result<MetaJSON, ScrapeError> {
-        fetch_json(
+    /// Fetch repositories.json for backend detection, settling the scheme of the server.
+    ///
+    /// Without an explicit scheme, HTTPS is tried first. Only a failure to connect falls back to
+    /// HTTP, an HTTP error status over HTTPS means the server is reachable there. If fetching over
+    /// HTTP redirects to HTTPS on the same host, the server is switched to HTTPS, see
+    /// `fetch_repos_json_upgrading`.
+    async fn detect_repos_json(
+        &mut self,
+        client: &reqwest::Client,
+        info_path: &str,
+    ) -> Result<Fetched<RepositoriesJSON>, ScrapeError> {
+        if self.scheme.is_none() && self.local_root.is_none() {
+            self.scheme = Some(Scheme::Https);
+            match self.fetch_repos_json_and_url(client, info_path).await {
+                Err(error) if error.is_connect_error() => {
+                    debug!(
+                        "HTTPS connection to {} failed, falling back to HTTP ({})",
+                        self.hostname, error
+                    );
+                    self.scheme = Some(Scheme::Http);
+                }
+                result => {
+                    debug!("Using https for {}", self.hostname);
+                    return result;
+                }
+            }
+        }
+        let result = self.fetch_repos_json_upgrading(client, info_path).await;
+        debug!(
+            "Using {} for {}",
+            self.scheme.unwrap_or_default(),
+            self.hostname
+        );
+        result
+    }
+
+    /// Fetch repositories.json, switching the server to HTTPS if it redirects there.
+    ///
+    /// Many servers redirect plain HTTP to HTTPS. If the redirect is followed, the rest of the
+    /// scrape uses HTTPS directly rather than being redirected for every request. If the redirect
+    /// is not followed (see `RedirectPolicy`), repositories.json is fetched over HTTPS directly,
+    /// keeping HTTP and the original error if that fails too.
+    async fn fetch_repos_json_upgrading(
+        &mut self,
+        client: &reqwest::Client,
+        info_path: &str,
+    ) -> Result<Fetched<RepositoriesJSON>, ScrapeError> {
+        match self.fetch_repos_json_and_url(client, info_path).await {
+            Ok(fetched) => {
+                if self.is_https_upgrade(&fetched.url) {
+                    debug!(
+                        "{} redirected to {}, switching to https",
+                        self.hostname, fetched.url
+                    );
+                    self.scheme = Some(Scheme::Https);
+                }
+                Ok(fetched)
+            }
+            Err(error) => match error.redirect_location() {
+                Some(location) if self.is_https_upgrade(location) => {
+                    debug!(
+                        "{} redirects to {}, retrying with https",
+                        self.hostname, location
+                    );
+                    let previous = self.scheme.replace(Scheme::Https);
+                    match self.fetch_repos_json_and_url(client, info_path).await {
+                        Ok(fetched) => Ok(fetched),
+                        Err(retry_error) => {
+                            debug!(
+                                "Retrying {} with https failed: {}",
+                                self.hostname, retry_error
+                            );
+                            self.scheme = previous;
+                            Err(error)
+                        }
+                    }
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    /// Returns true if the URL is on this server (same host and port) over HTTPS, while the
+    /// server uses HTTP.
+    fn is_https_upgrade(&self, url: &str) -> bool {
+        if self.local_root.is_some() || self.scheme == Some(Scheme::Https) {
+            return false;
+        }
+        reqwest::Url::parse(url).is_ok_and(|url| {
+            url.scheme() == "https"
+                && url.port() == self.port.or(self.hostname.port())
+                && url.host_str().is_some_and(|host| {
+                    host.trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .eq_ignore_ascii_case(self.hostname.to_str())
+                })
+        })
+    }
+
+    async fn fetch_meta_json(
+        &self,
+        client: &reqwest::Client,
+        info_path: &str,
+    ) -> Result<MetaJSON, ScrapeError> {
+        fetch_document(
             client,
-            format!("http://{}/cvmfs/info/v1/meta.json", self.hostname),
+            self.meta_json_url(info_path),
+            DocumentKind::MetaJson,
+            &format!("host {}", self.hostname),
         )
         .await
     }
 
+    /// Check whether a publish transaction is open on a Stratum0, through its gateway.
+    ///
+    /// The gateway lists the active leases, and a repository (or a path within it) is in a
+    /// transaction while a publisher holds a lease on it. Returns None if the gateway cannot be
+    /// reached or the response is not understood.
+    async fn fetch_transaction_state(&self, client: &reqwest::Client, port: u16) -> Option<bool> {
+        let url = format!(
+            "{}://{}/api/v1/leases",
+            self.scheme.unwrap_or_default(),
+            self.hostname.as_url_authority_with_port(Some(port))
+        );
+        let leases: serde_json::Value = match fetch_json(client, &url).await {
+            Ok(leases) => leases,
+            Err(error) => {
+                debug!("No transaction state for {}: {}", self.hostname, error);
+                return None;
+            }
+        };
+        match leases.get("data")? {
+            serde_json::Value::Object(leases) => Some(!leases.is_empty()),
+            serde_json::Value::Array(leases) => Some(!leases.is_empty()),
+            serde_json::Value::Null => Some(false),
+            _ => None,
+        }
+    }
+
     async fn fetch_geoapi(
         &self,
         client: &reqwest::Client,
-        repository_name: &String,
+        repository_name: &str,
         backend_type: &ServerBackendType,
         geoapi_hosts: Vec<Hostname>,
     ) -> Result<GeoapiServerQuery, ScrapeError> {
-        // S3 servers do not have GeoAPI support. S3 _is_ the GeoAPI.
-        if *backend_type == ServerBackendType::S3 {
-            debug!("Skipping GeoAPI for S3 server {}", self.hostname);
+        // S3 servers do not have GeoAPI support. S3 _is_ the GeoAPI. Local directories have no
+        // GeoAPI either.
+        if *backend_type == ServerBackendType::S3 || self.local_root.is_some() {
+            debug!("Skipping GeoAPI for {}", self.hostname);
             return Ok(GeoapiServerQuery {
                 hostname: self.hostname.clone(),
                 geoapi_hosts,
@@ -378,9 +2423,8 @@ impl Server {
             random_string
         );
         let url = format!(
-            "http://{}/cvmfs/{}/api/v1.0/geo/{}/{}",
-            self.hostname,
-            repository_name,
+            "{}/api/v1.0/geo/{}/{}",
+            self.repository_url(repository_name),
             random_string,
             geoapi_hosts
                 .iter()
@@ -417,32 +2461,84 @@ impl Server {
         })
     }
 
+    /// Validate repositories.json, returning any warnings.
+    ///
+    /// An empty repositories.json is checked first. If it is reported (as a warning or an
+    /// error), the server type is not checked, as an empty repositories.json also causes a server
+    /// type mismatch for replicating servers.
+    fn validate_repo_json(
+        &self,
+        repo_json: &RepositoriesJSON,
+        options: &ScrapeOptions,
+    ) -> Result<Vec<ScrapeWarning>, CVMFSScraperError> {
+        if let Some(warning) =
+            self.check_published_repositories(repo_json, options.empty_repositories)?
+        {
+            return Ok(vec![warning]);
+        }
+        Ok(self
+            .validate_repo_json_and_server_type(repo_json, options.sync_server_lenient)?
+            .into_iter()
+            .collect())
+    }
+
+    /// Check that repositories.json lists at least one repository or replica.
+    fn check_published_repositories(
+        &self,
+        repo_json: &RepositoriesJSON,
+        policy: EmptyRepositoriesPolicy,
+    ) -> Result<Option<ScrapeWarning>, CVMFSScraperError> {
+        if !repo_json.repositories.is_empty() || !repo_json.replicas.is_empty() {
+            return Ok(None);
+        }
+        match policy {
+            EmptyRepositoriesPolicy::Accept => Ok(None),
+            EmptyRepositoriesPolicy::Warn => {
+                warn!("No repositories published on {}", self.hostname);
+                Ok(Some(ScrapeWarning::NoRepositoriesPublished))
+            }
+            EmptyRepositoriesPolicy::Fail => {
+                Err(ScrapeError::NoRepositoriesPublished(self.hostname.to_string()).into())
+            }
+        }
+    }
+
+    /// Check that repositories.json matches the server type.
+    ///
+    /// Returns a warning instead of an error for a SyncServer without replicas if
+    /// `sync_server_lenient` is set.
     fn validate_repo_json_and_server_type(
         &self,
         repo_json: &RepositoriesJSON,
-    ) -> Result<(), CVMFSScraperError> {
+        sync_server_lenient: bool,
+    ) -> Result<Option<ScrapeWarning>, CVMFSScraperError> {
         trace!("Validating {}", self.hostname);
-        match (self.server_type, repo_json.replicas.is_empty()) {
-            (ServerType::Stratum0, false) => Err(CVMFSScraperError::ScrapeError(
-                ScrapeError::ServerTypeMismatch(format!(
-                    "{} is a Stratum0 server, but replicas were found in the repositories.json",
-                    self.hostname
-                )),
-            )),
-            (ServerType::Stratum1, true) => Err(CVMFSScraperError::ScrapeError(
-                ScrapeError::ServerTypeMismatch(format!(
-                    "{} is a Stratum1 server, but no replicas were found in the repositories.json",
-                    self.hostname
-                )),
-            )),
-            (ServerType::SyncServer, true) => Err(CVMFSScraperError::ScrapeError(
-                ScrapeError::ServerTypeMismatch(format!(
-                    "{} is a SyncServer, but no replicas were found in the repositories.json",
-                    self.hostname
-                )),
-            )),
-            _ => Ok(()),
+        let server_type = self.server_type;
+        let has_replicas = !repo_json.replicas.is_empty();
+        let described = match server_type {
+            ServerType::SyncServer => "a SyncServer".to_string(),
+            other => format!("a {:?} server", other),
+        };
+        if has_replicas && !server_type.can_have_replicas() {
+            return Err(ScrapeError::ServerTypeMismatch(format!(
+                "{} is {}, but replicas were found in the repositories.json",
+                self.hostname, described
+            ))
+            .into());
+        }
+        if !has_replicas && server_type.is_replica_server() {
+            let message = format!(
+                "{} is {}, but no replicas were found in the repositories.json",
+                self.hostname, described
+            );
+            // A server that also holds master copies may only publish repositories.
+            if server_type.is_source_server() && sync_server_lenient {
+                warn!("{}", message);
+                return Ok(Some(ScrapeWarning::ServerTypeMismatch(message)));
+            }
+            return Err(ScrapeError::ServerTypeMismatch(message).into());
         }
+        Ok(None)
     }
 
     fn merge_metadata(
@@ -450,22 +2546,7 @@ impl Server {
         repo_meta: MetadataFromRepoJSON,
         meta_json: Option<MetaJSON>,
     ) -> ServerMetadata {
-        let mut server_metadata = if let Some(meta) = meta_json {
-            ServerMetadata::from(meta)
-        } else {
-            ServerMetadata {
-                schema_version: None,
-                cvmfs_version: None,
-                last_geodb_update: MaybeRfc2822DateTime(None),
-                os_version_id: None,
-                os_pretty_name: None,
-                os_id: None,
-                administrator: None,
-                email: None,
-                organisation: None,
-                custom: None,
-            }
-        };
+        let mut server_metadata = meta_json.map(ServerMetadata::from).unwrap_or_default();
 
         server_metadata.merge_repo_metadata(repo_meta);
         server_metadata
@@ -477,18 +2558,59 @@ impl std::fmt::Display for PopulatedServer {
         write!(
             f,
             "{} ({:?}, {:?})",
-            self.hostname, self.server_type, self.backend_type
+            self.location(),
+            self.server_type,
+            self.backend_type
         )
     }
 }
 
+/// The header line of the CSV export, see `PopulatedServer::to_csv_rows`.
+pub(crate) const CSV_HEADER: &str =
+    "hostname,server_type,repo_name,revision,last_snapshot,last_gc,root_catalog_size";
+
+/// Quote a CSV field if it contains a comma, quote, or newline.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A date for the CSV export, in ISO 8601 if it can be parsed.
+fn csv_date(date: Option<&MaybeRfc2822DateTime>) -> String {
+    match date {
+        Some(date) => match date.try_into_datetime() {
+            Ok(Some(parsed)) => parsed.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            _ => date.0.clone().unwrap_or_default(),
+        },
+        None => String::new(),
+    }
+}
+
 impl PopulatedServer {
+    /// The hostname, with the port and base path if set, e.g. `cvmfs.example.org:8080/mirror`.
+    pub fn location(&self) -> String {
+        match &self.base_path {
+            Some(path) => format!(
+                "{}/{}",
+                self.hostname.as_url_authority_with_port(self.port),
+                path
+            ),
+            None => self.hostname.as_url_authority_with_port(self.port),
+        }
+    }
+
     pub fn output(&self) {
-        println!("Server: {}", self.hostname);
+        println!("Server: {}", self.location());
         println!("Type: {:?}", self.server_type);
         println!("Backend: {:?}", self.backend_type);
         if self.backend_type == ServerBackendType::AutoDetect {
             println!("Detected Backend: {:?}", self.backend_detected);
+            if let Some(fallback_error) = &self.detection.fallback_error {
+                println!("Detection fallback reason: {}", fallback_error);
+            }
         }
         if self.backend_detected != ServerBackendType::S3 {
             self.metadata.output();
@@ -501,6 +2623,12 @@ impl PopulatedServer {
         } else {
             println!("GeoAPI: Not available for S3 servers.");
         }
+        if !self.warnings.is_empty() {
+            println!("Warnings:");
+            for warning in &self.warnings {
+                println!("  {}", warning);
+            }
+        }
 
         println!("Repositories:");
         for repo in &self.repositories {
@@ -509,57 +2637,352 @@ impl PopulatedServer {
         }
     }
 
-    pub fn has_repository(&self, repository: &str) -> bool {
-        self.repositories.iter().any(|r| r.name == *repository)
+    /// Render the server as a table, for terminal output.
+    ///
+    /// The server and its metadata are listed above a table with one row per repository, giving
+    /// the name, revision, last snapshot, and last garbage collection. Requires the `table`
+    /// feature.
+    #[cfg(feature = "table")]
+    pub fn to_table(&self) -> String {
+        use comfy_table::presets::UTF8_FULL;
+        use comfy_table::{ContentArrangement, Table};
+
+        let mut header = vec![
+            format!("Server: {}", self.hostname),
+            format!("Type: {}", self.server_type),
+            format!("Backend: {:?}", self.backend_detected),
+        ];
+        if let Some(cvmfs_version) = &self.metadata.cvmfs_version {
+            header.push(format!("CVMFS Version: {}", cvmfs_version));
+        }
+        if let Some(os_pretty_name) = &self.metadata.os_pretty_name {
+            header.push(format!("OS: {}", os_pretty_name));
+        }
+        if let MaybeRfc2822DateTime(Some(last_geodb_update)) = &self.metadata.last_geodb_update {
+            header.push(format!("Last GeoDB Update: {}", last_geodb_update));
+        }
+        if let Some(organisation) = &self.metadata.organisation {
+            header.push(format!("Organisation: {}", organisation));
+        }
+        for warning in &self.warnings {
+            header.push(format!("Warning: {}", warning));
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec!["Repository", "Revision", "Last Snapshot", "Last GC"]);
+        for repo in &self.repositories {
+            table.add_row(vec![
+                repo.name.to_string(),
+                repo.revision().to_string(),
+                repo.last_snapshot
+                    .as_ref()
+                    .map(|date| date.to_string())
+                    .unwrap_or_default(),
+                repo.last_gc
+                    .as_ref()
+                    .map(|date| date.to_string())
+                    .unwrap_or_default(),
+            ]);
+        }
+
+        format!("{}\n{}", header.join("\n"), table)
     }
-}
 
-/// Metadata about the server from the repositories.json file.
+    /// The full Debug representation of the server, including every field and repository.
+    ///
+    /// The Debug implementation of PopulatedServer is abbreviated, use this when everything is
+    /// needed, e.g. `println!("{:#?}", server.debug_full())`.
+    pub fn debug_full(&self) -> impl std::fmt::Debug + '_ {
+        PopulatedServerDebugFull(self)
+    }
+
+    /// The repositories of the server as CSV lines, one per repository, optionally preceded by
+    /// the header line.
+    ///
+    /// The columns are hostname, server_type, repo_name, revision, last_snapshot, last_gc, and
+    /// root_catalog_size. Dates are given in ISO 8601 (e.g. `2024-06-18T13:40:04Z`), or as
+    /// reported if they cannot be parsed, and are empty if unset. Manifests do not record the
+    /// number of catalogs in a repository, so the size of the root catalog in bytes is given
+    /// instead. Fields containing commas, quotes, or newlines are quoted. See `fleet_to_csv` for
+    /// several servers.
+    pub fn to_csv_rows(&self, include_header: bool) -> Vec<String> {
+        let mut rows = vec![];
+        if include_header {
+            rows.push(CSV_HEADER.to_string());
+        }
+        for repo in &self.repositories {
+            let fields = [
+                self.hostname.to_string(),
+                self.server_type.to_string(),
+                repo.name.to_string(),
+                repo.revision().to_string(),
+                csv_date(repo.last_snapshot.as_ref()),
+                csv_date(repo.last_gc.as_ref()),
+                repo.manifest.b.to_string(),
+            ];
+            rows.push(
+                fields
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+        rows
+    }
+
+    /// Returns true if both results are for the same server (see `ServerIdentity`).
+    ///
+    /// Unlike `==`, this ignores the data scraped from the servers.
+    pub fn same_server(a: &PopulatedServer, b: &PopulatedServer) -> bool {
+        a.identity() == b.identity()
+    }
+
+    /// The identity of the server, see `ServerIdentity`.
+    pub fn identity(&self) -> ServerIdentity {
+        ServerIdentity::from(self)
+    }
+
+    /// The Stratum1 URLs the server recommends for client configuration, from repositories.json.
+    ///
+    /// The URLs are as published, typically with an `@fqrn@` placeholder for the repository
+    /// name, e.g. `http://stratum1.example.org/cvmfs/@fqrn@`. Empty if the server does not
+    /// publish a recommendation (or has no repositories.json), see `Server::discover_stratum1s`.
+    pub fn recommended_stratum1_list(&self) -> Vec<&str> {
+        self.metadata
+            .recommended_stratum1_list
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The names of the repositories that were scraped, in the order they were scraped.
+    pub fn scraped_repository_names(&self) -> Vec<&str> {
+        self.repositories.iter().map(|r| r.name.to_str()).collect()
+    }
+
+    pub fn has_repository(&self, repository: &str) -> bool {
+        self.repositories.iter().any(|r| r.name == *repository)
+    }
+
+    /// The names of the repositories scraped on both servers, sorted by name.
+    pub fn repositories_in_common<'a>(
+        a: &'a PopulatedServer,
+        b: &'a PopulatedServer,
+    ) -> Vec<&'a str> {
+        a.repository_name_set()
+            .intersection(&b.repository_name_set())
+            .copied()
+            .collect()
+    }
+
+    /// The names of the repositories scraped on `a` but not on `b`, sorted by name.
+    pub fn repositories_only_in<'a>(
+        a: &'a PopulatedServer,
+        b: &'a PopulatedServer,
+    ) -> Vec<&'a str> {
+        a.repository_name_set()
+            .difference(&b.repository_name_set())
+            .copied()
+            .collect()
+    }
+
+    /// Returns true if every repository scraped on `other` was also scraped on this server.
+    ///
+    /// Use this to check that a Stratum1 replicates every repository of its Stratum0.
+    pub fn has_all_repositories_of(&self, other: &PopulatedServer) -> bool {
+        PopulatedServer::repositories_only_in(other, self).is_empty()
+    }
+
+    fn repository_name_set(&self) -> std::collections::BTreeSet<&str> {
+        self.repositories.iter().map(|r| r.name.to_str()).collect()
+    }
+}
+
+/// Metadata about the server from the repositories.json file.
 ///
 /// Note that all the fields are optional. They are not set if the backend is S3, and a CVMFS server
 /// may opt not to provide some of the fields for privacy reasons.
 ///
 /// - schema_version: The schema version, typically 1
 /// - cvmfs_version: The version of CVMFS running on the server
+/// - cvmfs_version_raw: The version as reported, if it is not a valid semver version
 /// - last_geodb_update: The last time the GeoDB was updated
 /// - os_version_id: The version of the operating system
 /// - os_pretty_name: The pretty name of the operating system
 /// - os_id: The ID of the operating system (e.g. rhel)
+/// - recommended_stratum0: The Stratum0 URL recommended for client configuration
+/// - recommended_stratum1_list: The Stratum1 URLs recommended for client configuration
 #[derive(Debug, Clone, PartialEq)]
 pub struct MetadataFromRepoJSON {
     pub schema_version: Option<u32>,
     pub cvmfs_version: Option<semver::Version>,
+    pub cvmfs_version_raw: Option<String>,
     pub last_geodb_update: MaybeRfc2822DateTime,
     pub os_version_id: Option<String>,
     pub os_pretty_name: Option<String>,
     pub os_id: Option<String>,
+    pub recommended_stratum0: Option<String>,
+    pub recommended_stratum1_list: Vec<String>,
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-impl TryFrom<RepositoriesJSON> for MetadataFromRepoJSON {
-    type Error = ScrapeError;
+impl MetadataFromRepoJSON {
+    /// Extract the server metadata from a repositories.json, without failing.
+    ///
+    /// The CVMFS version is parsed according to `version_parsing`. If it cannot be parsed, it is
+    /// left unset with the raw value kept in `cvmfs_version_raw`. A schema that is missing or
+    /// could not be read (see `RepositoriesJSON::schema`) is left unset as well. A
+    /// `ScrapeWarning::MetadataConversion` is returned for each of them. Metadata is
+    /// informational, so a problem with it should never fail an otherwise healthy scrape.
+    pub fn from_repositories_json(
+        repo_json: &RepositoriesJSON,
+        version_parsing: VersionParsing,
+    ) -> (Self, Vec<ScrapeWarning>) {
+        let mut warnings = vec![];
 
-    fn try_from(repo_json: RepositoriesJSON) -> Result<Self, Self::Error> {
-        let cvmfs_version = repo_json
-            .cvmfs_version
-            .clone()
-            .map(|v| {
-                v.parse::<semver::Version>()
-                    .map_err(|e| ScrapeError::ConversionError(e.to_string()))
-            })
-            .transpose()?;
+        let schema_version = match repo_json.schema {
+            0 => {
+                warnings.push(ScrapeWarning::MetadataConversion {
+                    field: "schema".to_string(),
+                    value: repo_json.schema.to_string(),
+                    error: "missing or not a positive integer".to_string(),
+                });
+                None
+            }
+            schema => {
+                if schema != SUPPORTED_REPOSITORIES_JSON_SCHEMA {
+                    warnings.push(ScrapeWarning::UnsupportedSchema {
+                        advertised: schema,
+                        supported: SUPPORTED_REPOSITORIES_JSON_SCHEMA,
+                    });
+                }
+                Some(schema)
+            }
+        };
+
+        let (cvmfs_version, cvmfs_version_raw) = match &repo_json.cvmfs_version {
+            None => (None, None),
+            Some(raw) => match parse_cvmfs_version(raw, version_parsing) {
+                Ok(version) => (Some(version), None),
+                Err(error) => {
+                    warnings.push(ScrapeWarning::MetadataConversion {
+                        field: "cvmfs_version".to_string(),
+                        value: raw.clone(),
+                        error: error.to_string(),
+                    });
+                    (None, Some(raw.clone()))
+                }
+            },
+        };
 
-        Ok(MetadataFromRepoJSON {
-            schema_version: Some(repo_json.schema),
+        let metadata = MetadataFromRepoJSON {
+            schema_version,
             cvmfs_version,
+            cvmfs_version_raw,
             last_geodb_update: repo_json.last_geodb_update.clone(),
             os_version_id: repo_json.os_version_id.clone(),
             os_pretty_name: repo_json.os_pretty_name.clone(),
             os_id: repo_json.os_id.clone(),
-        })
+            recommended_stratum0: repo_json.recommended_stratum0.clone(),
+            recommended_stratum1_list: repo_json.recommended_stratum1_list.clone(),
+            extra: repo_json.extra.clone(),
+        };
+        (metadata, warnings)
+    }
+}
+
+impl TryFrom<RepositoriesJSON> for MetadataFromRepoJSON {
+    type Error = ScrapeError;
+
+    /// Extract the server metadata from a repositories.json, failing if the CVMFS version is not
+    /// a valid semver version.
+    ///
+    /// Deprecated: a problem with the metadata should not fail a scrape, use
+    /// `MetadataFromRepoJSON::from_repositories_json` instead. (Trait impls cannot carry a
+    /// `#[deprecated]` attribute.)
+    fn try_from(repo_json: RepositoriesJSON) -> Result<Self, Self::Error> {
+        let (metadata, _) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::Strict);
+        match metadata.cvmfs_version_raw {
+            Some(raw) => Err(ScrapeError::conversion(&raw, "Not a valid semver version")),
+            None => Ok(metadata),
+        }
+    }
+}
+
+/// Build servers of the given type from URLs such as `http://stratum1.example.org/cvmfs/@fqrn@`.
+///
+/// Invalid URLs are skipped with a warning, and servers are only listed once.
+pub(crate) fn servers_from_urls(urls: &[String], server_type: ServerType) -> Vec<Server> {
+    let mut servers: Vec<Server> = vec![];
+    for url in urls {
+        let server = match parse_server_url(url, server_type) {
+            Ok(server) => server,
+            Err(error) => {
+                warn!("Skipping recommended server {:?}: {}", url, error);
+                continue;
+            }
+        };
+        if !servers.contains(&server) {
+            servers.push(server);
+        }
+    }
+    servers
+}
+
+fn parse_server_url(url: &str, server_type: ServerType) -> Result<Server, ScrapeError> {
+    let parsed =
+        reqwest::Url::parse(url.trim()).map_err(|error| ScrapeError::conversion(url, error))?;
+    let scheme: Scheme = parsed.scheme().parse()?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| ScrapeError::conversion(url, "No host"))?;
+    let hostname = Hostname::try_from(host).map_err(|error| ScrapeError::conversion(url, error))?;
+    let base_path = parsed
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .take_while(|segment| *segment != "cvmfs")
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut server =
+        Server::new(server_type, ServerBackendType::AutoDetect, hostname).with_base_path(base_path);
+    server.scheme = Some(scheme);
+    server.port = parsed.port();
+    Ok(server)
+}
+
+/// Parse a CVMFS version, see `VersionParsing`.
+fn parse_cvmfs_version(
+    raw: &str,
+    version_parsing: VersionParsing,
+) -> Result<semver::Version, semver::Error> {
+    let strict = raw.trim().parse::<semver::Version>();
+    if strict.is_ok() || version_parsing == VersionParsing::Strict {
+        return strict;
     }
+    // Keep the leading dotted numbers, e.g. "2.11.0" from "2.11.0-1.el9_4" or "v2.9".
+    let numeric = raw
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()
+        .unwrap_or_default();
+    let mut parts = numeric.split('.').filter(|part| !part.is_empty()).take(3);
+    let Some(major) = parts.next() else {
+        return strict;
+    };
+    let minor = parts.next().unwrap_or("0");
+    let patch = parts.next().unwrap_or("0");
+    format!("{}.{}.{}", major, minor, patch).parse()
 }
 
-// Custom serializer function as semver::Version does not implement Serialize
+// Custom serializer function as semver::Version does not implement Serialize. The version is
+// written in its canonical form, e.g. "2.11.3-1".
 fn serialize_version_as_string<S>(
     version: &Option<semver::Version>,
     serializer: S,
@@ -573,15 +2996,43 @@ where
     }
 }
 
+// The counterpart of serialize_version_as_string.
+fn deserialize_version_from_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<semver::Version>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|version| version.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
 /// Merged metadata about the server from the repositories.json and meta.json files.
 ///
 /// This struct contains metadata about the server. It is a combination of the metadata from the
 /// repositories.json file and the meta.json file.
-#[derive(Debug, Clone, PartialEq, Serialize)]
+///
+/// If the CVMFS version could not be parsed, the value as reported by the server is kept in
+/// `cvmfs_version_raw`.
+///
+/// The metadata survives a JSON round-trip unchanged: the CVMFS version is serialized as its
+/// canonical semver string (e.g. "2.11.3-1"), and `last_geodb_update` as the original date string
+/// reported by the server, see `MaybeRfc2822DateTime`.
+///
+/// Fields of repositories.json and meta.json that are not otherwise modeled are kept in `extra`,
+/// keyed by their name in the document. If both documents have a field of the same name, the
+/// one from repositories.json is kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ServerMetadata {
     pub schema_version: Option<u32>,
-    #[serde(serialize_with = "serialize_version_as_string")]
+    #[serde(
+        serialize_with = "serialize_version_as_string",
+        deserialize_with = "deserialize_version_from_string"
+    )]
     pub cvmfs_version: Option<semver::Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cvmfs_version_raw: Option<String>,
     pub last_geodb_update: MaybeRfc2822DateTime,
     pub os_version_id: Option<String>,
     pub os_pretty_name: Option<String>,
@@ -590,33 +3041,55 @@ pub struct ServerMetadata {
     pub email: Option<String>,
     pub organisation: Option<String>,
     pub custom: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_stratum0: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recommended_stratum1_list: Vec<String>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl From<MetaJSON> for ServerMetadata {
     fn from(meta: MetaJSON) -> Self {
         ServerMetadata {
-            schema_version: None,
-            cvmfs_version: None,
-            last_geodb_update: MaybeRfc2822DateTime(None),
-            os_version_id: None,
-            os_pretty_name: None,
-            os_id: None,
             administrator: Some(meta.administrator),
             email: Some(meta.email),
             organisation: Some(meta.organisation),
             custom: Some(meta.custom),
+            extra: meta.extra,
+            ..Default::default()
         }
     }
 }
 
 impl ServerMetadata {
+    /// Check if the CVMFS version of the server satisfies the given requirement.
+    ///
+    /// Returns None if the version is unknown (S3 backends, servers not publishing it, or a
+    /// version that could not be parsed). CVMFS versions carry the package release as a semver
+    /// pre-release (e.g. "2.11.3-1"), which semver would exclude from most requirements. It is
+    /// therefore ignored, so "2.11.3-1" satisfies ">=2.11".
+    pub fn meets_minimum_version(&self, min: &semver::VersionReq) -> Option<bool> {
+        self.cvmfs_version.as_ref().map(|version| {
+            min.matches(&semver::Version::new(
+                version.major,
+                version.minor,
+                version.patch,
+            ))
+        })
+    }
+
     pub fn merge_repo_metadata(&mut self, repo_meta: MetadataFromRepoJSON) {
         self.schema_version = repo_meta.schema_version;
         self.cvmfs_version = repo_meta.cvmfs_version;
+        self.cvmfs_version_raw = repo_meta.cvmfs_version_raw;
         self.last_geodb_update = repo_meta.last_geodb_update;
         self.os_version_id = repo_meta.os_version_id;
         self.os_pretty_name = repo_meta.os_pretty_name;
         self.os_id = repo_meta.os_id;
+        self.recommended_stratum0 = repo_meta.recommended_stratum0;
+        self.recommended_stratum1_list = repo_meta.recommended_stratum1_list;
+        self.extra.extend(repo_meta.extra);
     }
 
     pub fn output(&self) {
@@ -627,6 +3100,9 @@ impl ServerMetadata {
         if let Some(cvmfs_version) = &self.cvmfs_version {
             println!("  CVMFS Version: {}", cvmfs_version);
         }
+        if let Some(cvmfs_version_raw) = &self.cvmfs_version_raw {
+            println!("  CVMFS Version (unparsed): {}", cvmfs_version_raw);
+        }
         if let MaybeRfc2822DateTime(Some(last_geodb_update)) = &self.last_geodb_update {
             println!("  Last GeoDB Update: {}", last_geodb_update);
         }
@@ -651,59 +3127,386 @@ impl ServerMetadata {
         if let Some(custom) = &self.custom {
             println!("  Custom: {}", custom);
         }
+        if let Some(recommended_stratum0) = &self.recommended_stratum0 {
+            println!("  Recommended Stratum0: {}", recommended_stratum0);
+        }
+        for recommended_stratum1 in &self.recommended_stratum1_list {
+            println!("  Recommended Stratum1: {}", recommended_stratum1);
+        }
+        for (key, value) in &self.extra {
+            println!("  {}: {}", key, value);
+        }
+    }
+}
+
+/// The repositories of a server, resolved and ready to be scraped by a custom scheduler.
+///
+/// Created by `Server::repository_scrapes`. Resolving the repositories fetches repositories.json
+/// as needed (detecting the backend and scheme for AutoDetect) and applies the ignored
+/// repositories, but nothing is scraped until the futures from `into_futures` are polled.
+///
+/// Fields:
+///
+/// - server: The server, with the scheme settled by backend detection.
+/// - backend_detected: The backend the repositories are scraped with (S3 or CVMFS).
+/// - repositories: The repositories to scrape, sorted by name.
+pub struct RepositoryScrapes {
+    pub server: Server,
+    pub backend_detected: ServerBackendType,
+    pub repositories: Vec<RepositoryOrReplica>,
+    client: reqwest::Client,
+    listed: std::collections::BTreeMap<String, ListedRepository>,
+}
+
+impl RepositoryScrapes {
+    /// The HTTP client the scrapes use, built from the scrape options.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// One future per repository, in the same order as `repositories`.
+    ///
+    /// The futures are independent and can be polled in any order and with any concurrency. A
+    /// future resolves to the populated repository, with its upstream URL and canonical name set
+    /// from repositories.json, or to the error scraping it.
+    pub fn into_futures(
+        self,
+    ) -> Vec<
+        impl std::future::Future<Output = Result<PopulatedRepositoryOrReplica, CVMFSScraperError>>
+            + Send
+            + 'static,
+    > {
+        let RepositoryScrapes {
+            repositories,
+            client,
+            listed,
+            ..
+        } = self;
+        repositories
+            .into_iter()
+            .map(|repo| {
+                let client = client.clone();
+                let listed = listed.get(repo.name.to_str()).cloned().unwrap_or_default();
+                async move {
+                    let mut populated = repo.scrape(&client).await?;
+                    listed.apply_to(&mut populated);
+                    Ok(populated)
+                }
+            })
+            .collect()
     }
 }
 
+/// A repository (or replica) on a server, to be scraped.
+///
+/// The backend type decides where the repository is found, see `Server::with_s3_path_prefix`.
+/// If allow_missing_status_json is set, a .cvmfs_status.json that does not exist (HTTP 404) is
+/// treated as empty rather than failing the scrape, see
+/// `ScrapeOptions::allow_missing_status_json`. It is set for S3 backends by default.
+///
+/// The whitelist of the repository is only fetched if fetch_whitelist is set, see
+/// `ScrapeOptions::fetch_whitelist`. With the `tags` feature, the named tags of the repository
+/// are also fetched if fetch_tags is set, see `ScrapeOptions::fetch_tags`.
 pub struct RepositoryOrReplica {
     pub server: Server,
-    pub name: String,
+    pub name: RepositoryName,
+    pub backend_type: ServerBackendType,
+    pub allow_missing_status_json: bool,
+    pub fetch_whitelist: bool,
+    #[cfg(feature = "tags")]
+    pub fetch_tags: bool,
 }
 
 impl RepositoryOrReplica {
-    pub fn new(name: &str, server: &Server) -> Self {
+    pub fn new(name: &RepositoryName, server: &Server) -> Self {
+        Self::for_backend(name, server, server.backend_type)
+    }
+
+    /// Create a repository on a server whose backend has been resolved (e.g. by AutoDetect).
+    pub fn for_backend(
+        name: &RepositoryName,
+        server: &Server,
+        backend_type: ServerBackendType,
+    ) -> Self {
         RepositoryOrReplica {
             server: server.clone(),
-            name: name.to_string(),
+            name: name.clone(),
+            backend_type,
+            allow_missing_status_json: backend_type == ServerBackendType::S3,
+            fetch_whitelist: false,
+            #[cfg(feature = "tags")]
+            fetch_tags: false,
         }
     }
 
+    /// Set whether a missing .cvmfs_status.json is accepted, see `allow_missing_status_json`.
+    pub fn with_missing_status_json_allowed(mut self, allow: bool) -> Self {
+        self.allow_missing_status_json = allow;
+        self
+    }
+
+    /// Set whether the whitelist is fetched when scraping, see `fetch_whitelist`.
+    pub fn with_whitelist_fetched(mut self, fetch: bool) -> Self {
+        self.fetch_whitelist = fetch;
+        self
+    }
+
+    /// Set whether the named tags are fetched when scraping, see `fetch_tags`.
+    #[cfg(feature = "tags")]
+    pub fn with_tags_fetched(mut self, fetch: bool) -> Self {
+        self.fetch_tags = fetch;
+        self
+    }
+
+    /// Fetch the named tags of the repository from its tag history database, newest revision
+    /// first.
+    ///
+    /// The database is found through the manifest (its `h` field), so the manifest is fetched
+    /// first. The database is a compressed SQLite file in the content-addressed storage of the
+    /// repository (`data/<xx>/<hash>H`), which is read in full. Only with the `tags` feature.
+    #[cfg(feature = "tags")]
+    pub async fn fetch_tag_list(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<Vec<Tag>, CVMFSScraperError> {
+        let (manifest, _) = self.fetch_repository_manifest(client).await?;
+        Ok(self.fetch_history(&manifest.h, client).await?)
+    }
+
+    #[cfg(feature = "tags")]
+    fn history_database_url(&self, hash: &HexString) -> String {
+        let hash = hash.to_string();
+        let (directory, file) = hash.split_at(2.min(hash.len()));
+        format!("{}/data/{}/{}H", self.repository_url(), directory, file)
+    }
+
+    #[cfg(feature = "tags")]
+    async fn fetch_history(
+        &self,
+        hash: &HexString,
+        client: &reqwest::Client,
+    ) -> Result<Vec<Tag>, ScrapeError> {
+        let context = || format!("repository {} on host {}", self.name, self.server.hostname);
+        let database = fetch_bytes(client, self.history_database_url(hash))
+            .await
+            .map_err(|e| e.in_document(DocumentKind::HistoryDatabase, context()))?;
+        parse_history_database(&database)
+            .map_err(|e| e.in_document(DocumentKind::HistoryDatabase, context()))
+    }
+
+    fn repository_url(&self) -> String {
+        self.server
+            .repository_url_for_backend(self.name.to_str(), self.backend_type)
+    }
+
     pub async fn scrape(
         &self,
         client: &reqwest::Client,
+    ) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
+        self.scrape_timed(client).await.0
+    }
+
+    /// As `scrape`, with the time taken to scrape the repository.
+    async fn scrape_timed(
+        &self,
+        client: &reqwest::Client,
+    ) -> (
+        Result<PopulatedRepositoryOrReplica, CVMFSScraperError>,
+        std::time::Duration,
+    ) {
+        let span = ScrapeSpan::repository(self.name.to_str());
+        let result = self.scrape_repository(client).await;
+        let duration = match &result {
+            Ok(populated) => {
+                span.set_revision(populated.revision());
+                span.finish(None)
+            }
+            Err(error) => span.finish(Some(error.to_string())),
+        };
+        (result, duration)
+    }
+
+    async fn scrape_repository(
+        &self,
+        client: &reqwest::Client,
     ) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
         let repo_status = self.fetch_repository_status_json(client).await?;
-        Ok(PopulatedRepositoryOrReplica {
-            name: self.name.clone(),
-            manifest: self.fetch_repository_manifest(client).await?,
-            last_snapshot: repo_status.last_snapshot,
-            last_gc: repo_status.last_gc,
-        })
+        let (manifest, validators) = self.fetch_repository_manifest(client).await?;
+        let mut populated =
+            PopulatedRepositoryOrReplica::from_parts(self.name.clone(), manifest, repo_status);
+        populated.manifest_validators = Some(validators).filter(|v| !v.is_empty());
+        if self.fetch_whitelist {
+            populated.whitelist = self.fetch_whitelist(client).await;
+        }
+        #[cfg(feature = "tags")]
+        if self.fetch_tags {
+            populated.tags = Some(self.fetch_history(&populated.manifest.h, client).await?);
+        }
+        Ok(populated)
+    }
+
+    /// Scrape the repository again, reusing the manifest of a previous scrape if it is unchanged.
+    ///
+    /// The manifest is fetched with a conditional request, sending back the validators (ETag and
+    /// Last-Modified) of the response the previous manifest came from, see
+    /// `PopulatedRepositoryOrReplica::manifest_validators`. If the server reports it as not
+    /// modified, the previous manifest is reused. The status and the whitelist (if fetched at all,
+    /// see `fetch_whitelist`) are always fetched, as garbage collection and resigning update
+    /// them without publishing a new revision. A previous scrape without
+    /// validators, or a server ignoring conditional requests, results in a full fetch.
+    pub async fn rescrape(
+        &self,
+        client: &reqwest::Client,
+        previous: &PopulatedRepositoryOrReplica,
+    ) -> Result<PopulatedRepositoryOrReplica, CVMFSScraperError> {
+        let Some(validators) = previous
+            .manifest_validators
+            .as_ref()
+            .filter(|validators| !validators.is_empty())
+        else {
+            trace!("No validators for {}, scraping fully", self.name);
+            let mut populated = self.scrape(client).await?;
+            ListedRepository::from(previous).apply_to(&mut populated);
+            return Ok(populated);
+        };
+
+        let repo_status = self.fetch_repository_status_json(client).await?;
+        let (manifest, validators) =
+            match fetch_text_if_changed(client, self.manifest_url(), validators).await? {
+                Some((response, validators)) => (response.parse::<Manifest>()?, validators),
+                None => {
+                    trace!("Manifest of {} is unchanged, reusing it", self.name);
+                    (previous.manifest.clone(), validators.clone())
+                }
+            };
+        let mut populated =
+            PopulatedRepositoryOrReplica::from_parts(self.name.clone(), manifest, repo_status);
+        populated.manifest_validators = Some(validators).filter(|v| !v.is_empty());
+        if self.fetch_whitelist {
+            populated.whitelist = self.fetch_whitelist(client).await;
+        }
+        ListedRepository::from(previous).apply_to(&mut populated);
+        #[cfg(feature = "tags")]
+        if self.fetch_tags {
+            // The history database is content-addressed, so unchanged tags need no fetch.
+            populated.tags = match &previous.tags {
+                Some(tags) if previous.manifest.h == populated.manifest.h => Some(tags.clone()),
+                _ => Some(self.fetch_history(&populated.manifest.h, client).await?),
+            };
+        }
+        Ok(populated)
+    }
+
+    /// Scrape several repositories concurrently, with at most `concurrency` in flight at a time.
+    ///
+    /// The results are returned in the same order as the given repositories. A failure to scrape
+    /// one repository does not stop the others from being scraped. A concurrency of 0 is treated
+    /// as 1.
+    pub async fn bulk_scrape(
+        repos: Vec<RepositoryOrReplica>,
+        client: &reqwest::Client,
+        concurrency: usize,
+    ) -> Vec<Result<PopulatedRepositoryOrReplica, CVMFSScraperError>> {
+        Self::bulk_scrape_timed(repos, client, concurrency)
+            .await
+            .into_iter()
+            .map(|(result, _)| result)
+            .collect()
+    }
+
+    /// As `bulk_scrape`, with the time taken to scrape each repository.
+    async fn bulk_scrape_timed(
+        repos: Vec<RepositoryOrReplica>,
+        client: &reqwest::Client,
+        concurrency: usize,
+    ) -> Vec<(
+        Result<PopulatedRepositoryOrReplica, CVMFSScraperError>,
+        std::time::Duration,
+    )> {
+        let mut results = repos.iter().map(|_| None).collect::<Vec<_>>();
+        let mut pending = repos.iter().enumerate();
+        let mut in_flight = FuturesUnordered::new();
+        loop {
+            while in_flight.len() < concurrency.max(1) {
+                match pending.next() {
+                    Some((index, repo)) => {
+                        in_flight.push(async move { (index, repo.scrape_timed(client).await) })
+                    }
+                    None => break,
+                }
+            }
+            match in_flight.next().await {
+                Some((index, result)) => results[index] = Some(result),
+                None => break,
+            }
+        }
+        results
+            .into_iter()
+            .map(|result| result.expect("every repository is scraped"))
+            .collect()
+    }
+
+    fn manifest_url(&self) -> String {
+        format!("{}/.cvmfspublished", self.repository_url())
+    }
+
+    fn status_json_url(&self) -> String {
+        format!("{}/.cvmfs_status.json", self.repository_url())
+    }
+
+    fn whitelist_url(&self) -> String {
+        format!("{}/.cvmfswhitelist", self.repository_url())
+    }
+
+    /// Fetch the whitelist of the repository, if the server has a parsable one.
+    ///
+    /// The whitelist is only used for health checks, so a missing or invalid whitelist does not
+    /// fail the scrape.
+    async fn fetch_whitelist(&self, client: &reqwest::Client) -> Option<Whitelist> {
+        let result = match fetch_text(client, self.whitelist_url()).await {
+            Ok(response) => response.parse::<Whitelist>().map_err(|e| e.to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        result
+            .map_err(|error| {
+                debug!(
+                    "No whitelist for {} on {}: {}",
+                    self.name, self.server.hostname, error
+                )
+            })
+            .ok()
     }
 
     async fn fetch_repository_manifest(
         &self,
         client: &reqwest::Client,
-    ) -> Result<Manifest, ManifestError> {
-        let url = format!(
-            "http://{}/cvmfs/{}/.cvmfspublished",
-            self.server.hostname, self.name
-        );
-        let response = client.get(url).send().await?;
-        response.error_for_status()?.text().await?.parse()
+    ) -> Result<(Manifest, CacheValidators), CVMFSScraperError> {
+        let (response, validators) =
+            fetch_text_with_validators(client, self.manifest_url()).await?;
+        Ok((response.parse::<Manifest>()?, validators))
     }
 
     async fn fetch_repository_status_json(
         &self,
         client: &reqwest::Client,
     ) -> Result<StatusJSON, ScrapeError> {
-        fetch_json(
+        let result = fetch_document(
             client,
-            format!(
-                "http://{}/cvmfs/{}/.cvmfs_status.json",
-                self.server.hostname, self.name
-            ),
+            self.status_json_url(),
+            DocumentKind::StatusJson,
+            &format!("repository {} on host {}", self.name, self.server.hostname),
         )
-        .await
+        .await;
+        match result {
+            Err(error) if self.allow_missing_status_json && error.is_not_found() => {
+                debug!(
+                    "No .cvmfs_status.json for {} on {}, treating it as empty",
+                    self.name, self.server.hostname
+                );
+                Ok(StatusJSON::default())
+            }
+            result => result,
+        }
     }
 }
 
@@ -719,57 +3522,422 @@ impl RepositoryOrReplica {
 /// - manifest: The manifest of the repository
 /// - last_snapshot: The last time a snapshot was taken (optional)
 /// - last_gc: The last time garbage collection was run (optional)
+/// - last_snapshot_start: The time the last snapshot was started (optional, Stratum0 only)
+/// - last_check: The last time `cvmfs_server check` was run (optional)
+/// - check_status: The outcome of the last check, e.g. "succeeded" or "failed" (optional)
+/// - upstream_url: The URL of the upstream (Stratum0) repository, if the server's
+///   repositories.json lists it for this replica (optional)
+/// - canonical_name: The canonical name of the repository, if the server's repositories.json
+///   lists this name as an alias of it (optional)
+/// - tags: The named tags of the repository, newest revision first, if they were fetched
+///   (optional), see `ScrapeOptions::fetch_tags` and `latest_tag`
+/// - manifest_validators: The validators (ETag and Last-Modified) of the response the manifest
+///   came from, if the server sent any (optional), see `RepositoryOrReplica::rescrape`
+/// - whitelist: The whitelist of the repository, if fetched (see `ScrapeOptions::fetch_whitelist`)
+///   and the server has a parsable one (optional), see `whitelist_remaining`
 ///
 /// The MaybeRfc2822DateTime type is used to represent a date and time that may or may not be present,
 /// and may or may not be in the RFC 2822 format. See the documentation for the MaybeRfc2822DateTime
 /// type for more information.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PopulatedRepositoryOrReplica {
-    pub name: String,
+    pub name: RepositoryName,
     pub manifest: Manifest,
     pub last_snapshot: Option<MaybeRfc2822DateTime>,
     pub last_gc: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub last_snapshot_start: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub last_check: Option<MaybeRfc2822DateTime>,
+    #[serde(default)]
+    pub check_status: Option<String>,
+    #[serde(default)]
+    pub upstream_url: Option<String>,
+    #[serde(default)]
+    pub canonical_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<Tag>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_validators: Option<CacheValidators>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub whitelist: Option<Whitelist>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl PopulatedRepositoryOrReplica {
+    /// Build a populated repository without scraping, for tests of code using scrape results.
+    ///
+    /// The manifest has the given revision and publication time, and a fixed root catalog hash.
+    /// The status fields (last_snapshot, last_gc, last_check, etc.) are unset. Only
+    /// available with the `test-util` feature, see `PopulatedServer::mock`.
+    ///
+    /// Panics if the name is not a valid `RepositoryName`.
+    pub fn mock(name: &str, revision: u64, published: DateTime<Utc>) -> Self {
+        PopulatedRepositoryOrReplica {
+            name: RepositoryName::try_from(name).expect("a valid repository name"),
+            manifest: Manifest::builder()
+                .with_name(name)
+                .with_root_catalog_hash(
+                    crate::models::HexString::new("600230b0ba7620426f2e898f1e1f43c5466efe59")
+                        .unwrap(),
+                )
+                .with_revision(revision)
+                .with_timestamp(published)
+                .build()
+                .expect("a manifest with every required field"),
+            last_snapshot: None,
+            last_gc: None,
+            last_snapshot_start: None,
+            last_check: None,
+            check_status: None,
+            upstream_url: None,
+            canonical_name: None,
+            tags: None,
+            manifest_validators: None,
+            whitelist: None,
+        }
+    }
+}
+
+impl PopulatedRepositoryOrReplica {
+    pub(crate) fn from_parts(
+        name: RepositoryName,
+        manifest: Manifest,
+        repo_status: StatusJSON,
+    ) -> Self {
+        PopulatedRepositoryOrReplica {
+            name,
+            manifest,
+            last_snapshot: repo_status.last_snapshot,
+            last_gc: repo_status.last_gc,
+            last_snapshot_start: repo_status.last_snapshot_start,
+            last_check: repo_status.last_check,
+            check_status: repo_status.check_status,
+            upstream_url: None,
+            canonical_name: None,
+            tags: None,
+            manifest_validators: None,
+            whitelist: None,
+        }
+    }
+}
+
+/// The number of characters of the root catalog hash used in a revision tag.
+pub const REVISION_TAG_HASH_LENGTH: usize = 7;
+
+/// Format a revision tag, see `PopulatedRepositoryOrReplica::revision_tag`.
+fn format_revision_tag(revision: u64, root_hash: &str) -> String {
+    let prefix = root_hash
+        .char_indices()
+        .nth(REVISION_TAG_HASH_LENGTH)
+        .map_or(root_hash, |(end, _)| &root_hash[..end]);
+    format!("r{}@{}", revision, prefix)
 }
 
 impl PopulatedRepositoryOrReplica {
     pub fn output(&self) {
-        if self.last_gc.is_some() {
-            println!("  Last Snapshot: {}", self.last_snapshot.as_ref().unwrap());
+        if let Some(last_snapshot) = &self.last_snapshot {
+            println!("  Last Snapshot: {}", last_snapshot);
+        }
+        if let Some(last_gc) = &self.last_gc {
+            println!("  Last GC: {}", last_gc);
         }
-        if self.last_gc.is_some() {
-            println!("  Last GC: {}", self.last_gc.as_ref().unwrap());
+        if let Some(last_check) = &self.last_check {
+            match &self.check_status {
+                Some(status) => println!("  Last Check: {} ({})", last_check, status),
+                None => println!("  Last Check: {}", last_check),
+            }
+        }
+        if let Some(upstream_url) = &self.upstream_url {
+            println!("  Upstream: {}", upstream_url);
+        }
+        if let Some(canonical_name) = &self.canonical_name {
+            println!("  Alias of: {}", canonical_name);
+        }
+        if let Some(tags) = &self.tags {
+            match self.latest_tag() {
+                Some(tag) => println!("  Tags: {} (latest: {})", tags.len(), tag.name),
+                None => println!("  Tags: 0"),
+            }
         }
         self.manifest.output();
     }
-    pub fn revision(&self) -> i32 {
+    /// The tag of the newest revision, if tags were fetched and there are any.
+    pub fn latest_tag(&self) -> Option<&Tag> {
+        self.tags.as_ref()?.first()
+    }
+
+    pub fn revision(&self) -> u64 {
         self.manifest.s
     }
-}
-#[cfg(test)]
-mod test {
-    use super::*;
-    use serde_json::{json, Value};
-    use yare::parameterized;
 
-    #[parameterized(
-        test_full_data = {
-            Some(1),
-            Some("2.8.4"),
-            Some("Wed, 21 Oct 2015 07:28:00 GMT"),
-            Some("rhel7"),
-            Some("Red Hat Enterprise Linux 7"),
-            Some("rhel"),
-            Some("admin"),
-            Some("admin@host.com"),
-            Some("host.com"),
-            None // custom field
-        },
-        test_minimal_data = {
-            None, None, None, None, None, None, None, None, None, None
-        },
-        test_custom_data = {
-            None, None, None, None, None, None, None, None, None, Some(json!({"key": "value"}))
-        }
+    /// Returns true if the last `cvmfs_server check` succeeded, false if it did not, and None if
+    /// the server does not report checks.
+    pub fn check_succeeded(&self) -> Option<bool> {
+        self.check_status
+            .as_deref()
+            .map(|status| status.eq_ignore_ascii_case("succeeded"))
+    }
+
+    /// The revision and the root catalog hash, which together identify the published state of
+    /// the repository.
+    pub fn revision_and_root_hash(&self) -> (u64, String) {
+        (self.revision(), self.manifest.c.to_string())
+    }
+
+    /// A compact identifier for the published state of the repository, e.g. `r12345@600230b`.
+    ///
+    /// The identifier is the revision and the first `REVISION_TAG_HASH_LENGTH` characters of the
+    /// root catalog hash. Replicas in sync report the same tag, which makes it useful for
+    /// correlating servers and for concise log lines.
+    pub fn revision_tag(&self) -> String {
+        let (revision, root_hash) = self.revision_and_root_hash();
+        format_revision_tag(revision, &root_hash)
+    }
+
+    /// The revision as an i32, saturating at i32::MAX.
+    #[deprecated(
+        since = "0.0.6",
+        note = "revisions are unsigned and may exceed i32::MAX, use revision() instead"
+    )]
+    pub fn revision_i32(&self) -> i32 {
+        i32::try_from(self.manifest.s).unwrap_or(i32::MAX)
+    }
+
+    /// Returns true if the repository reports when garbage collection last ran.
+    ///
+    /// Repositories that never garbage collect do not report last_gc, so its presence is taken
+    /// as a sign that garbage collection is enabled.
+    pub fn gc_enabled(&self) -> bool {
+        self.last_gc.as_ref().is_some_and(|gc| gc.is_some())
+    }
+
+    /// The time since garbage collection last ran, if known and parsable.
+    pub fn gc_age(&self) -> Option<chrono::Duration> {
+        let last_gc = self.last_gc.as_ref()?.try_into_datetime().ok()??;
+        Some(Utc::now() - last_gc)
+    }
+
+    /// The publication time of the current revision, from the manifest (see
+    /// `Manifest::publish_timestamp`).
+    pub fn publish_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.manifest.publish_timestamp()
+    }
+
+    /// The time of the last snapshot, from .cvmfs_status.json if known and parsable, otherwise
+    /// the publication time of the manifest.
+    pub fn last_snapshot_time(&self) -> Option<DateTime<Utc>> {
+        match self.last_snapshot.as_ref().map(|s| s.try_into_datetime()) {
+            Some(Ok(Some(last_snapshot))) => Some(last_snapshot),
+            _ => self.publish_timestamp(),
+        }
+    }
+
+    /// The time since the last snapshot (see `last_snapshot_time`).
+    ///
+    /// A snapshot time in the future (see `clock_skew`) gives an age of zero, never a negative
+    /// age.
+    pub fn snapshot_age(&self) -> Option<chrono::Duration> {
+        let age = Utc::now() - self.last_snapshot_time()?;
+        Some(age.max(chrono::Duration::zero()))
+    }
+
+    /// How far the manifest publication time or the last snapshot is in the future.
+    ///
+    /// Returns None if neither is in the future relative to the local clock. A future timestamp
+    /// means that the clock of the server (or of the scraper) is off, or that the server reports
+    /// times in the wrong time zone.
+    pub fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.clock_skew_at(Utc::now())
+    }
+
+    fn clock_skew_at(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        let published = self.publish_timestamp();
+        let last_snapshot = match self.last_snapshot.as_ref().map(|s| s.try_into_datetime()) {
+            Some(Ok(Some(last_snapshot))) => Some(last_snapshot),
+            _ => None,
+        };
+        let newest = published.into_iter().chain(last_snapshot).max()?;
+        Some(newest - now).filter(|skew| *skew > chrono::Duration::zero())
+    }
+
+    /// The time it took to publish the last snapshot, from last_snapshot_start to last_snapshot.
+    ///
+    /// Returns None unless both times are present and parsable. While a snapshot is in
+    /// progress, last_snapshot_start is newer than last_snapshot, and there is no duration to
+    /// report, so None is returned then as well. Long publish durations may indicate catalog
+    /// issues or network problems during replication.
+    pub fn publish_duration(&self) -> Option<chrono::Duration> {
+        let start = self
+            .last_snapshot_start
+            .as_ref()?
+            .try_into_datetime()
+            .ok()??;
+        let end = self.last_snapshot.as_ref()?.try_into_datetime().ok()??;
+        Some(end - start).filter(|duration| *duration >= chrono::Duration::zero())
+    }
+
+    /// Returns true if the last snapshot took longer than `threshold` to publish.
+    ///
+    /// Repositories without a known publish duration are never considered slow.
+    pub fn is_publish_slow(&self, threshold: chrono::Duration) -> bool {
+        self.publish_duration()
+            .is_some_and(|duration| duration > threshold)
+    }
+
+    /// The time left until the whitelist expires, negative once it has expired.
+    ///
+    /// Returns None if the whitelist was not fetched or not found. Clients refuse a repository with an expired
+    /// whitelist, see `HealthConfig::whitelist_warning`.
+    pub fn whitelist_remaining(&self) -> Option<chrono::Duration> {
+        Some(self.whitelist.as_ref()?.remaining_at(Utc::now()))
+    }
+
+    /// Returns true if garbage collection ran after the last snapshot.
+    ///
+    /// Returns None unless both last_gc and last_snapshot are present and parsable. Garbage
+    /// collection running after the last snapshot may indicate a stuck publisher, or a garbage
+    /// collection run without new content to replicate.
+    pub fn gc_after_snapshot(&self) -> Option<bool> {
+        let last_gc = self.last_gc.as_ref()?.try_into_datetime().ok()??;
+        let last_snapshot = self.last_snapshot.as_ref()?.try_into_datetime().ok()??;
+        Some(last_gc > last_snapshot)
+    }
+
+    /// Returns true if the upstream has published a revision that this replica has not
+    /// snapshotted yet.
+    ///
+    /// `upstream_revision` is the revision of the repository on its Stratum0, e.g. from a scrape
+    /// of the Stratum0 (see `pending_snapshots`). A replica behind its Stratum0 has a snapshot
+    /// pending or stuck, which is more precise than judging by the age of the last snapshot. A
+    /// replica ahead of the given revision (e.g. of an older scrape) is not behind.
+    pub fn pending_snapshot(&self, upstream_revision: u64) -> bool {
+        upstream_revision > self.revision()
+    }
+
+    /// Returns true if garbage collection appears to have stalled.
+    ///
+    /// Garbage collection is considered stalled if it has not run for longer than `max_age`
+    /// while new revisions have been published since it last ran. Repositories without (parsable)
+    /// garbage collection information are never considered stalled.
+    pub fn gc_stalled(&self, max_age: chrono::Duration) -> bool {
+        let last_gc = match self.last_gc.as_ref().map(|gc| gc.try_into_datetime()) {
+            Some(Ok(Some(last_gc))) => last_gc,
+            _ => return false,
+        };
+        let published_since_gc = self
+            .publish_timestamp()
+            .is_some_and(|published| published > last_gc);
+        published_since_gc && Utc::now() - last_gc > max_age
+    }
+
+    /// The health of the repository, judged by the thresholds in `config`.
+    ///
+    /// The repository is critical if its last snapshot (see `snapshot_age`) is older than
+    /// `stale_critical`, if the last `cvmfs_server check` failed, or if its whitelist has
+    /// expired. Warnings are given for a last snapshot older than `stale_warning`, a whitelist
+    /// expiring within `whitelist_warning`, a slow publish, stalled garbage collection, and
+    /// clock skew. Checks that need data the server does not report are skipped. Use
+    /// `health_status_with_upstream` to also check for a pending snapshot.
+    pub fn health_status(&self, config: &HealthConfig) -> RepoHealth {
+        self.health_status_with_upstream(config, None)
+    }
+
+    /// As `health_status`, also giving a warning if the replica is behind the given revision of
+    /// its Stratum0 (see `pending_snapshot`). The check is skipped if `upstream_revision` is None.
+    pub fn health_status_with_upstream(
+        &self,
+        config: &HealthConfig,
+        upstream_revision: Option<u64>,
+    ) -> RepoHealth {
+        let mut critical = vec![];
+        let mut warnings = vec![];
+        if let Some(age) = self.snapshot_age() {
+            let issue = format!("Last snapshot is {} hours old", age.num_hours());
+            if age > config.stale_critical {
+                critical.push(issue);
+            } else if age > config.stale_warning {
+                warnings.push(issue);
+            }
+        }
+        if self.check_succeeded() == Some(false) {
+            critical.push(format!(
+                "Last check did not succeed: {}",
+                self.check_status.as_deref().unwrap_or_default()
+            ));
+        }
+        if let Some(remaining) = self.whitelist_remaining() {
+            if remaining <= chrono::Duration::zero() {
+                critical.push(format!(
+                    "Whitelist expired {} hours ago",
+                    (-remaining).num_hours()
+                ));
+            } else if remaining < config.whitelist_warning {
+                warnings.push(format!(
+                    "Whitelist expires in {} hours",
+                    remaining.num_hours()
+                ));
+            }
+        }
+        if let Some(duration) = self
+            .publish_duration()
+            .filter(|duration| *duration > config.max_publish_duration)
+        {
+            warnings.push(format!(
+                "Last publish took {} minutes",
+                duration.num_minutes()
+            ));
+        }
+        if self.gc_stalled(config.max_gc_age) {
+            warnings.push("Garbage collection has stalled".to_string());
+        }
+        if let Some(upstream_revision) =
+            upstream_revision.filter(|revision| self.pending_snapshot(*revision))
+        {
+            warnings.push(format!(
+                "Revision {} of the Stratum0 has not been snapshotted (at revision {})",
+                upstream_revision,
+                self.revision()
+            ));
+        }
+        if let Some(skew) = self.clock_skew() {
+            warnings.push(format!(
+                "Timestamps are {}s in the future",
+                skew.num_seconds()
+            ));
+        }
+        RepoHealth::from_issues(critical, warnings)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::DEFAULT_INFO_PATH;
+    use crate::errors::RepositoryNameError;
+    use crate::options::RedirectPolicy;
+    use crate::test_utilities::{self, ok_response};
+    use serde_json::{json, Value};
+    use yare::parameterized;
+
+    #[parameterized(
+        test_full_data = {
+            Some(1),
+            Some("2.8.4"),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some("rhel7"),
+            Some("Red Hat Enterprise Linux 7"),
+            Some("rhel"),
+            Some("admin"),
+            Some("admin@host.com"),
+            Some("host.com"),
+            None // custom field
+        },
+        test_minimal_data = {
+            None, None, None, None, None, None, None, None, None, None
+        },
+        test_custom_data = {
+            None, None, None, None, None, None, None, None, None, Some(json!({"key": "value"}))
+        }
     )]
     fn test_serialization_of_metadata(
         schema_version: Option<u32>,
@@ -795,6 +3963,7 @@ mod test {
             email: email.map(|s| s.to_string()),
             organisation: organisation.map(|s| s.to_string()),
             custom: custom.clone(),
+            ..Default::default()
         };
 
         // Build the expected JSON
@@ -817,4 +3986,2484 @@ mod test {
         // Compare the actual JSON with the expected JSON
         assert_eq!(json, expected);
     }
+
+    fn inventory_server(backend_type: ServerBackendType) -> Server {
+        Server::new(
+            ServerType::Stratum1,
+            backend_type,
+            Hostname::try_from("cvmfs.example.org").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_url_inventory_cvmfs_backend() {
+        let server = inventory_server(ServerBackendType::CVMFS);
+        let urls = server.url_inventory(&["software.eessi.io", "dev.eessi.io"]);
+        assert_eq!(
+            urls,
+            vec![
+                "http://cvmfs.example.org/cvmfs/dev.eessi.io/.cvmfs_status.json",
+                "http://cvmfs.example.org/cvmfs/dev.eessi.io/.cvmfspublished",
+                "http://cvmfs.example.org/cvmfs/info/v1/meta.json",
+                "http://cvmfs.example.org/cvmfs/info/v1/repositories.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfs_status.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished",
+            ]
+        );
+    }
+
+    #[parameterized(
+        bare = { "mirror" },
+        slashes = { "/mirror/" },
+    )]
+    fn test_url_inventory_base_path(base_path: &str) {
+        let server = inventory_server(ServerBackendType::CVMFS).with_base_path(base_path);
+        let urls = server.url_inventory(&["software.eessi.io"]);
+        assert_eq!(
+            urls,
+            vec![
+                "http://cvmfs.example.org/mirror/cvmfs/info/v1/meta.json",
+                "http://cvmfs.example.org/mirror/cvmfs/info/v1/repositories.json",
+                "http://cvmfs.example.org/mirror/cvmfs/software.eessi.io/.cvmfs_status.json",
+                "http://cvmfs.example.org/mirror/cvmfs/software.eessi.io/.cvmfspublished",
+            ]
+        );
+        assert_eq!(
+            inventory_server(ServerBackendType::CVMFS)
+                .with_base_path("/")
+                .base_path,
+            None
+        );
+    }
+
+    #[test]
+    fn test_url_inventory_s3_backend_skips_repositories_json() {
+        let server = inventory_server(ServerBackendType::S3);
+        let urls = server.url_inventory(&["software.eessi.io"]);
+        assert!(!urls.iter().any(|u| u.ends_with("repositories.json")));
+        assert_eq!(urls.len(), 3);
+    }
+
+    #[test]
+    fn test_url_inventory_with_whitelist() {
+        let server = inventory_server(ServerBackendType::S3);
+        let options = ScrapeOptions {
+            fetch_whitelist: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            server.url_inventory_with_options(&["software.eessi.io"], &options),
+            vec![
+                "http://cvmfs.example.org/cvmfs/info/v1/meta.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfs_status.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfswhitelist",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_url_inventory_deduplicates() {
+        let server = inventory_server(ServerBackendType::AutoDetect);
+        let urls = server.url_inventory(&["software.eessi.io", "software.eessi.io"]);
+        assert_eq!(urls.len(), 4);
+    }
+
+    #[test]
+    fn test_plan_cvmfs_backend() {
+        let server = inventory_server(ServerBackendType::CVMFS);
+        let options = ScrapeOptions {
+            ignored_repositories: vec!["dev.eessi.io".to_string()],
+            geoapi_servers: vec![Hostname::try_from("geo.example.org").unwrap()],
+            fetch_whitelist: true,
+            ..Default::default()
+        };
+        let plan = server.plan_with_options(&["software.eessi.io", "dev.eessi.io"], &options);
+        let urls = plan.iter().map(|r| r.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "http://cvmfs.example.org/cvmfs/info/v1/repositories.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfs_status.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfswhitelist",
+                "http://cvmfs.example.org/cvmfs/*",
+                "http://cvmfs.example.org/cvmfs/info/v1/meta.json",
+                "http://cvmfs.example.org/cvmfs/software.eessi.io/api/v1.0/geo/*/geo.example.org",
+            ]
+        );
+        assert_eq!(plan[4].kind, PlannedRequestKind::DiscoveredRepositories);
+    }
+
+    #[test]
+    fn test_plan_autodetect_probes_https() {
+        let server = inventory_server(ServerBackendType::AutoDetect);
+        let plan = server.plan(&["software.eessi.io"]);
+        assert_eq!(
+            plan[..2]
+                .iter()
+                .map(|r| (r.url.as_str(), r.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "https://cvmfs.example.org/cvmfs/info/v1/repositories.json",
+                    PlannedRequestKind::RepositoriesJson
+                ),
+                (
+                    "http://cvmfs.example.org/cvmfs/info/v1/repositories.json",
+                    PlannedRequestKind::RepositoriesJson
+                ),
+            ]
+        );
+
+        // An explicit scheme disables the HTTPS probe.
+        let explicit = server
+            .with_scheme(Scheme::Http)
+            .plan(&["software.eessi.io"]);
+        assert_eq!(
+            explicit
+                .iter()
+                .filter(|r| r.kind == PlannedRequestKind::RepositoriesJson)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_plan_s3_backend() {
+        let server = inventory_server(ServerBackendType::S3).with_s3_path_prefix("repos");
+        let plan = server.plan(&["software.eessi.io"]);
+        let kinds = plan.iter().map(|r| r.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                PlannedRequestKind::Manifest,
+                PlannedRequestKind::StatusJson,
+                PlannedRequestKind::MetaJson,
+            ]
+        );
+        assert_eq!(
+            plan[0].url,
+            "http://cvmfs.example.org/repos/software.eessi.io/.cvmfspublished"
+        );
+    }
+
+    #[parameterized(
+        no_prefix = { ServerBackendType::S3, None, "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished" },
+        prefix = { ServerBackendType::S3, Some("/repos/cvmfs/"), "http://cvmfs.example.org/repos/cvmfs/software.eessi.io/.cvmfspublished" },
+        empty_prefix = { ServerBackendType::S3, Some(""), "http://cvmfs.example.org/software.eessi.io/.cvmfspublished" },
+        prefix_ignored_for_cvmfs = { ServerBackendType::CVMFS, Some("repos"), "http://cvmfs.example.org/cvmfs/software.eessi.io/.cvmfspublished" },
+    )]
+    fn test_s3_path_prefix(backend_type: ServerBackendType, prefix: Option<&str>, expected: &str) {
+        let mut server = inventory_server(backend_type);
+        if let Some(prefix) = prefix {
+            server = server.with_s3_path_prefix(prefix);
+        }
+        let urls = server.url_inventory(&["software.eessi.io"]);
+        assert!(urls.contains(&expected.to_string()), "{:?}", urls);
+    }
+
+    #[parameterized(
+        default = { DEFAULT_INFO_PATH, "http://cvmfs.example.org/cvmfs/info/v1/meta.json" },
+        custom = { "/info/v2/", "http://cvmfs.example.org/cvmfs/info/v2/meta.json" },
+    )]
+    fn test_info_path(info_path: &str, expected: &str) {
+        let server = inventory_server(ServerBackendType::CVMFS);
+        assert_eq!(server.meta_json_url(info_path), expected);
+        let options = ScrapeOptions {
+            info_path: info_path.to_string(),
+            ..Default::default()
+        };
+        assert!(server
+            .url_inventory_with_options(&[], &options)
+            .contains(&expected.to_string()));
+    }
+
+    #[parameterized(
+        unchanged = { "software.eessi.io", "software.eessi.io" },
+        uppercase = { "Software.EESSI.io", "software.eessi.io" },
+        trailing_dot = { "software.eessi.io.", "software.eessi.io" },
+        whitespace = { " software.eessi.io ", "software.eessi.io" },
+    )]
+    fn test_normalize_repository_name(name: &str, expected: &str) {
+        assert_eq!(normalize_repository_name(name), expected);
+    }
+
+    #[test]
+    fn test_discovered_repositories_are_normalized() {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": 1,
+            "repositories": [],
+            "replicas": [
+                { "name": "Software.EESSI.io", "url": "/cvmfs/software.eessi.io" },
+                { "name": "dev.eessi.io.", "url": "/cvmfs/dev.eessi.io" }
+            ]
+        }))
+        .unwrap();
+        let discovered = discovered_repositories(&repo_json);
+        assert_eq!(discovered["software.eessi.io"], "Software.EESSI.io");
+        assert_eq!(discovered["dev.eessi.io"], "dev.eessi.io");
+    }
+
+    #[test]
+    fn test_listed_repositories() {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": 1,
+            "repositories": [],
+            "replicas": [
+                { "name": "software.eessi.io", "url": "/cvmfs/software.eessi.io",
+                  "upstream": "http://rs0.eessi.io/cvmfs/software.eessi.io" },
+                { "name": "EESSI.io", "url": "/cvmfs/eessi.io", "alias_of": "Software.EESSI.io",
+                  "upstream": "http://rs0.eessi.io/cvmfs/eessi.io" },
+                { "name": "dev.eessi.io", "url": "/cvmfs/dev.eessi.io" }
+            ]
+        }))
+        .unwrap();
+        let listed = listed_repositories(&repo_json);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed["software.eessi.io"].canonical_name, None);
+        assert_eq!(
+            listed["eessi.io"],
+            ListedRepository {
+                upstream_url: Some("http://rs0.eessi.io/cvmfs/eessi.io".to_string()),
+                canonical_name: Some("Software.EESSI.io".to_string()),
+            }
+        );
+    }
+
+    fn empty_repositories_json() -> RepositoriesJSON {
+        serde_json::from_value(json!({
+            "schema": 1,
+            "repositories": [],
+            "replicas": []
+        }))
+        .unwrap()
+    }
+
+    #[parameterized(
+        valid = { json!(1), "2.11.3-1", Some(1), true, 0 },
+        numeric_string_schema = { json!("1"), "2.11.3", Some(1), true, 0 },
+        invalid_schema = { json!("v1"), "2.11.3", None, true, 1 },
+        invalid_version = { json!(1), "2.11.3.0", Some(1), false, 1 },
+        both_invalid = { json!(-1), "latest", None, false, 2 },
+    )]
+    fn test_metadata_from_repositories_json_is_lossy(
+        schema: Value,
+        cvmfs_version: &str,
+        expected_schema: Option<u32>,
+        version_parses: bool,
+        expected_warnings: usize,
+    ) {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": schema,
+            "cvmfs_version": cvmfs_version,
+            "os_id": "rhel",
+            "repositories": [],
+            "replicas": []
+        }))
+        .unwrap();
+        let (metadata, warnings) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::Strict);
+
+        assert_eq!(metadata.schema_version, expected_schema);
+        assert_eq!(metadata.cvmfs_version.is_some(), version_parses);
+        if !version_parses {
+            assert_eq!(metadata.cvmfs_version_raw, Some(cvmfs_version.to_string()));
+        }
+        assert_eq!(metadata.os_id, Some("rhel".to_string()));
+        assert_eq!(warnings.len(), expected_warnings);
+        assert!(warnings
+            .iter()
+            .all(|w| matches!(w, ScrapeWarning::MetadataConversion { .. })));
+    }
+
+    #[parameterized(
+        supported = { json!(1), vec![] },
+        newer = { json!(2), vec![ScrapeWarning::UnsupportedSchema { advertised: 2, supported: 1 }] },
+        newer_as_string = { json!("3"), vec![ScrapeWarning::UnsupportedSchema { advertised: 3, supported: 1 }] },
+        missing = { Value::Null, vec![ScrapeWarning::MetadataConversion {
+            field: "schema".to_string(),
+            value: "0".to_string(),
+            error: "missing or not a positive integer".to_string(),
+        }] },
+    )]
+    fn test_unsupported_schema_warning(schema: Value, expected: Vec<ScrapeWarning>) {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": schema,
+            "repositories": [],
+            "replicas": []
+        }))
+        .unwrap();
+        let (_, warnings) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::default());
+        assert_eq!(warnings, expected);
+    }
+
+    #[parameterized(
+        strict_semver = { "2.11.3", VersionParsing::Strict, Some("2.11.3") },
+        strict_release = { "2.11.0-1", VersionParsing::Strict, Some("2.11.0-1") },
+        strict_short = { "2.9", VersionParsing::Strict, None },
+        lenient_release = { "2.11.0-1", VersionParsing::Lenient, Some("2.11.0-1") },
+        lenient_short = { "2.9", VersionParsing::Lenient, Some("2.9.0") },
+        lenient_major_only = { "3", VersionParsing::Lenient, Some("3.0.0") },
+        lenient_distro_suffix = { "2.11.0-1.el9_4", VersionParsing::Lenient, Some("2.11.0") },
+        lenient_four_components = { "2.12.0.0", VersionParsing::Lenient, Some("2.12.0") },
+        lenient_prefix = { "v2.10.1", VersionParsing::Lenient, Some("2.10.1") },
+        lenient_garbage = { "not a version", VersionParsing::Lenient, None },
+        lenient_empty = { "", VersionParsing::Lenient, None },
+    )]
+    fn test_parse_cvmfs_version(
+        raw: &str,
+        version_parsing: VersionParsing,
+        expected: Option<&str>,
+    ) {
+        assert_eq!(
+            parse_cvmfs_version(raw, version_parsing)
+                .ok()
+                .map(|version| version.to_string()),
+            expected.map(|version| version.to_string())
+        );
+    }
+
+    #[test]
+    fn test_metadata_try_from_repositories_json() {
+        let mut repo_json = empty_repositories_json();
+        repo_json.cvmfs_version = Some("2.11.3-1".to_string());
+        let metadata = MetadataFromRepoJSON::try_from(repo_json.clone()).unwrap();
+        assert_eq!(metadata.schema_version, Some(1));
+
+        repo_json.cvmfs_version = Some("2.11.3.0".to_string());
+        assert!(MetadataFromRepoJSON::try_from(repo_json).is_err());
+    }
+
+    #[test]
+    fn test_lenient_version_parsing_does_not_warn() {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": 1,
+            "cvmfs_version": "2.9",
+            "repositories": [],
+            "replicas": []
+        }))
+        .unwrap();
+        let (metadata, warnings) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::Lenient);
+        assert_eq!(metadata.cvmfs_version, Some(semver::Version::new(2, 9, 0)));
+        assert_eq!(metadata.cvmfs_version_raw, None);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_metadata_raw_values_are_serialized_only_when_set() {
+        let mut metadata = ServerMetadata::default();
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert!(json.get("cvmfs_version_raw").is_none());
+
+        metadata.cvmfs_version_raw = Some("2.11.3.0".to_string());
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["cvmfs_version_raw"], "2.11.3.0");
+        assert_eq!(json["cvmfs_version"], Value::Null);
+    }
+
+    #[test]
+    fn test_metadata_json_roundtrip_is_lossless() {
+        let date = "fre. 21. juni 17:40:02 +0200 2024";
+        let metadata = ServerMetadata {
+            schema_version: Some(1),
+            cvmfs_version: Some(semver::Version::parse("2.11.3-1").unwrap()),
+            last_geodb_update: MaybeRfc2822DateTime(Some(date.to_string())),
+            organisation: Some("EESSI".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert!(json.contains(r#""cvmfs_version":"2.11.3-1""#), "{}", json);
+        assert!(
+            json.contains(&format!(r#""last_geodb_update":"{}""#, date)),
+            "{}",
+            json
+        );
+
+        let parsed: ServerMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, metadata);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn test_unknown_metadata_fields_are_kept() {
+        let repo_json: RepositoriesJSON = serde_json::from_value(json!({
+            "schema": 1,
+            "repositories": [],
+            "replicas": [],
+            "gateway_url": "http://s0.example.org:4929/api/v1",
+            "shared": "from repositories.json"
+        }))
+        .unwrap();
+        let meta_json: MetaJSON = serde_json::from_value(json!({
+            "administrator": "admin",
+            "email": "admin@example.org",
+            "organisation": "example",
+            "custom": {},
+            "site": "Oslo",
+            "shared": "from meta.json"
+        }))
+        .unwrap();
+
+        let (repo_meta, _) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::default());
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("s0.example.org").unwrap(),
+        );
+        let metadata = server.merge_metadata(repo_meta, Some(meta_json));
+        assert_eq!(
+            metadata.extra.get("gateway_url"),
+            Some(&json!("http://s0.example.org:4929/api/v1"))
+        );
+        assert_eq!(metadata.extra.get("site"), Some(&json!("Oslo")));
+        assert_eq!(
+            metadata.extra.get("shared"),
+            Some(&json!("from repositories.json"))
+        );
+
+        let parsed: ServerMetadata =
+            serde_json::from_str(&serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert_eq!(parsed, metadata);
+    }
+
+    #[test]
+    fn test_recommended_stratum1_list() {
+        let mut repo_json = empty_repositories_json();
+        repo_json.recommended_stratum1_list = vec![
+            "http://s1.example.org/cvmfs/@fqrn@".to_string(),
+            "https://s1.example.org:8443/cvmfs/@fqrn@".to_string(),
+        ];
+        let (metadata, _) =
+            MetadataFromRepoJSON::from_repositories_json(&repo_json, VersionParsing::default());
+        let mut server = test_utilities::server("s0.example.org", ServerType::Stratum0, vec![]);
+        server.metadata.merge_repo_metadata(metadata);
+        assert_eq!(
+            server.recommended_stratum1_list(),
+            vec![
+                "http://s1.example.org/cvmfs/@fqrn@",
+                "https://s1.example.org:8443/cvmfs/@fqrn@"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discover_stratum1s_uses_info_path() {
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/cvmfs/custom/info/repositories.json") {
+                ok_response(
+                    r#"{"schema": 1, "replicas": [], "repositories": [],
+                        "recommended_stratum1_list": ["http://s1.example.org/cvmfs/@fqrn@"]}"#,
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            info_path: "custom/info".to_string(),
+            ..Default::default()
+        };
+        let stratum1s = server
+            .discover_stratum1s(&reqwest::Client::new(), &options)
+            .await
+            .unwrap();
+        assert_eq!(stratum1s.len(), 1);
+        assert_eq!(stratum1s[0].hostname.to_str(), "s1.example.org");
+    }
+
+    #[parameterized(
+        https = { "https://s1.example.org", Some(Scheme::Https), None, None },
+        https_port = { "https://s1.example.org:8443/", Some(Scheme::Https), Some(8443), None },
+        http_path = { "http://s1.example.org/cvmfs/@fqrn@", Some(Scheme::Http), None, None },
+        default_port = { "https://s1.example.org:443", Some(Scheme::Https), None, None },
+        base_path = { "https://s1.example.org/mirror/", Some(Scheme::Https), None, Some("mirror") },
+        base_path_cvmfs = { "http://s1.example.org/a/b/cvmfs/@fqrn@", Some(Scheme::Http), None, Some("a/b") },
+        ftp = { "ftp://s1.example.org", None, None, None },
+        no_scheme = { "s1.example.org", None, None, None },
+    )]
+    fn test_server_from_url(
+        url: &str,
+        scheme: Option<Scheme>,
+        port: Option<u16>,
+        base_path: Option<&str>,
+    ) {
+        let server = Server::from_url(ServerType::Stratum1, url);
+        match scheme {
+            Some(scheme) => {
+                let server = server.unwrap();
+                assert_eq!(server.hostname.to_str(), "s1.example.org");
+                assert_eq!(server.server_type, ServerType::Stratum1);
+                assert_eq!(server.backend_type, ServerBackendType::AutoDetect);
+                assert_eq!(server.scheme, Some(scheme));
+                assert_eq!(server.port, port);
+                assert_eq!(server.base_path.as_deref(), base_path);
+            }
+            None => assert!(matches!(server, Err(ScrapeError::ConversionError { .. }))),
+        }
+    }
+
+    #[test]
+    fn test_servers_from_urls() {
+        let urls = [
+            "http://s1.example.org/cvmfs/@fqrn@",
+            "https://s2.example.org:8443/cvmfs/@fqrn@",
+            "http://s1.example.org/cvmfs/@fqrn@",
+            "not a url",
+            "ftp://s3.example.org/cvmfs/@fqrn@",
+            "http://under_score.example.org/cvmfs/@fqrn@",
+        ]
+        .map(String::from);
+        let servers = servers_from_urls(&urls, ServerType::Stratum1);
+        assert_eq!(servers.len(), 2);
+
+        assert_eq!(servers[0].hostname.to_str(), "s1.example.org");
+        assert_eq!(servers[0].server_type, ServerType::Stratum1);
+        assert_eq!(servers[0].backend_type, ServerBackendType::AutoDetect);
+        assert_eq!(servers[0].scheme, Some(Scheme::Http));
+        assert_eq!(servers[0].port, None);
+
+        assert_eq!(servers[1].hostname.to_str(), "s2.example.org");
+        assert_eq!(servers[1].scheme, Some(Scheme::Https));
+        assert_eq!(servers[1].port, Some(8443));
+    }
+
+    #[test]
+    fn test_ipv6_server_urls() {
+        let urls = ["http://[2001:db8::1]:8000/cvmfs/@fqrn@".to_string()];
+        let servers = servers_from_urls(&urls, ServerType::Stratum1);
+        assert_eq!(servers.len(), 1);
+        assert!(servers[0].hostname.is_ipv6());
+        assert_eq!(servers[0].port, Some(8000));
+        assert_eq!(
+            servers[0].url_inventory(&["software.eessi.io"]),
+            vec![
+                "http://[2001:db8::1]:8000/cvmfs/info/v1/meta.json",
+                "http://[2001:db8::1]:8000/cvmfs/info/v1/repositories.json",
+                "http://[2001:db8::1]:8000/cvmfs/software.eessi.io/.cvmfs_status.json",
+                "http://[2001:db8::1]:8000/cvmfs/software.eessi.io/.cvmfspublished",
+            ]
+        );
+        assert!(servers[0]
+            .is_https_upgrade("https://[2001:db8::1]:8000/cvmfs/info/v1/repositories.json"));
+    }
+
+    #[test]
+    fn test_metadata_json_rejects_invalid_version() {
+        let json = json!({
+            "schema_version": null,
+            "cvmfs_version": "latest",
+            "last_geodb_update": null,
+            "os_version_id": null,
+            "os_pretty_name": null,
+            "os_id": null,
+            "administrator": null,
+            "email": null,
+            "organisation": null,
+            "custom": null,
+        });
+        assert!(serde_json::from_value::<ServerMetadata>(json).is_err());
+    }
+
+    #[parameterized(
+        strict = { false },
+        lenient = { true },
+    )]
+    fn test_sync_server_without_replicas(lenient: bool) {
+        let server = Server::new(
+            ServerType::SyncServer,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("sync.example.org").unwrap(),
+        );
+        let result = server.validate_repo_json_and_server_type(&empty_repositories_json(), lenient);
+        if lenient {
+            assert!(matches!(
+                result,
+                Ok(Some(ScrapeWarning::ServerTypeMismatch(_)))
+            ));
+        } else {
+            assert!(matches!(
+                result,
+                Err(CVMFSScraperError::ScrapeError(
+                    ScrapeError::ServerTypeMismatch(_)
+                ))
+            ));
+        }
+    }
+
+    #[parameterized(
+        accept = { EmptyRepositoriesPolicy::Accept },
+        warn = { EmptyRepositoriesPolicy::Warn },
+        fail = { EmptyRepositoriesPolicy::Fail },
+    )]
+    fn test_empty_repositories_json(policy: EmptyRepositoriesPolicy) {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from("stratum1.example.org").unwrap(),
+        );
+        let result = server.check_published_repositories(&empty_repositories_json(), policy);
+        match policy {
+            EmptyRepositoriesPolicy::Accept => assert!(matches!(result, Ok(None))),
+            EmptyRepositoriesPolicy::Warn => assert!(matches!(
+                result,
+                Ok(Some(ScrapeWarning::NoRepositoriesPublished))
+            )),
+            EmptyRepositoriesPolicy::Fail => assert!(matches!(
+                result,
+                Err(CVMFSScraperError::ScrapeError(
+                    ScrapeError::NoRepositoriesPublished(_)
+                ))
+            )),
+        }
+    }
+
+    #[test]
+    fn test_empty_repositories_json_warning_skips_server_type_check() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("stratum1.example.org").unwrap(),
+        );
+        let options = ScrapeOptions {
+            empty_repositories: EmptyRepositoriesPolicy::Warn,
+            ..Default::default()
+        };
+        let warnings = server
+            .validate_repo_json(&empty_repositories_json(), &options)
+            .unwrap();
+        assert_eq!(warnings, vec![ScrapeWarning::NoRepositoriesPublished]);
+        assert!(server
+            .validate_repo_json(&empty_repositories_json(), &ScrapeOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_stratum1_without_replicas_is_strict_when_lenient() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("stratum1.example.org").unwrap(),
+        );
+        let result = server.validate_repo_json_and_server_type(&empty_repositories_json(), true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_clone_with_type_and_backend() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("cvmfs.example.org").unwrap(),
+        )
+        .with_s3_path_prefix("repos")
+        .with_port(8000);
+
+        let stratum0 = server.clone_with_type(ServerType::Stratum0);
+        assert_eq!(stratum0.server_type, ServerType::Stratum0);
+        assert_eq!(stratum0.clone_with_type(ServerType::Stratum1), server);
+        assert_eq!(server.clone().into_stratum0(), stratum0);
+        assert_eq!(stratum0.clone().into_stratum1(), server);
+        assert_eq!(
+            server.clone().into_sync_server().server_type,
+            ServerType::SyncServer
+        );
+
+        let cvmfs = server.clone_with_backend(ServerBackendType::CVMFS);
+        assert_eq!(cvmfs.backend_type, ServerBackendType::CVMFS);
+        assert_eq!(cvmfs.server_type, ServerType::Stratum1);
+        assert_eq!(cvmfs.port, Some(8000));
+        assert_eq!(cvmfs.s3_path_prefix.as_deref(), Some("repos"));
+    }
+
+    #[test]
+    fn test_server_deserializes_without_s3_path_prefix() {
+        let server: Server = serde_json::from_value(json!({
+            "server_type": "Stratum1",
+            "hostname": "cvmfs.example.org"
+        }))
+        .unwrap();
+        assert_eq!(server.s3_path_prefix, None);
+    }
+
+    #[test]
+    fn test_backend_detection_explicit_did_not_fall_back() {
+        let detection = BackendDetection::explicit(ServerBackendType::S3);
+        assert!(!detection.attempted);
+        assert!(!detection.fell_back());
+        assert_eq!(detection.outcome, ServerBackendType::S3);
+    }
+
+    #[test]
+    fn test_backend_detection_fell_back() {
+        let detection = BackendDetection {
+            attempted: true,
+            outcome: ServerBackendType::S3,
+            fallback_error: Some("HTTP status server error (503 Service Unavailable)".to_string()),
+        };
+        assert!(detection.fell_back());
+    }
+
+    #[parameterized(
+        plain = { "software.eessi.io", "software.eessi.io" },
+        comma = { "a,b", "\"a,b\"" },
+        quote = { "say \"hi\"", "\"say \"\"hi\"\"\"" },
+        newline = { "a\nb", "\"a\nb\"" },
+    )]
+    fn test_csv_field(field: &str, expected: &str) {
+        assert_eq!(csv_field(field), expected);
+    }
+
+    #[test]
+    fn test_to_csv_rows() {
+        let server = test_utilities::server(
+            "cvmfs.example.org",
+            ServerType::Stratum1,
+            vec![
+                test_utilities::repository(
+                    "software.eessi.io",
+                    42,
+                    1718718004,
+                    Some("Tue Jun 18 13:40:04 UTC 2024"),
+                    None,
+                ),
+                test_utilities::repository("dev.eessi.io", 7, 1718718004, None, Some("yesterday")),
+            ],
+        );
+        let rows = server.to_csv_rows(true);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], CSV_HEADER);
+        let size = server.repositories[0].manifest.b;
+        assert_eq!(
+            rows[1],
+            format!(
+                "cvmfs.example.org,stratum1,software.eessi.io,42,2024-06-18T13:40:04Z,,{}",
+                size
+            )
+        );
+        assert_eq!(
+            rows[2],
+            format!(
+                "cvmfs.example.org,stratum1,dev.eessi.io,7,,yesterday,{}",
+                size
+            )
+        );
+        assert_eq!(server.to_csv_rows(false), rows[1..]);
+    }
+
+    #[test]
+    fn test_repositories_in_common() {
+        let repos = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| test_utilities::repository(name, 1, 0, None, None))
+                .collect::<Vec<_>>()
+        };
+        let stratum0 = test_utilities::server(
+            "s0.example.org",
+            ServerType::Stratum0,
+            repos(&["software.eessi.io", "dev.eessi.io", "riscv.eessi.io"]),
+        );
+        let stratum1 = test_utilities::server(
+            "s1.example.org",
+            ServerType::Stratum1,
+            repos(&["software.eessi.io", "dev.eessi.io", "extra.eessi.io"]),
+        );
+
+        assert_eq!(
+            PopulatedServer::repositories_in_common(&stratum0, &stratum1),
+            vec!["dev.eessi.io", "software.eessi.io"]
+        );
+        assert_eq!(
+            PopulatedServer::repositories_only_in(&stratum0, &stratum1),
+            vec!["riscv.eessi.io"]
+        );
+        assert_eq!(
+            PopulatedServer::repositories_only_in(&stratum1, &stratum0),
+            vec!["extra.eessi.io"]
+        );
+        assert!(!stratum1.has_all_repositories_of(&stratum0));
+        assert!(stratum0.has_all_repositories_of(&stratum0));
+
+        let empty = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![]);
+        assert!(stratum1.has_all_repositories_of(&empty));
+        assert!(!empty.has_all_repositories_of(&stratum0));
+    }
+
+    #[test]
+    fn test_gc_enabled() {
+        let with_gc = test_utilities::repository(
+            "software.eessi.io",
+            1,
+            1718991602,
+            None,
+            Some("Sun Jun 16 00:00:59 UTC 2024"),
+        );
+        let without_gc = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+        assert!(with_gc.gc_enabled());
+        assert!(with_gc.gc_age().is_some());
+        assert!(!without_gc.gc_enabled());
+        assert!(without_gc.gc_age().is_none());
+    }
+
+    #[parameterized(
+        both_present = { Some("Fri Jun 21 17:35:00 UTC 2024"), Some("Fri Jun 21 17:40:02 UTC 2024"), Some(302) },
+        missing_start = { None, Some("Fri Jun 21 17:40:02 UTC 2024"), None },
+        missing_end = { Some("Fri Jun 21 17:35:00 UTC 2024"), None, None },
+        unparsable_start = { Some("fre. 21. juni 17:35:00 +0200 2024"), Some("Fri Jun 21 17:40:02 UTC 2024"), None },
+        snapshot_in_progress = { Some("Fri Jun 21 17:45:00 UTC 2024"), Some("Fri Jun 21 17:40:02 UTC 2024"), None },
+    )]
+    fn test_publish_duration(start: Option<&str>, end: Option<&str>, expected: Option<i64>) {
+        let mut repo = test_utilities::repository("software.eessi.io", 1, 1718991602, end, None);
+        repo.last_snapshot_start = start.map(|s| MaybeRfc2822DateTime(Some(s.to_string())));
+        assert_eq!(
+            repo.publish_duration(),
+            expected.map(chrono::Duration::seconds)
+        );
+        assert_eq!(
+            repo.is_publish_slow(chrono::Duration::minutes(1)),
+            expected.is_some()
+        );
+    }
+
+    #[parameterized(
+        in_the_past = { 1718991602, None, None },
+        published_in_future = { 1718991662, None, Some(60) },
+        snapshot_in_future = { 1718991602, Some("Fri Jun 21 17:42:02 UTC 2024"), Some(120) },
+        newest_wins = { 1718991662, Some("Fri Jun 21 17:42:02 UTC 2024"), Some(120) },
+    )]
+    fn test_clock_skew(published: i64, last_snapshot: Option<&str>, expected: Option<i64>) {
+        // Fri Jun 21 17:40:02 UTC 2024
+        let now = DateTime::from_timestamp(1718991602, 0).unwrap();
+        let repo =
+            test_utilities::repository("software.eessi.io", 1, published, last_snapshot, None);
+        assert_eq!(
+            repo.clock_skew_at(now),
+            expected.map(chrono::Duration::seconds)
+        );
+    }
+
+    #[parameterized(
+        gc_after = { Some("Fri Jun 21 17:40:02 UTC 2024"), Some("Sat Jun 22 00:00:59 UTC 2024"), Some(true) },
+        gc_before = { Some("Fri Jun 21 17:40:02 UTC 2024"), Some("Sun Jun 16 00:00:59 UTC 2024"), Some(false) },
+        no_gc = { Some("Fri Jun 21 17:40:02 UTC 2024"), None, None },
+        no_snapshot = { None, Some("Sun Jun 16 00:00:59 UTC 2024"), None },
+        unparsable_gc = { Some("Fri Jun 21 17:40:02 UTC 2024"), Some("yesterday"), None },
+    )]
+    fn test_gc_after_snapshot(
+        last_snapshot: Option<&str>,
+        last_gc: Option<&str>,
+        expected: Option<bool>,
+    ) {
+        let repo =
+            test_utilities::repository("software.eessi.io", 1, 1718991602, last_snapshot, last_gc);
+        assert_eq!(repo.gc_after_snapshot(), expected);
+    }
+
+    #[test]
+    fn test_publish_timestamp() {
+        let repo = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+        let published = DateTime::from_timestamp(1718991602, 0).unwrap();
+        assert_eq!(repo.publish_timestamp(), Some(published));
+        assert_eq!(repo.last_snapshot_time(), Some(published));
+
+        let mut repo = repo;
+        repo.manifest.t = 0;
+        assert_eq!(repo.publish_timestamp(), None);
+        assert_eq!(repo.last_snapshot_time(), None);
+    }
+
+    #[test]
+    fn test_snapshot_age_is_never_negative() {
+        let future = Utc::now().timestamp() + 3600;
+        let repo = test_utilities::repository("software.eessi.io", 1, future, None, None);
+        assert_eq!(repo.snapshot_age(), Some(chrono::Duration::zero()));
+        assert!(repo.clock_skew().is_some());
+
+        let repo = test_utilities::repository(
+            "software.eessi.io",
+            1,
+            future,
+            Some("Fri Jun 21 17:40:02 UTC 2024"),
+            None,
+        );
+        assert!(repo.snapshot_age().unwrap() > chrono::Duration::zero());
+    }
+
+    #[parameterized(
+        published_after_gc = { 1718991602, true },
+        published_before_gc = { 1718000000, false },
+    )]
+    fn test_gc_stalled(published: i64, expected: bool) {
+        let repo = test_utilities::repository(
+            "software.eessi.io",
+            1,
+            published,
+            None,
+            Some("Sun Jun 16 00:00:59 UTC 2024"),
+        );
+        assert_eq!(repo.gc_stalled(chrono::Duration::days(7)), expected);
+    }
+
+    #[parameterized(
+        fresh = { 0, None, false, 0 },
+        stale = { 5, None, false, 1 },
+        very_stale = { 30, None, true, 1 },
+        failed_check = { 0, Some("failed"), true, 1 },
+        stale_and_failed_check = { 5, Some("failed"), true, 2 },
+        succeeded_check = { 0, Some("succeeded"), false, 0 },
+    )]
+    fn test_health_status(
+        hours_old: i64,
+        check_status: Option<&str>,
+        critical: bool,
+        issues: usize,
+    ) {
+        let published = Utc::now() - chrono::Duration::hours(hours_old);
+        let repo = PopulatedRepositoryOrReplica {
+            check_status: check_status.map(str::to_string),
+            ..test_utilities::repository("software.eessi.io", 1, published.timestamp(), None, None)
+        };
+        let health = repo.health_status(&HealthConfig::default());
+        assert_eq!(health.is_critical(), critical);
+        assert_eq!(health.issues().len(), issues);
+        assert_eq!(health.is_healthy(), issues == 0);
+    }
+
+    #[parameterized(
+        slow_publish = { "slow_publish", false, "Last publish took 120 minutes" },
+        stalled_gc = { "stalled_gc", false, "Garbage collection has stalled" },
+        clock_skew = { "clock_skew", false, "Timestamps are" },
+        whitelist_expiring = { "whitelist_expiring", false, "Whitelist expires in 23 hours" },
+        whitelist_expired = { "whitelist_expired", true, "Whitelist expired 2 hours ago" },
+    )]
+    fn test_health_status_issue(case: &str, critical: bool, issue: &str) {
+        let now = Utc::now();
+        let mut repo = test_utilities::repository(
+            "software.eessi.io",
+            1,
+            (now - chrono::Duration::minutes(10)).timestamp(),
+            None,
+            None,
+        );
+        let date = |time: DateTime<Utc>| {
+            let date = time.format("%a %b %d %H:%M:%S UTC %Y").to_string();
+            Some(MaybeRfc2822DateTime(Some(date)))
+        };
+        let whitelist = |expires| Whitelist {
+            created: None,
+            expires,
+            repository: None,
+            fingerprints: vec![],
+        };
+        match case {
+            "slow_publish" => {
+                repo.last_snapshot = date(now - chrono::Duration::minutes(10));
+                repo.last_snapshot_start = date(now - chrono::Duration::minutes(130));
+            }
+            "stalled_gc" => repo.last_gc = date(now - chrono::Duration::days(10)),
+            "clock_skew" => {
+                repo.manifest.t = (now + chrono::Duration::hours(1)).timestamp();
+            }
+            "whitelist_expiring" => {
+                repo.whitelist = Some(whitelist(now + chrono::Duration::minutes(23 * 60 + 30)));
+            }
+            "whitelist_expired" => {
+                repo.whitelist = Some(whitelist(now - chrono::Duration::minutes(150)));
+            }
+            _ => unreachable!(),
+        }
+        let health = repo.health_status(&HealthConfig::default());
+        assert_eq!(health.is_critical(), critical);
+        assert_eq!(health.issues().len(), 1, "{:?}", health.issues());
+        assert!(
+            health.issues()[0].starts_with(issue),
+            "{:?}",
+            health.issues()
+        );
+    }
+
+    #[parameterized(
+        sorted = { RepositoryOrder::Sorted, vec!["a.org", "b.org", "c.org", "d.org"] },
+        requested = { RepositoryOrder::Requested, vec!["c.org", "a.org", "b.org", "d.org"] },
+    )]
+    fn test_order_repositories(order: RepositoryOrder, expected: Vec<&str>) {
+        // d.org is discovered, e.org is requested but ignored, c.org is requested twice.
+        let repositories = ["a.org", "b.org", "c.org", "d.org"]
+            .iter()
+            .map(|repo| repo.to_string())
+            .collect();
+        let requested = ["c.org", "e.org", "a.org", "c.org", "b.org"]
+            .iter()
+            .map(|repo| repo.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order_repositories(repositories, &requested, order),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_scrape() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let failed = server
+            .try_scrape(vec!["software.eessi.io"])
+            .await
+            .unwrap_err();
+        assert_eq!(failed.hostname.to_str(), "localhost");
+        assert!(failed
+            .to_string()
+            .starts_with("Failed to scrape localhost: "));
+        assert!(std::error::Error::source(&failed).is_some());
+
+        let boxed: Box<dyn std::error::Error> = failed.into();
+        assert!(boxed.to_string().contains("software.eessi.io"));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_accepts_as_ref_str_repositories() {
+        // Only AsRef<str> is required of the repository names, not Display or Clone.
+        struct Repo(&'static str);
+        impl AsRef<str> for Repo {
+            fn as_ref(&self) -> &str {
+                self.0
+            }
+        }
+
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let failed = server
+            .scrape(
+                vec![Repo("software.eessi.io")],
+                vec![Repo("ignored.eessi.io")],
+                None,
+            )
+            .await
+            .get_failed_server()
+            .unwrap();
+        assert!(failed.error.to_string().contains("software.eessi.io"));
+
+        let repos: Vec<std::sync::Arc<str>> = vec!["software.eessi.io".into()];
+        assert!(server.try_scrape(repos).await.is_err());
+    }
+
+    #[test]
+    fn test_scraped_server_into_result() {
+        let populated = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![]);
+        let scraped = ScrapedServer::Populated(populated.clone());
+        assert_eq!(scraped.into_result().unwrap(), populated);
+
+        let failed = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("s1.example.org").unwrap(),
+        )
+        .to_failed_server(ScrapeError::EmptyRepositoryList("s1.example.org".to_string()).into());
+        let result: Result<PopulatedServer, FailedServer> = ScrapedServer::Failed(failed).into();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_spawn_returns_result() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let handle = server.scrape_spawn(vec![], ScrapeOptions::default());
+        let result = handle.await.unwrap();
+        assert!(result.is_failed());
+        assert_eq!(
+            result.get_failed_server().unwrap().hostname.to_str(),
+            "localhost"
+        );
+    }
+
+    #[parameterized(
+        sequential = { 1 },
+        concurrent = { 4 },
+        zero_is_one = { 0 },
+    )]
+    fn test_bulk_scrape_keeps_order(concurrency: usize) {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let server = Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::S3,
+                Hostname::try_from("localhost").unwrap(),
+            );
+            let names = ["a.example.org", "b.example.org", "c.example.org"];
+            let repos = names
+                .iter()
+                .map(|name| {
+                    RepositoryOrReplica::new(&RepositoryName::try_from(*name).unwrap(), &server)
+                })
+                .collect::<Vec<_>>();
+            let client = reqwest::Client::new();
+            let results = RepositoryOrReplica::bulk_scrape(repos, &client, concurrency).await;
+            assert_eq!(results.len(), names.len());
+            for (name, result) in names.iter().zip(results) {
+                let error = result.unwrap_err().to_string();
+                assert!(error.contains(name), "{}", error);
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_repository_scrapes() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let options = ScrapeOptions {
+            ignored_repositories: vec!["c.example.org".to_string()],
+            ..Default::default()
+        };
+        let scrapes = server
+            .repository_scrapes(
+                vec!["b.example.org", "a.example.org", "c.example.org"],
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(scrapes.backend_detected, ServerBackendType::S3);
+        assert_eq!(
+            scrapes
+                .repositories
+                .iter()
+                .map(|repo| repo.name.to_str())
+                .collect::<Vec<_>>(),
+            vec!["a.example.org", "b.example.org"]
+        );
+
+        // The futures are 'static, so they can be handed to any executor.
+        let handles = scrapes
+            .into_futures()
+            .into_iter()
+            .map(tokio::spawn)
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert!(handle.await.unwrap().is_err());
+        }
+
+        assert!(server
+            .repository_scrapes(Vec::<String>::new(), &options)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rescrape() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let client = reqwest::Client::new();
+
+        let empty = test_utilities::server("localhost", ServerType::Stratum1, vec![]);
+        let result = server.rescrape(&empty, &client).await;
+        assert_eq!(result.get_populated_server().unwrap(), empty);
+
+        let previous = test_utilities::server(
+            "localhost",
+            ServerType::Stratum1,
+            vec![test_utilities::repository(
+                "software.eessi.io",
+                10,
+                1718718004,
+                None,
+                None,
+            )],
+        );
+        assert!(server.rescrape(&previous, &client).await.is_failed());
+    }
+
+    #[tokio::test]
+    async fn test_rescrape_not_modified() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let manifest = include_str!(
+            "../../tests/fixtures/local/stratum1/cvmfs/software.eessi.io/.cvmfspublished"
+        );
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let port = test_utilities::serve_with(move |request| {
+            if !request.contains("/.cvmfspublished") {
+                return ok_response("{}");
+            }
+            let request = request.to_lowercase();
+            if request.contains("if-none-match: \"v1\"")
+                && request.contains("if-modified-since: tue, 18 jun 2024 13:40:04 gmt")
+            {
+                return "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n".to_string();
+            }
+            counter.fetch_add(1, Ordering::SeqCst);
+            format!(
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Tue, 18 Jun 2024 13:40:04 GMT\r\n\
+                 Content-Length: {}\r\n\r\n{}",
+                manifest.len(),
+                manifest
+            )
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let name = RepositoryName::try_from("software.eessi.io").unwrap();
+        let repo = RepositoryOrReplica::new(&name, &server);
+        let client = reqwest::Client::new();
+
+        let scraped = repo.scrape(&client).await.unwrap();
+        assert_eq!(
+            scraped.manifest_validators,
+            Some(CacheValidators {
+                last_modified: Some("Tue, 18 Jun 2024 13:40:04 GMT".to_string()),
+                etag: Some("\"v1\"".to_string()),
+            })
+        );
+        let rescraped = repo.rescrape(&client, &scraped).await.unwrap();
+        assert_eq!(rescraped, scraped);
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+        // Without validators, there is nothing to send back, so the manifest is fetched again.
+        let unvalidated = PopulatedRepositoryOrReplica {
+            manifest_validators: None,
+            ..scraped.clone()
+        };
+        assert_eq!(repo.rescrape(&client, &unvalidated).await.unwrap(), scraped);
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rescrape_recomputes_repository_warnings() {
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/.cvmfspublished") {
+                let manifest = include_str!(
+                    "../../tests/fixtures/local/stratum1/cvmfs/software.eessi.io/.cvmfspublished"
+                );
+                ok_response(manifest)
+            } else {
+                ok_response("{}")
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let mut previous = test_utilities::server(
+            "localhost",
+            ServerType::Stratum1,
+            vec![test_utilities::repository(
+                "software.eessi.io",
+                10,
+                1718718004,
+                None,
+                None,
+            )],
+        );
+        previous.warnings = vec![
+            ScrapeWarning::NoRepositoriesPublished,
+            ScrapeWarning::GcAfterSnapshot {
+                repository: "software.eessi.io".to_string(),
+            },
+        ];
+        let rescraped = server
+            .rescrape(&previous, &reqwest::Client::new())
+            .await
+            .get_populated_server()
+            .unwrap();
+        assert_eq!(rescraped.repositories[0].revision(), 12345);
+        assert_eq!(
+            rescraped.warnings,
+            vec![ScrapeWarning::NoRepositoriesPublished]
+        );
+    }
+
+    #[parameterized(
+        default = { false },
+        fetched = { true },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_scrape_fetches_whitelist_only_if_asked(fetch_whitelist: bool) {
+        use std::sync::{Arc, Mutex};
+
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let paths = requested.clone();
+        let port = test_utilities::serve_with(move |request| {
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            paths.lock().unwrap().push(path.to_string());
+            if path.ends_with("/.cvmfspublished") {
+                let manifest = include_str!(
+                    "../../tests/fixtures/local/stratum1/cvmfs/software.eessi.io/.cvmfspublished"
+                );
+                ok_response(manifest)
+            } else {
+                ok_response("{}")
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            fetch_whitelist,
+            ..Default::default()
+        };
+        let populated = server
+            .scrape_with_options(vec!["software.eessi.io"], &options)
+            .await
+            .get_populated_server()
+            .unwrap();
+
+        assert_eq!(populated.repositories[0].revision(), 12345);
+        let requested = requested.lock().unwrap();
+        assert_eq!(
+            requested
+                .iter()
+                .any(|path| path.ends_with("/.cvmfswhitelist")),
+            fetch_whitelist
+        );
+    }
+
+    #[parameterized(
+        https_falls_back_to_http = { None, Scheme::Http },
+        explicit_https = { Some(Scheme::Https), Scheme::Https },
+        explicit_http = { Some(Scheme::Http), Scheme::Http },
+    )]
+    fn test_autodetect_scheme(scheme: Option<Scheme>, expected: Scheme) {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            // Nothing listens on the port, so every connection is refused and detection ends
+            // up on S3. Only a missing scheme falls back from HTTPS to HTTP.
+            let port = test_utilities::closed_port();
+            let mut server = Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::AutoDetect,
+                Hostname::try_from("localhost").unwrap(),
+            )
+            .with_port(port);
+            server.scheme = scheme;
+            let populated = server
+                .scrape_with_options(Vec::<String>::new(), &ScrapeOptions::default())
+                .await;
+            let populated = populated.get_populated_server().unwrap();
+            assert_eq!(populated.scheme, expected);
+            assert_eq!(populated.backend_detected, ServerBackendType::S3);
+            let fallback_error = populated.detection.fallback_error.unwrap();
+            assert!(
+                fallback_error.contains(&format!("{}://localhost:{}/", expected, port)),
+                "{}",
+                fallback_error
+            );
+        });
+    }
+
+    #[test]
+    fn test_scheme_in_urls() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("cvmfs.example.org").unwrap(),
+        )
+        .with_scheme(Scheme::Https);
+        assert_eq!(
+            server.repositories_json_url(DEFAULT_INFO_PATH),
+            "https://cvmfs.example.org/cvmfs/info/v1/repositories.json"
+        );
+        assert_eq!(Scheme::Https.to_string(), "https");
+        assert_eq!(serde_json::to_string(&Scheme::Http).unwrap(), r#""http""#);
+    }
+
+    #[parameterized(
+        defaults = { None, None, "https://cvmfs.example.org:8080/cvmfs/info/v1/repositories.json", "cvmfs.example.org" },
+        explicit_scheme = { Some(Scheme::Http), None, "http://cvmfs.example.org:8080/cvmfs/info/v1/repositories.json", "cvmfs.example.org" },
+        explicit_port = { None, Some(8000), "https://cvmfs.example.org:8000/cvmfs/info/v1/repositories.json", "cvmfs.example.org" },
+        hostname_port = { None, None, "https://cvmfs.example.org:8443/cvmfs/info/v1/repositories.json", "cvmfs.example.org:8443" },
+        explicit_over_hostname_port = { None, Some(8000), "https://cvmfs.example.org:8000/cvmfs/info/v1/repositories.json", "cvmfs.example.org:8443" },
+    )]
+    fn test_default_scheme_and_port(
+        scheme: Option<Scheme>,
+        port: Option<u16>,
+        expected: &str,
+        hostname: &str,
+    ) {
+        let mut server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from(hostname).unwrap(),
+        );
+        server.scheme = scheme;
+        server.port = port;
+        let options = ScrapeOptions {
+            default_scheme: Some(Scheme::Https),
+            default_port: Some(8080),
+            ..Default::default()
+        };
+        let plan = server.plan_with_options::<String>(&[], &options);
+        assert_eq!(plan[0].url, expected);
+    }
+
+    #[parameterized(
+        same_host = { None, "https://cvmfs.example.org/cvmfs/info/v1/repositories.json", true },
+        same_host_uppercase = { None, "https://CVMFS.example.org/cvmfs/info/v1/repositories.json", true },
+        other_host = { None, "https://mirror.example.org/cvmfs/info/v1/repositories.json", false },
+        plain_http = { None, "http://cvmfs.example.org/cvmfs/info/v1/repositories.json", false },
+        same_port = { Some(8000), "https://cvmfs.example.org:8000/cvmfs/info/v1/repositories.json", true },
+        other_port = { Some(8000), "https://cvmfs.example.org/cvmfs/info/v1/repositories.json", false },
+        invalid = { None, "not a url", false },
+    )]
+    fn test_is_https_upgrade(port: Option<u16>, url: &str, expected: bool) {
+        let mut server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from("cvmfs.example.org").unwrap(),
+        )
+        .with_scheme(Scheme::Http);
+        server.port = port;
+        assert_eq!(server.is_https_upgrade(url), expected);
+        assert!(!server.with_scheme(Scheme::Https).is_https_upgrade(url));
+    }
+
+    #[tokio::test]
+    async fn test_autodetect_redirect_to_https_keeps_http_if_https_fails() {
+        // The mock server only speaks plain HTTP, so retrying over HTTPS fails and the scrape
+        // reports the redirect it could not follow.
+        let port = test_utilities::serve(
+            "HTTP/1.1 301 Moved Permanently\r\n\
+             Location: https://localhost:{port}/cvmfs/info/v1/repositories.json\r\n\
+             Content-Length: 0\r\n\r\n",
+        )
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_scheme(Scheme::Http)
+        .with_port(port);
+        let options = ScrapeOptions {
+            redirect_policy: RedirectPolicy::Reject,
+            ..Default::default()
+        };
+        let result = server
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await;
+        let failed = result.get_failed_server().unwrap();
+        let error = failed.error.to_string();
+        assert!(error.contains("Unexpected redirect"), "{}", error);
+        assert_eq!(failed.redirects.len(), 1);
+        assert_eq!(failed.redirects[0].status, 301);
+        assert!(!failed.redirects[0].followed);
+        assert!(failed.redirects[0].changes_scheme());
+    }
+
+    #[parameterized(
+        lease = { Some(r#"{"status": "ok", "data": {"software.eessi.io/": {"key_id": "k"}}}"#), Some(true) },
+        no_leases = { Some(r#"{"status": "ok", "data": {}}"#), Some(false) },
+        unexpected = { Some("not json"), None },
+        no_gateway = { None, None },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_scrape_stratum0_transaction_state(leases: Option<&str>, expected: Option<bool>) {
+        let port = test_utilities::serve(&ok_response(
+            r#"{"schema": 1, "replicas": [],
+                "repositories": [{"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"}]}"#,
+        ))
+        .await;
+        let gateway_port = match leases {
+            Some(leases) => Some(test_utilities::serve(&ok_response(leases)).await),
+            None => None,
+        };
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            mode: ScrapeMode::MetadataOnly,
+            gateway_port,
+            ..Default::default()
+        };
+        let populated = server
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await
+            .into_result()
+            .unwrap();
+        assert_eq!(populated.in_transaction, expected);
+    }
+
+    #[parameterized(
+        alive = { "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", true, true },
+        not_found = { "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n", false, true },
+        forbidden = { "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n", false, true },
+        server_error = { "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n", false, false },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_ping(response: &str, alive: bool, s3_alive: bool) {
+        let port = test_utilities::serve(response).await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let client = reqwest::Client::new();
+        let options = ScrapeOptions::default();
+        assert_eq!(server.ping(&client, &options).await.is_ok(), alive);
+        assert_eq!(
+            server
+                .ping_path(&client, "/cvmfs/info/v1/meta.json")
+                .await
+                .is_ok(),
+            alive
+        );
+        let s3 = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        assert_eq!(s3.ping(&client, &options).await.is_ok(), s3_alive);
+    }
+
+    #[tokio::test]
+    async fn test_ping_uses_info_path() {
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/cvmfs/custom/info/repositories.json") {
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            info_path: "custom/info".to_string(),
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        assert!(server.ping(&client, &options).await.is_ok());
+        assert!(server
+            .ping(&client, &ScrapeOptions::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ping_unreachable() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(1);
+        let error = server
+            .ping(&reqwest::Client::new(), &ScrapeOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.is_connect_error());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_repository_names_uses_info_path() {
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/cvmfs/custom/info/repositories.json") {
+                ok_response(
+                    r#"{"schema": 1, "replicas": [],
+                        "repositories": [{"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"}]}"#,
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let options = ScrapeOptions {
+            info_path: "custom/info".to_string(),
+            default_port: Some(port),
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        assert_eq!(
+            server
+                .fetch_repository_names_with_options(&client, &[], &options)
+                .await
+                .unwrap(),
+            vec!["software.eessi.io"]
+        );
+        let default_path = ScrapeOptions {
+            default_port: Some(port),
+            ..Default::default()
+        };
+        assert!(server
+            .fetch_repository_names_with_options(&client, &[], &default_path)
+            .await
+            .is_err());
+    }
+
+    #[parameterized(
+        required = { true },
+        not_required = { false },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_requested_repository_not_found(require_all_requested: bool) {
+        // S3 servers have no repositories.json, so every request is for the repository.
+        let port =
+            test_utilities::serve("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            require_all_requested,
+            ..Default::default()
+        };
+
+        let failed = server
+            .scrape_with_options(vec!["software.eessi.io"], &options)
+            .await
+            .into_result()
+            .unwrap_err();
+        if require_all_requested {
+            assert!(
+                matches!(
+                    failed.error,
+                    CVMFSScraperError::ScrapeError(ScrapeError::RequestedRepositoryNotFound(_))
+                ),
+                "{}",
+                failed.error
+            );
+        } else {
+            // The underlying 404 of the fetch.
+            assert!(failed.error.is_not_found(), "{}", failed.error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scrape_through_base_path() {
+        // Serve the local stratum1 fixture under /proxy only, as a reverse proxy would.
+        let port = test_utilities::serve_with(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            if path.starts_with("/proxy/cvmfs/software.eessi.io/api/v1.0/geo/") {
+                return ok_response("1,2,3");
+            }
+            let file = path.strip_prefix("/proxy/").map(|file| {
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/fixtures/local/stratum1")
+                    .join(file)
+            });
+            match file.and_then(|file| std::fs::read(file).ok()) {
+                Some(body) => ok_response(&String::from_utf8_lossy(&body)),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            cache: Some(crate::RepositoryCache::new(std::time::Duration::from_secs(
+                60,
+            ))),
+            ..Default::default()
+        };
+
+        let proxied = server
+            .clone()
+            .with_base_path("proxy")
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await
+            .into_result()
+            .unwrap();
+        assert_eq!(proxied.repositories[0].revision(), 12345);
+        assert_eq!(proxied.port, Some(port));
+        assert_eq!(proxied.base_path.as_deref(), Some("proxy"));
+        assert_eq!(proxied.location(), format!("localhost:{}/proxy", port));
+
+        // The same host without the base path is another server, and must not be answered
+        // from the cache.
+        let direct = server
+            .scrape_with_options(vec!["software.eessi.io"], &options)
+            .await;
+        assert!(direct.is_failed());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_falls_back_through_the_proxy_chain() {
+        // A proxy serving the local stratum1 fixture for any origin, behind a proxy that is down.
+        let port = test_utilities::serve_with(|request| {
+            let url = request.split_whitespace().nth(1).unwrap_or_default();
+            let path = url
+                .split_once("/cvmfs/")
+                .map(|(_, path)| path)
+                .unwrap_or_default();
+            if path.starts_with("software.eessi.io/api/v1.0/geo/") {
+                return ok_response("1,2,3");
+            }
+            let file = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/local/stratum1/cvmfs")
+                .join(path);
+            match std::fs::read(file) {
+                Ok(body) => ok_response(&String::from_utf8_lossy(&body)),
+                Err(_) => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("cvmfs.invalid").unwrap(),
+        );
+        let options = |setting: String| ScrapeOptions {
+            cvmfs_proxy: Some(setting),
+            ..Default::default()
+        };
+        let down = format!("http://127.0.0.1:{}", test_utilities::closed_port());
+        let up = format!("http://127.0.0.1:{}", port);
+
+        let scraped = server
+            .scrape_with_options(Vec::<String>::new(), &options(format!("{};{}", down, up)))
+            .await
+            .into_result()
+            .unwrap();
+        assert_eq!(scraped.repositories[0].revision(), 12345);
+
+        let failed = server
+            .scrape_with_options(Vec::<String>::new(), &options(down))
+            .await
+            .into_result()
+            .unwrap_err();
+        assert!(failed.error.is_connect_error(), "{}", failed.error);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_metrics_only_time_the_proxy_that_answered() {
+        let port = test_utilities::serve_with(|request| {
+            let url = request.split_whitespace().nth(1).unwrap_or_default();
+            if url.ends_with("/.cvmfspublished") {
+                let manifest = include_str!(
+                    "../../tests/fixtures/local/stratum1/cvmfs/software.eessi.io/.cvmfspublished"
+                );
+                ok_response(manifest)
+            } else {
+                ok_response("{}")
+            }
+        })
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("cvmfs.invalid").unwrap(),
+        );
+        let options = ScrapeOptions {
+            cvmfs_proxy: Some(format!(
+                "http://127.0.0.1:{};http://127.0.0.1:{}",
+                test_utilities::closed_port(),
+                port
+            )),
+            ..Default::default()
+        };
+
+        let (scraped, metrics) = server
+            .scrape_with_metrics(vec!["software.eessi.io"], &options)
+            .await;
+        assert_eq!(
+            scraped.into_result().unwrap().repositories[0].revision(),
+            12345
+        );
+        assert_eq!(
+            metrics
+                .per_repo_durations
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["software.eessi.io"]
+        );
+    }
+
+    #[parameterized(
+        hostname = { true },
+        default_port = { false },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_scraped_servers_record_the_resolved_port(port_in_hostname: bool) {
+        let server = |port: u16| {
+            let hostname = match port_in_hostname {
+                true => format!("localhost:{}", port),
+                false => "localhost".to_string(),
+            };
+            Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::S3,
+                Hostname::try_from(hostname.as_str()).unwrap(),
+            )
+        };
+        let options = |port: u16| ScrapeOptions {
+            default_port: Some(port).filter(|_| !port_in_hostname),
+            ..Default::default()
+        };
+
+        let port = test_utilities::serve_with(|request| {
+            if request.contains("/.cvmfspublished") {
+                let manifest = include_str!(
+                    "../../tests/fixtures/local/stratum1/cvmfs/software.eessi.io/.cvmfspublished"
+                );
+                ok_response(manifest)
+            } else {
+                ok_response("{}")
+            }
+        })
+        .await;
+        let populated = server(port)
+            .scrape_with_options(vec!["software.eessi.io"], &options(port))
+            .await
+            .into_result()
+            .unwrap();
+        assert_eq!(populated.port, Some(port));
+
+        let closed = test_utilities::closed_port();
+        let failed = server(closed)
+            .scrape_with_options(vec!["software.eessi.io"], &options(closed))
+            .await
+            .into_result()
+            .unwrap_err();
+        assert_eq!(failed.port, Some(closed));
+    }
+
+    #[test]
+    fn test_identity_includes_port_and_base_path() {
+        let server = test_utilities::server("cvmfs.example.org", ServerType::Stratum1, vec![]);
+        let proxied = PopulatedServer {
+            base_path: Some("mirror".to_string()),
+            ..server.clone()
+        };
+        let other_port = PopulatedServer {
+            port: Some(8080),
+            ..server.clone()
+        };
+        assert!(!PopulatedServer::same_server(&server, &proxied));
+        assert!(!PopulatedServer::same_server(&server, &other_port));
+        let failed = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("cvmfs.example.org").unwrap(),
+        )
+        .with_base_path("mirror")
+        .to_failed_server(ScrapeError::EmptyRepositoryList("test".to_string()).into());
+        assert_eq!(ServerIdentity::from(&failed), proxied.identity());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_refuses_invalid_repository_names() {
+        let port = test_utilities::serve(&ok_response(
+            r#"{"schema": 1, "replicas": [],
+                "repositories": [{"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"},
+                                 {"name": "../etc", "url": "/cvmfs/../etc"}]}"#,
+        ))
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            mode: ScrapeMode::MetadataOnly,
+            ..Default::default()
+        };
+
+        let populated = server
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await
+            .into_result()
+            .unwrap();
+        assert_eq!(populated.discovered_but_skipped, vec!["../etc"]);
+
+        let failed = server
+            .scrape_with_options(vec!["software"], &options)
+            .await
+            .into_result()
+            .unwrap_err();
+        assert!(matches!(
+            failed.error,
+            CVMFSScraperError::RepositoryNameError(RepositoryNameError::NotFullyQualified(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_records_repositories_json_timing() {
+        let body = r#"{"schema": 1, "replicas": [],
+            "repositories": [{"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"}]}"#;
+        let port = test_utilities::serve(&ok_response(body)).await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            mode: ScrapeMode::MetadataOnly,
+            ..Default::default()
+        };
+        let populated = server
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await
+            .into_result()
+            .unwrap();
+        let timing = populated.repositories_json_timing.unwrap();
+        assert_eq!(timing.bytes, Some(body.len()));
+        assert!(timing.duration > std::time::Duration::ZERO);
+
+        // Timing jitter does not make otherwise identical scrapes differ.
+        let mut other = populated.clone();
+        other.repositories_json_timing = Some(RepositoriesJsonTiming {
+            duration: timing.duration * 2,
+            bytes: None,
+        });
+        assert_eq!(populated, other);
+        other.in_transaction = Some(true);
+        assert_ne!(populated, other);
+    }
+
+    #[tokio::test]
+    async fn test_autodetect_fallback_records_repositories_json_timing() {
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_scheme(Scheme::Http)
+        .with_port(1);
+        let client = reqwest::Client::new();
+        let resolved = server
+            .resolve(
+                vec!["software.eessi.io"],
+                &ScrapeOptions::default(),
+                &client,
+            )
+            .await
+            .unwrap();
+        assert!(resolved.detection.fell_back());
+        assert_eq!(resolved.repositories_json_timing.unwrap().bytes, None);
+    }
+
+    #[parameterized(
+        discovered = { vec![], vec!["software.eessi.io"] },
+        requested = { vec!["eessi.io"], vec!["eessi.io", "software.eessi.io"] },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_aliases_are_not_scraped_twice(requested: Vec<&str>, expected: Vec<&str>) {
+        let port = test_utilities::serve(&ok_response(
+            r#"{"schema": 1, "replicas": [], "repositories": [
+                {"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"},
+                {"name": "eessi.io", "url": "/cvmfs/eessi.io", "alias_of": "software.eessi.io"}]}"#,
+        ))
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let client = reqwest::Client::new();
+        let resolved = server
+            .resolve(requested.clone(), &ScrapeOptions::default(), &client)
+            .await
+            .unwrap();
+        assert_eq!(resolved.repositories, expected);
+        assert_eq!(
+            resolved.discovered_but_skipped.is_empty(),
+            !requested.is_empty()
+        );
+        assert_eq!(
+            resolved.listed["eessi.io"].canonical_name.as_deref(),
+            Some("software.eessi.io")
+        );
+    }
+
+    #[parameterized(
+        listed_case = { vec!["software.eessi.io"], vec!["dev.eessi.io", "Software.EESSI.io"] },
+        requested_case = { vec!["DEV.eessi.io."], vec!["dev.eessi.io", "Software.EESSI.io"] },
+        unlisted = { vec!["Other.eessi.io"], vec!["dev.eessi.io", "Other.eessi.io", "Software.EESSI.io"] },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_repository_names_keep_their_case(requested: Vec<&str>, expected: Vec<&str>) {
+        let port = test_utilities::serve(&ok_response(
+            r#"{"schema": 1, "replicas": [], "repositories": [
+                {"name": "Software.EESSI.io", "url": "/cvmfs/Software.EESSI.io"},
+                {"name": "dev.eessi.io", "url": "/cvmfs/dev.eessi.io"}]}"#,
+        ))
+        .await;
+        let server = Server::new(
+            ServerType::Stratum0,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let client = reqwest::Client::new();
+        let resolved = server
+            .resolve(requested, &ScrapeOptions::default(), &client)
+            .await
+            .unwrap();
+        assert_eq!(resolved.repositories, expected);
+        assert_eq!(
+            resolved.discovered_repositories,
+            vec!["dev.eessi.io", "Software.EESSI.io"]
+        );
+        // Names are sorted by their normalized form, and fetched as listed.
+        let repo = resolved.repositories.last().unwrap();
+        assert!(RepositoryOrReplica::new(repo, &resolved.server)
+            .manifest_url()
+            .ends_with("/cvmfs/Software.EESSI.io/.cvmfspublished"));
+    }
+
+    #[parameterized(
+        s3 = { ServerBackendType::S3, None, true },
+        cvmfs = { ServerBackendType::CVMFS, None, false },
+        s3_disallowed = { ServerBackendType::S3, Some(false), false },
+        cvmfs_allowed = { ServerBackendType::CVMFS, Some(true), true },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_missing_status_json(
+        backend_type: ServerBackendType,
+        allow: Option<bool>,
+        accepted: bool,
+    ) {
+        let port =
+            test_utilities::serve("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            backend_type,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            allow_missing_status_json: allow,
+            ..Default::default()
+        };
+        let repo = RepositoryOrReplica::new(
+            &RepositoryName::try_from("software.eessi.io").unwrap(),
+            &server,
+        )
+        .with_missing_status_json_allowed(options.allows_missing_status_json(backend_type));
+        let result = repo
+            .fetch_repository_status_json(&reqwest::Client::new())
+            .await;
+        if accepted {
+            assert_eq!(result.unwrap(), StatusJSON::default());
+        } else {
+            assert!(result.unwrap_err().is_not_found());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scrape_records_cross_origin_redirects() {
+        let body = r#"{"schema": 1, "repositories": [],
+            "replicas": [{"name": "software.eessi.io", "url": "/cvmfs/software.eessi.io"}]}"#;
+        let target = test_utilities::serve(&format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .await;
+        let port = test_utilities::serve(&format!(
+            "HTTP/1.1 302 Found\r\n\
+             Location: http://127.0.0.1:{}/cvmfs/info/v1/repositories.json\r\n\
+             Content-Length: 0\r\n\r\n",
+            target
+        ))
+        .await;
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::CVMFS,
+            Hostname::try_from("localhost").unwrap(),
+        )
+        .with_port(port);
+        let options = ScrapeOptions {
+            mode: ScrapeMode::MetadataOnly,
+            ..Default::default()
+        };
+        let populated = server
+            .scrape_with_options(Vec::<String>::new(), &options)
+            .await
+            .into_result()
+            .unwrap();
+
+        let repos_json = &populated.redirects[0];
+        assert_eq!(
+            repos_json.from,
+            format!("http://localhost:{}/cvmfs/info/v1/repositories.json", port)
+        );
+        assert_eq!(
+            repos_json.to,
+            format!(
+                "http://127.0.0.1:{}/cvmfs/info/v1/repositories.json",
+                target
+            )
+        );
+        assert_eq!(repos_json.status, 302);
+        assert!(repos_json.followed && repos_json.changes_host());
+        assert!(populated
+            .warnings
+            .contains(&ScrapeWarning::CrossOriginRedirect {
+                from: repos_json.from.clone(),
+                to: repos_json.to.clone(),
+            }));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_events_for_failed_scrape() {
+        let (tx, mut rx) = tokio::sync::broadcast::channel(16);
+        let options = ScrapeOptions {
+            event_tx: Some(tx),
+            ..Default::default()
+        };
+        let hostname = Hostname::try_from("localhost").unwrap();
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            hostname.clone(),
+        );
+        let result = server
+            .scrape_with_options(vec!["software.eessi.io"], &options)
+            .await;
+        assert!(result.is_failed());
+
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            ScrapeEvent::ScrapeStarted {
+                hostname: hostname.clone()
+            }
+        );
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            ScrapeEvent::RepositoryFailed { repo_name, .. } if repo_name == "software.eessi.io"
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            ScrapeEvent::ScrapeFailed { hostname: failed, .. } if failed == hostname
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scrape_events_without_receivers_do_not_fail() {
+        let (tx, rx) = tokio::sync::broadcast::channel(1);
+        drop(rx);
+        let options = ScrapeOptions {
+            event_tx: Some(tx),
+            ..Default::default()
+        };
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let result = server
+            .scrape_with_options(vec!["software.eessi.io"], &options)
+            .await;
+        assert!(result.is_failed());
+    }
+
+    #[cfg(feature = "table")]
+    #[test]
+    fn test_to_table() {
+        let mut server = test_utilities::server(
+            "stratum1.example.org",
+            ServerType::Stratum1,
+            vec![
+                test_utilities::repository(
+                    "software.eessi.io",
+                    12345,
+                    1718991602,
+                    Some("Fri Jun 21 17:40:02 UTC 2024"),
+                    Some("Sun Jun 16 00:00:59 UTC 2024"),
+                ),
+                test_utilities::repository("dev.eessi.io", 42, 1718991602, None, None),
+            ],
+        );
+        server.metadata.cvmfs_version = Some(semver::Version::parse("2.11.3-1").unwrap());
+        let table = server.to_table();
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "Server: stratum1.example.org");
+        assert!(lines.contains(&"CVMFS Version: 2.11.3-1"));
+        assert!(table.contains("Repository"));
+        let row = lines
+            .iter()
+            .find(|line| line.contains("software.eessi.io"))
+            .unwrap();
+        assert!(row.contains("12345"));
+        assert!(row.contains("Sun Jun 16 00:00:59 UTC 2024"));
+        assert!(lines.iter().any(|line| line.contains("dev.eessi.io")));
+    }
+
+    #[parameterized(
+        full_hash = { 12345, "600230b0ba7620426f2e898f1e1f43c5466efe59", "r12345@600230b" },
+        short_hash = { 1, "abc", "r1@abc" },
+        empty_hash = { 0, "", "r0@" },
+        large_revision = { u64::MAX, "600230b0", "r18446744073709551615@600230b" },
+    )]
+    fn test_format_revision_tag(revision: u64, root_hash: &str, expected: &str) {
+        assert_eq!(format_revision_tag(revision, root_hash), expected);
+    }
+
+    #[test]
+    fn test_revision_tag() {
+        let repo = test_utilities::repository("software.eessi.io", 12345, 1718991602, None, None);
+        let (revision, root_hash) = repo.revision_and_root_hash();
+        assert_eq!(revision, 12345);
+        assert_eq!(root_hash, repo.manifest.c.to_string());
+        assert_eq!(
+            repo.revision_tag(),
+            format!("r12345@{}", &root_hash[..REVISION_TAG_HASH_LENGTH])
+        );
+    }
+
+    #[parameterized(
+        none = { 0, "repositories: []" },
+        few = { 2, "repositories: [\"repo0.example.org\", \"repo1.example.org\"]" },
+        limit = { 5, "\"repo4.example.org\"]" },
+        many = { 100, "repositories: [100 repos, first: \"repo0.example.org\", last: \"repo99.example.org\"]" },
+    )]
+    fn test_populated_server_debug(count: usize, expected: &str) {
+        let repos = (0..count)
+            .map(|i| {
+                test_utilities::repository(&format!("repo{}.example.org", i), 1, 1, None, None)
+            })
+            .collect();
+        let server = test_utilities::server("stratum1.example.org", ServerType::Stratum1, repos);
+        let debug = format!("{:?}", server);
+        assert!(debug.contains(expected), "{}", debug);
+        assert!(debug.contains("stratum1.example.org"));
+        assert!(!debug.contains("manifest"));
+
+        let full = format!("{:?}", server.debug_full());
+        assert_eq!(full.matches("manifest: ").count(), count);
+        assert!(full.contains("geoapi"));
+    }
+
+    #[test]
+    fn test_server_type_ordering() {
+        let mut types = vec![
+            ServerType::SyncServer,
+            ServerType::Stratum1,
+            ServerType::Stratum0,
+        ];
+        types.sort();
+        assert_eq!(
+            types,
+            vec![
+                ServerType::Stratum0,
+                ServerType::Stratum1,
+                ServerType::SyncServer
+            ]
+        );
+    }
+
+    #[parameterized(
+        stratum0 = { ServerType::Stratum0, "stratum0", "\"Stratum0\"" },
+        stratum1 = { ServerType::Stratum1, "stratum1", "\"Stratum1\"" },
+        syncserver = { ServerType::SyncServer, "syncserver", "\"SyncServer\"" },
+    )]
+    fn test_server_type_display_and_serde(server_type: ServerType, display: &str, json: &str) {
+        assert_eq!(server_type.to_string(), display);
+        assert_eq!(serde_json::to_string(&server_type).unwrap(), json);
+    }
+
+    #[parameterized(
+        canonical = { "Stratum0", ServerType::Stratum0 },
+        lowercase = { "stratum1", ServerType::Stratum1 },
+        dashed = { "stratum-0", ServerType::Stratum0 },
+        spaced = { "Stratum 1", ServerType::Stratum1 },
+        short = { "S0", ServerType::Stratum0 },
+        sync = { "sync", ServerType::SyncServer },
+        sync_server = { "sync_server", ServerType::SyncServer },
+    )]
+    fn test_server_type_aliases(name: &str, expected: ServerType) {
+        assert_eq!(name.parse::<ServerType>().unwrap(), expected);
+        let json = serde_json::to_string(name).unwrap();
+        let server_type: ServerType = serde_json::from_str(&json).unwrap();
+        assert_eq!(server_type, expected);
+        assert_eq!(
+            serde_json::to_string(&server_type).unwrap(),
+            format!("\"{:?}\"", expected)
+        );
+    }
+
+    #[parameterized(
+        canonical = { "AutoDetect", ServerBackendType::AutoDetect },
+        s3 = { "s3", ServerBackendType::S3 },
+        cvmfs = { "cvmfs", ServerBackendType::CVMFS },
+        uppercase = { "CVMFS", ServerBackendType::CVMFS },
+        auto = { "auto", ServerBackendType::AutoDetect },
+        dashed = { "auto-detect", ServerBackendType::AutoDetect },
+    )]
+    fn test_server_backend_type_aliases(name: &str, expected: ServerBackendType) {
+        assert_eq!(name.parse::<ServerBackendType>().unwrap(), expected);
+        let json = serde_json::to_string(name).unwrap();
+        let backend_type: ServerBackendType = serde_json::from_str(&json).unwrap();
+        assert_eq!(backend_type, expected);
+        assert_eq!(
+            serde_json::to_string(&backend_type).unwrap(),
+            format!("\"{:?}\"", expected)
+        );
+    }
+
+    #[test]
+    fn test_invalid_type_names() {
+        assert!("stratum2".parse::<ServerType>().is_err());
+        assert!("s3".parse::<ServerType>().is_err());
+        assert!("gcs".parse::<ServerBackendType>().is_err());
+        assert!(serde_json::from_str::<ServerType>(r#""Stratum 2""#).is_err());
+        assert!(serde_json::from_str::<ServerBackendType>("1").is_err());
+    }
+
+    #[parameterized(
+        in_sync = { 42, false },
+        behind = { 43, true },
+        ahead = { 41, false },
+    )]
+    fn test_pending_snapshot(upstream_revision: u64, expected: bool) {
+        let published = Utc::now().timestamp();
+        let repo = test_utilities::repository("software.eessi.io", 42, published, None, None);
+        assert_eq!(repo.pending_snapshot(upstream_revision), expected);
+
+        let config = HealthConfig::default();
+        let health = repo.health_status_with_upstream(&config, Some(upstream_revision));
+        assert_eq!(health.issues().len(), usize::from(expected));
+        assert!(!health.is_critical());
+        assert!(repo.health_status(&config).is_healthy());
+    }
+
+    #[parameterized(
+        succeeded = { Some("succeeded"), Some(true) },
+        failed = { Some("failed"), Some(false) },
+        not_reported = { None, None },
+    )]
+    fn test_check_succeeded(check_status: Option<&str>, expected: Option<bool>) {
+        let mut repo = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+        repo.check_status = check_status.map(String::from);
+        assert_eq!(repo.check_succeeded(), expected);
+    }
+
+    #[parameterized(
+        stratum0 = { ServerType::Stratum0, false, true, false },
+        stratum1 = { ServerType::Stratum1, true, false, true },
+        syncserver = { ServerType::SyncServer, true, true, true },
+    )]
+    fn test_server_type_roles(
+        server_type: ServerType,
+        replica: bool,
+        source: bool,
+        can_have_replicas: bool,
+    ) {
+        assert_eq!(server_type.is_replica_server(), replica);
+        assert_eq!(server_type.is_source_server(), source);
+        assert_eq!(server_type.can_have_replicas(), can_have_replicas);
+    }
+
+    #[test]
+    fn test_scraped_repository_names() {
+        let server = test_utilities::server(
+            "stratum1.example.org",
+            ServerType::Stratum1,
+            vec![
+                test_utilities::repository("dev.eessi.io", 1, 1718991602, None, None),
+                test_utilities::repository("software.eessi.io", 1, 1718991602, None, None),
+            ],
+        );
+        assert_eq!(
+            server.scraped_repository_names(),
+            vec!["dev.eessi.io", "software.eessi.io"]
+        );
+    }
+
+    #[test]
+    fn test_same_server_ignores_data() {
+        let repo = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+        let first = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![]);
+        let second = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![repo]);
+        let other_type = test_utilities::server("s1.example.org", ServerType::SyncServer, vec![]);
+
+        assert_ne!(first, second);
+        assert!(PopulatedServer::same_server(&first, &second));
+        assert!(!PopulatedServer::same_server(&first, &other_type));
+
+        let mut history: std::collections::HashMap<ServerIdentity, Vec<PopulatedServer>> =
+            std::collections::HashMap::new();
+        for server in [first, second, other_type] {
+            history.entry(server.identity()).or_default().push(server);
+        }
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn test_data_eq() {
+        let repo = test_utilities::repository("software.eessi.io", 1, 1718991602, None, None);
+        let first = test_utilities::server("s1.example.org", ServerType::Stratum1, vec![repo]);
+        let mut timed = first.clone();
+        timed.repositories_json_timing = Some(RepositoriesJsonTiming {
+            duration: std::time::Duration::from_millis(12),
+            bytes: Some(512),
+        });
+        let mut published = first.clone();
+        published.repositories[0] =
+            test_utilities::repository("software.eessi.io", 2, 1718991702, None, None);
+        let mut warned = first.clone();
+        warned.warnings.push(ScrapeWarning::GcAfterSnapshot {
+            repository: "software.eessi.io".to_string(),
+        });
+
+        assert!(first.data_eq(&timed));
+        assert!(!first.data_eq(&published));
+        assert!(!first.data_eq(&warned));
+
+        let mut seen = std::collections::HashSet::new();
+        assert!(seen.insert(DataEq(first)));
+        assert!(!seen.insert(DataEq::from(timed)));
+        assert!(seen.insert(DataEq(published)));
+        assert!(seen.insert(DataEq(warned)));
+        assert_eq!(seen.len(), 3);
+    }
 }
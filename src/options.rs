@@ -0,0 +1,761 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::RepositoryCache;
+use crate::constants::{
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_GEOAPI_SERVERS, DEFAULT_INFO_PATH, DEFAULT_MAX_REDIRECTS,
+    DEFAULT_REPOSITORY_CONCURRENCY, DEFAULT_REQUEST_TIMEOUT,
+};
+use crate::errors::ScrapeError;
+use crate::events::ScrapeEvent;
+use crate::models::{
+    Hostname, RepositoryName, RepositoryOrReplica, Scheme, Server, ServerBackendType,
+};
+use crate::redirects::{RedirectHop, RedirectLog};
+use crate::resolver::PreferenceResolver;
+
+/// How HTTP redirects are handled when scraping.
+///
+/// - Follow: Follow redirects that keep the scheme (e.g. to a canonical hostname).
+/// - Reject: Never follow redirects.
+/// - FollowCrossScheme: Follow all redirects, including from http to https (and back).
+///
+/// A redirect that is not followed results in a `ScrapeError::UnexpectedRedirect`, which
+/// contains both the requested URL and the location the server redirected to. Every redirect,
+/// followed or not, is recorded on the scrape result, see `RedirectHop`.
+///
+/// Defaults to FollowCrossScheme, which is how reqwest handles redirects by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedirectPolicy {
+    Follow,
+    Reject,
+    #[default]
+    FollowCrossScheme,
+}
+
+impl RedirectPolicy {
+    /// Returns true if a redirect from one URL to another should be followed.
+    pub fn allows(&self, from: &Url, to: &Url) -> bool {
+        match self {
+            RedirectPolicy::Follow => from.scheme() == to.scheme(),
+            RedirectPolicy::Reject => false,
+            RedirectPolicy::FollowCrossScheme => true,
+        }
+    }
+}
+
+/// How to treat a valid repositories.json that lists no repositories or replicas.
+///
+/// - Accept: Scrape the server as normal, returning a server without repositories.
+/// - Warn: As Accept, but add `ScrapeWarning::NoRepositoriesPublished` to the server.
+/// - Fail: Fail the scrape with `ScrapeError::NoRepositoriesPublished`.
+///
+/// An empty repositories.json typically means that the server has not been fully provisioned.
+/// Defaults to Accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyRepositoriesPolicy {
+    #[default]
+    Accept,
+    Warn,
+    Fail,
+}
+
+/// How the CVMFS version reported in repositories.json is parsed.
+///
+/// - Strict: The version must be valid semver (e.g. "2.11.3" or "2.11.3-1").
+/// - Lenient: Also accept common non-strict forms, by padding a missing minor or patch version
+///   ("2.9" becomes 2.9.0) and dropping anything semver cannot represent, such as distribution
+///   suffixes ("2.11.0-1.el9_4" becomes 2.11.0) or a fourth component ("2.12.0.0").
+///
+/// A version that cannot be parsed never fails the scrape: the raw value is kept in
+/// `ServerMetadata::cvmfs_version_raw` and a `ScrapeWarning::MetadataConversion` is recorded.
+/// Defaults to Lenient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionParsing {
+    Strict,
+    #[default]
+    Lenient,
+}
+
+/// How much of a server to scrape.
+///
+/// - Full: Scrape the server metadata and every repository (manifest and status).
+/// - MetadataOnly: Only fetch repositories.json and meta.json, for the server metadata and the
+///   names of the repositories. No repository is scraped and the GeoAPI is not queried, which
+///   makes this much faster for an inventory of a fleet. The populated server has no
+///   repositories, the names are in `PopulatedServer::discovered_repositories` (empty for S3
+///   backends, which have no repositories.json).
+///
+/// Defaults to Full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScrapeMode {
+    #[default]
+    Full,
+    MetadataOnly,
+}
+
+/// The order in which the repositories of a server are scraped and reported.
+///
+/// - Sorted: Alphabetically by name.
+/// - Requested: The repositories given by the caller first, in the order given, followed by the
+///   remaining repositories discovered via repositories.json in alphabetical order. Duplicates
+///   are only kept at their first position. Repositories are scraped concurrently, but they are
+///   started in this order, so the most important repositories can be listed first (e.g. when
+///   scraping against a deadline).
+///
+/// The order applies to both the scrape and `PopulatedServer::repositories`. Defaults to Sorted,
+/// which is deterministic regardless of how the repositories are given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepositoryOrder {
+    #[default]
+    Sorted,
+    Requested,
+}
+
+/// Which IP address family to prefer when connecting to dual-stack servers.
+///
+/// - System: Use the addresses in the order returned by the system resolver.
+/// - PreferIpv4: Try IPv4 addresses first, falling back to IPv6.
+/// - PreferIpv6: Try IPv6 addresses first, falling back to IPv4.
+///
+/// The fallback uses the happy eyeballs behavior of the underlying HTTP connector: if the
+/// preferred family does not connect within a short delay (300ms), the other family is tried.
+/// This is useful for mirrors where the AAAA (or A) record points at a broken path.
+///
+/// The preference is applied through a custom DNS resolver (`reqwest::ClientBuilder::dns_resolver`),
+/// which is available in reqwest without any additional features. Defaults to System.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpPreference {
+    #[default]
+    System,
+    PreferIpv4,
+    PreferIpv6,
+}
+
+/// Options controlling how servers are scraped.
+///
+/// The options are shared by every server in a scrape, and the defaults mirror the behavior of
+/// `Server::scrape` without any ignored repositories and with the default GeoAPI servers.
+///
+/// Fields:
+///
+/// - ignored_repositories: Repositories that will never be scraped, even if explicitly requested.
+/// - geoapi_servers: The servers to use for the GeoAPI query. If empty, the defaults are used.
+/// - info_path: The path of the info directory holding repositories.json and meta.json, relative
+///   to `/cvmfs/`. Defaults to `info/v1`.
+/// - max_redirects: The maximum number of redirects to follow per request, or None for no limit.
+///   Defaults to 10, like reqwest.
+/// - redirect_policy: Which redirects to follow, see `RedirectPolicy`.
+/// - sync_server_lenient: If true, a SyncServer without replicas in repositories.json (a normal
+///   state during initial setup) is reported as a warning rather than failing the scrape. The
+///   checks for Stratum0 and Stratum1 servers are always strict. Defaults to false.
+/// - empty_repositories: How to treat a repositories.json without any repositories, see
+///   `EmptyRepositoriesPolicy`.
+/// - cache: A cache of scraped repositories, see `RepositoryCache`. Disabled by default.
+/// - ip_preference: Which IP address family to prefer, see `IpPreference`.
+/// - require_all_requested: If true, a requested repository that does not exist on the server
+///   (HTTP 404) fails the scrape with `ScrapeError::RequestedRepositoryNotFound`, rather than
+///   with the underlying fetch error. Defaults to false.
+/// - version_parsing: How the CVMFS version is parsed, see `VersionParsing`.
+/// - event_tx: A broadcast channel to send `ScrapeEvent`s to. Disabled by default.
+/// - default_scheme: The scheme for servers without one set (see `Server::with_scheme`). Setting
+///   it disables the HTTPS probing of AutoDetect. Defaults to None.
+/// - default_port: The port for servers without one set (see `Server::with_port`). Defaults to
+///   None, i.e. the default port of the scheme.
+/// - connect_timeout: The timeout for establishing a connection (DNS resolution, TCP connect
+///   and any TLS handshake), or None for no timeout. Defaults to 5 seconds.
+/// - request_timeout: The timeout for each HTTP request as a whole, from connecting to reading
+///   the response body, or None for no timeout. Defaults to 30 seconds.
+/// - read_timeout: The timeout for each read from a connection, or None for no timeout. Unlike
+///   request_timeout, a slow but steady download is not interrupted, only a server that stops
+///   sending data. Defaults to None.
+/// - mode: How much of each server to scrape, see `ScrapeMode`.
+/// - repository_order: The order in which repositories are scraped, see `RepositoryOrder`.
+/// - gateway_port: The port of the publication gateway on Stratum0 servers (typically 4929), used
+///   to check whether a publish transaction is open (see `PopulatedServer::in_transaction`).
+///   CVMFS does not expose transactions otherwise, so the check is skipped if None (the default).
+/// - cvmfs_proxy: The CVMFS Squid proxies to send requests through, in the format of the
+///   `CVMFS_HTTP_PROXY` setting of CVMFS clients, see `cvmfs_proxy_chain`. A server that cannot
+///   be reached through the first group of proxies is scraped again through the next group.
+///   Defaults to None, i.e. the proxy settings of the environment (e.g. `http_proxy`) are used.
+/// - allow_missing_status_json: Whether a repository without a .cvmfs_status.json (HTTP 404) is
+///   scraped without status (no last snapshot, last GC, etc.) rather than failing the scrape.
+///   Defaults to None, which allows it for S3 backends, where no CVMFS server generates the
+///   file, but not for CVMFS backends.
+/// - fetch_tags: Whether to fetch the named tags of each repository from its tag history
+///   database, see `PopulatedRepositoryOrReplica::tags`. This is an extra (and potentially large)
+///   download per repository, so it defaults to false. Only with the `tags` feature.
+/// - fetch_whitelist: Whether to fetch the .cvmfswhitelist of each repository, see
+///   `PopulatedRepositoryOrReplica::whitelist`. The whitelist is only used for health checks,
+///   and S3 backends often do not serve it, so it defaults to false.
+/// - max_concurrent_servers: The maximum number of servers scraped at the same time when
+///   scraping several servers (see `Scraper` and `Fleet`), or None to scrape them all at once
+///   (the default). A limit of 0 is treated as 1.
+/// - repository_concurrency: The maximum number of repositories scraped at the same time on a
+///   single server. Defaults to `DEFAULT_REPOSITORY_CONCURRENCY`. A limit of 0 is treated as 1.
+///
+/// ### Example
+///
+/// ```rust
+/// use cvmfs_server_scraper::ScrapeOptions;
+///
+/// let options = ScrapeOptions {
+///     ignored_repositories: vec!["dev.eessi.io".to_string()],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScrapeOptions {
+    pub ignored_repositories: Vec<String>,
+    pub geoapi_servers: Vec<Hostname>,
+    pub info_path: String,
+    pub max_redirects: Option<usize>,
+    pub redirect_policy: RedirectPolicy,
+    pub sync_server_lenient: bool,
+    pub empty_repositories: EmptyRepositoriesPolicy,
+    pub cache: Option<RepositoryCache>,
+    pub ip_preference: IpPreference,
+    pub require_all_requested: bool,
+    pub version_parsing: VersionParsing,
+    pub event_tx: Option<tokio::sync::broadcast::Sender<ScrapeEvent>>,
+    pub default_scheme: Option<Scheme>,
+    pub default_port: Option<u16>,
+    pub connect_timeout: Option<Duration>,
+    pub request_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub mode: ScrapeMode,
+    pub repository_order: RepositoryOrder,
+    pub gateway_port: Option<u16>,
+    pub cvmfs_proxy: Option<String>,
+    pub allow_missing_status_json: Option<bool>,
+    #[cfg(feature = "tags")]
+    pub fetch_tags: bool,
+    pub fetch_whitelist: bool,
+    pub max_concurrent_servers: Option<usize>,
+    pub repository_concurrency: usize,
+}
+
+/// The environment variable holding the default scheme, see `ScrapeOptions::from_env`.
+const ENV_SCHEME: &str = "CVMFS_SCRAPER_SCHEME";
+/// The environment variable holding the default port, see `ScrapeOptions::from_env`.
+const ENV_PORT: &str = "CVMFS_SCRAPER_PORT";
+/// The environment variable holding the request timeout in seconds, see `ScrapeOptions::from_env`.
+const ENV_TIMEOUT: &str = "CVMFS_SCRAPER_TIMEOUT";
+/// The environment variable holding the connect timeout in seconds, see `ScrapeOptions::from_env`.
+const ENV_CONNECT_TIMEOUT: &str = "CVMFS_SCRAPER_CONNECT_TIMEOUT";
+/// The environment variable holding the read timeout in seconds, see `ScrapeOptions::from_env`.
+const ENV_READ_TIMEOUT: &str = "CVMFS_SCRAPER_READ_TIMEOUT";
+
+impl Default for ScrapeOptions {
+    fn default() -> Self {
+        ScrapeOptions {
+            ignored_repositories: Vec::new(),
+            geoapi_servers: DEFAULT_GEOAPI_SERVERS.clone(),
+            info_path: DEFAULT_INFO_PATH.to_string(),
+            max_redirects: Some(DEFAULT_MAX_REDIRECTS),
+            redirect_policy: RedirectPolicy::default(),
+            sync_server_lenient: false,
+            empty_repositories: EmptyRepositoriesPolicy::default(),
+            cache: None,
+            ip_preference: IpPreference::default(),
+            require_all_requested: false,
+            version_parsing: VersionParsing::default(),
+            event_tx: None,
+            default_scheme: None,
+            default_port: None,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+            read_timeout: None,
+            mode: ScrapeMode::default(),
+            repository_order: RepositoryOrder::default(),
+            gateway_port: None,
+            cvmfs_proxy: None,
+            allow_missing_status_json: None,
+            #[cfg(feature = "tags")]
+            fetch_tags: false,
+            fetch_whitelist: false,
+            max_concurrent_servers: None,
+            repository_concurrency: DEFAULT_REPOSITORY_CONCURRENCY,
+        }
+    }
+}
+
+impl ScrapeOptions {
+    /// The default options, with defaults read from the environment.
+    ///
+    /// The following variables are read, and unset or empty variables are ignored:
+    ///
+    /// - `CVMFS_SCRAPER_SCHEME`: The default scheme, "http" or "https".
+    /// - `CVMFS_SCRAPER_PORT`: The default port.
+    /// - `CVMFS_SCRAPER_TIMEOUT`: The request timeout in seconds, e.g. "30" or "2.5".
+    /// - `CVMFS_SCRAPER_CONNECT_TIMEOUT`: The connect timeout in seconds.
+    /// - `CVMFS_SCRAPER_READ_TIMEOUT`: The read timeout in seconds.
+    ///
+    /// The precedence is explicit > environment > built-in default: a scheme or port set on a
+    /// server always wins over the environment, and so does any field set on the returned
+    /// options. Fails with `ScrapeError::ConversionError` if a variable has an invalid value.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use cvmfs_server_scraper::ScrapeOptions;
+    ///
+    /// let options = ScrapeOptions {
+    ///     ignored_repositories: vec!["dev.eessi.io".to_string()],
+    ///     ..ScrapeOptions::from_env().unwrap()
+    /// };
+    /// ```
+    pub fn from_env() -> Result<Self, ScrapeError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// As `from_env`, but reading the variables through the given lookup function.
+    fn from_vars<F>(var: F) -> Result<Self, ScrapeError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |name: &str| var(name).filter(|value: &String| !value.trim().is_empty());
+        let mut options = ScrapeOptions::default();
+        if let Some(scheme) = var(ENV_SCHEME) {
+            options.default_scheme = Some(scheme.trim().parse()?);
+        }
+        if let Some(port) = var(ENV_PORT) {
+            options.default_port = Some(port.trim().parse().map_err(|error| {
+                ScrapeError::conversion(format!("{}={}", ENV_PORT, port), error)
+            })?);
+        }
+        let seconds = |name: &str| -> Result<Option<Duration>, ScrapeError> {
+            let Some(value) = var(name) else {
+                return Ok(None);
+            };
+            let invalid = |error: &dyn std::fmt::Display| {
+                ScrapeError::conversion(format!("{}={}", name, value), error)
+            };
+            let seconds = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|error| invalid(&error))?;
+            Duration::try_from_secs_f64(seconds)
+                .map(Some)
+                .map_err(|error| invalid(&error))
+        };
+        if let Some(timeout) = seconds(ENV_TIMEOUT)? {
+            options.request_timeout = Some(timeout);
+        }
+        if let Some(timeout) = seconds(ENV_CONNECT_TIMEOUT)? {
+            options.connect_timeout = Some(timeout);
+        }
+        if let Some(timeout) = seconds(ENV_READ_TIMEOUT)? {
+            options.read_timeout = Some(timeout);
+        }
+        Ok(options)
+    }
+
+    /// Build the HTTP client used for a scrape, honoring the redirect options.
+    pub(crate) fn build_client(&self) -> Result<reqwest::Client, ScrapeError> {
+        Ok(self.build_client_with_log()?.0)
+    }
+
+    /// As `build_client`, also returning the log of every redirect the client sees.
+    ///
+    /// The client uses the first proxy of `cvmfs_proxy`, see `build_fallback_clients_with_log`
+    /// for the rest of the chain.
+    pub(crate) fn build_client_with_log(
+        &self,
+    ) -> Result<(reqwest::Client, RedirectLog), ScrapeError> {
+        let proxy = match &self.cvmfs_proxy {
+            Some(setting) => Some(cvmfs_proxy_url(setting)?),
+            None => None,
+        };
+        self.build_client_with_proxy(proxy.as_ref())
+    }
+
+    /// A client (with its redirect log) for each group of the `cvmfs_proxy` chain, in order.
+    ///
+    /// Each client uses the first proxy of its group, as a scrape does not load-balance. Without
+    /// a `cvmfs_proxy`, this is the single client of `build_client_with_log`.
+    pub(crate) fn build_fallback_clients_with_log(
+        &self,
+    ) -> Result<Vec<(reqwest::Client, RedirectLog)>, ScrapeError> {
+        let Some(setting) = &self.cvmfs_proxy else {
+            return Ok(vec![self.build_client_with_proxy(None)?]);
+        };
+        cvmfs_proxy_chain(setting)?
+            .iter()
+            .map(|group| self.build_client_with_proxy(Some(&group[0])))
+            .collect()
+    }
+
+    // Build a client through the given proxy (None to connect directly), or with the proxy
+    // settings of the environment if no proxy is given.
+    fn build_client_with_proxy(
+        &self,
+        proxy: Option<&Option<String>>,
+    ) -> Result<(reqwest::Client, RedirectLog), ScrapeError> {
+        let policy = self.redirect_policy;
+        let max_redirects = self.max_redirects;
+        let log = RedirectLog::default();
+        let recorder = log.clone();
+        let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+            let within_limit = max_redirects.is_none_or(|max| attempt.previous().len() <= max);
+            let allowed = match attempt.previous().last() {
+                Some(from) => within_limit && policy.allows(from, attempt.url()),
+                None => within_limit,
+            };
+            if let Some(from) = attempt.previous().last() {
+                recorder.record(RedirectHop {
+                    from: from.to_string(),
+                    to: attempt.url().to_string(),
+                    status: attempt.status().as_u16(),
+                    followed: allowed,
+                });
+            }
+            if !within_limit {
+                return attempt.error(format!(
+                    "too many redirects (max {})",
+                    max_redirects.unwrap_or_default()
+                ));
+            }
+            if allowed {
+                attempt.follow()
+            } else {
+                // Stopping returns the redirect response itself, which the fetch layer turns
+                // into an UnexpectedRedirect error.
+                attempt.stop()
+            }
+        });
+        let mut builder = reqwest::Client::builder().redirect(redirect);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(resolver) = self.dns_resolver() {
+            builder = builder.dns_resolver(Arc::new(resolver));
+        }
+        builder = match proxy {
+            Some(Some(url)) => builder
+                .proxy(reqwest::Proxy::all(url).map_err(|e| ScrapeError::conversion(url, e))?),
+            Some(None) => builder.no_proxy(),
+            None => builder,
+        };
+        Ok((builder.build()?, log))
+    }
+
+    /// Whether a missing .cvmfs_status.json is accepted for the given backend, see
+    /// `allow_missing_status_json`.
+    pub(crate) fn allows_missing_status_json(&self, backend_type: ServerBackendType) -> bool {
+        self.allow_missing_status_json
+            .unwrap_or(backend_type == ServerBackendType::S3)
+    }
+
+    /// A repository to scrape on a server with the given (resolved) backend, as set up by the
+    /// options.
+    pub(crate) fn repository(
+        &self,
+        name: &RepositoryName,
+        server: &Server,
+        backend_type: ServerBackendType,
+    ) -> RepositoryOrReplica {
+        let repository = RepositoryOrReplica::for_backend(name, server, backend_type)
+            .with_missing_status_json_allowed(self.allows_missing_status_json(backend_type))
+            .with_whitelist_fetched(self.fetch_whitelist);
+        #[cfg(feature = "tags")]
+        let repository = repository.with_tags_fetched(self.fetch_tags);
+        repository
+    }
+
+    /// Send an event to the event channel, if any. This never blocks, and an event that cannot
+    /// be sent (because there are no receivers) is dropped.
+    pub(crate) fn emit(&self, event: ScrapeEvent) {
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(event);
+        }
+    }
+
+    /// The resolver applying the IP preference, or None to use the system resolver as is.
+    pub(crate) fn dns_resolver(&self) -> Option<PreferenceResolver> {
+        match self.ip_preference {
+            IpPreference::System => None,
+            preference => Some(PreferenceResolver::new(preference)),
+        }
+    }
+}
+
+/// The proxy chain of a CVMFS proxy setting: its groups of proxies in order, with None for
+/// connecting directly.
+///
+/// The setting follows `CVMFS_HTTP_PROXY` of CVMFS clients: groups of proxies separated by `;`,
+/// tried in order, where the proxies of a group are separated by `|` and load-balanced, and
+/// `DIRECT` means connecting without a proxy, e.g. `http://squid1:3128|http://squid2:3128;DIRECT`
+/// is `[[Some("http://squid1:3128"), Some("http://squid2:3128")], [None]]`. A proxy without a
+/// scheme (e.g. `squid:3128`) is reached over HTTP. Every group holds at least one proxy, an
+/// empty group or proxy is an error.
+///
+/// A scrape falls back to the next group if the server cannot be reached through the current one,
+/// but does not load-balance, it uses the first proxy of each group.
+pub fn cvmfs_proxy_chain(setting: &str) -> Result<Vec<Vec<Option<String>>>, ScrapeError> {
+    setting
+        .split(';')
+        .map(|group| {
+            group
+                .split('|')
+                .map(|proxy| parse_cvmfs_proxy(setting, proxy.trim()))
+                .collect()
+        })
+        .collect()
+}
+
+/// The first proxy of a CVMFS proxy setting, or None to connect directly.
+///
+/// See `cvmfs_proxy_chain` for the format of the setting, and for the rest of the chain.
+pub fn cvmfs_proxy_url(setting: &str) -> Result<Option<String>, ScrapeError> {
+    Ok(cvmfs_proxy_chain(setting)?.swap_remove(0).swap_remove(0))
+}
+
+fn parse_cvmfs_proxy(setting: &str, proxy: &str) -> Result<Option<String>, ScrapeError> {
+    if proxy.is_empty() {
+        return Err(ScrapeError::conversion(
+            setting,
+            "Empty proxy in CVMFS proxy setting",
+        ));
+    }
+    if proxy.eq_ignore_ascii_case("direct") {
+        return Ok(None);
+    }
+    let url = if proxy.contains("://") {
+        proxy.to_string()
+    } else {
+        format!("http://{}", proxy)
+    };
+    Url::parse(&url).map_err(|e| ScrapeError::conversion(setting, e))?;
+    Ok(Some(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yare::parameterized;
+
+    #[parameterized(
+        follow_same_scheme = { RedirectPolicy::Follow, "http://a.example.org/x", "http://b.example.org/x", true },
+        follow_cross_scheme = { RedirectPolicy::Follow, "http://a.example.org/x", "https://a.example.org/x", false },
+        reject = { RedirectPolicy::Reject, "http://a.example.org/x", "http://b.example.org/x", false },
+        cross_scheme = { RedirectPolicy::FollowCrossScheme, "http://a.example.org/x", "https://a.example.org/x", true },
+    )]
+    fn test_redirect_policy_allows(policy: RedirectPolicy, from: &str, to: &str, expected: bool) {
+        let from = Url::parse(from).unwrap();
+        let to = Url::parse(to).unwrap();
+        assert_eq!(policy.allows(&from, &to), expected);
+    }
+
+    #[test]
+    fn test_default_redirect_options() {
+        let options = ScrapeOptions::default();
+        assert_eq!(options.max_redirects, Some(DEFAULT_MAX_REDIRECTS));
+        assert_eq!(options.redirect_policy, RedirectPolicy::FollowCrossScheme);
+        assert!(options.build_client().is_ok());
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Result<ScrapeOptions, ScrapeError> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<std::collections::HashMap<_, _>>();
+        ScrapeOptions::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let options = vars(&[
+            (ENV_SCHEME, "HTTPS"),
+            (ENV_PORT, "8080"),
+            (ENV_TIMEOUT, "2.5"),
+            (ENV_CONNECT_TIMEOUT, "1"),
+            (ENV_READ_TIMEOUT, "0.5"),
+        ])
+        .unwrap();
+        assert_eq!(options.default_scheme, Some(Scheme::Https));
+        assert_eq!(options.default_port, Some(8080));
+        assert_eq!(options.request_timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(options.connect_timeout, Some(Duration::from_secs(1)));
+        assert_eq!(options.read_timeout, Some(Duration::from_millis(500)));
+        assert!(options.build_client().is_ok());
+
+        let options = vars(&[(ENV_SCHEME, ""), (ENV_PORT, " ")]).unwrap();
+        assert_eq!(options.default_scheme, None);
+        assert_eq!(options.default_port, None);
+        assert_eq!(options.request_timeout, Some(DEFAULT_REQUEST_TIMEOUT));
+        assert_eq!(options.connect_timeout, Some(DEFAULT_CONNECT_TIMEOUT));
+        assert_eq!(options.read_timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        use tokio::io::AsyncWriteExt;
+
+        // A server that sends the headers, and then nothing.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n{")
+                        .await;
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                });
+            }
+        });
+
+        let client = ScrapeOptions {
+            request_timeout: None,
+            read_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        }
+        .build_client()
+        .unwrap();
+        let started = std::time::Instant::now();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .await
+            .unwrap();
+        let error = response.text().await.unwrap_err();
+        assert!(error.is_timeout(), "{:?}", error);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[parameterized(
+        scheme = { ENV_SCHEME, "ftp" },
+        port = { ENV_PORT, "80800" },
+        timeout = { ENV_TIMEOUT, "soon" },
+        negative_timeout = { ENV_TIMEOUT, "-1" },
+        connect_timeout = { ENV_CONNECT_TIMEOUT, "5s" },
+        read_timeout = { ENV_READ_TIMEOUT, "never" },
+    )]
+    fn test_from_vars_invalid(name: &str, value: &str) {
+        assert!(matches!(
+            vars(&[(name, value)]),
+            Err(ScrapeError::ConversionError { .. })
+        ));
+    }
+
+    #[parameterized(
+        single = { "http://squid.example.org:3128", Some("http://squid.example.org:3128") },
+        no_scheme = { "squid.example.org:3128", Some("http://squid.example.org:3128") },
+        fallback_chain = { "http://p1:3128|http://p2:3128;http://p3:3128;DIRECT", Some("http://p1:3128") },
+        direct = { "DIRECT", None },
+        direct_lowercase = { " direct ;http://p1:3128", None },
+    )]
+    fn test_cvmfs_proxy_url(setting: &str, expected: Option<&str>) {
+        assert_eq!(cvmfs_proxy_url(setting).unwrap().as_deref(), expected);
+    }
+
+    #[test]
+    fn test_cvmfs_proxy_chain() {
+        let proxy = |url: &str| Some(url.to_string());
+        assert_eq!(
+            cvmfs_proxy_chain("http://p1:3128|p2:3128; http://p3:3128 ;DIRECT").unwrap(),
+            vec![
+                vec![proxy("http://p1:3128"), proxy("http://p2:3128")],
+                vec![proxy("http://p3:3128")],
+                vec![None],
+            ]
+        );
+    }
+
+    #[parameterized(
+        empty = { "" },
+        empty_group = { ";DIRECT" },
+        empty_later_group = { "http://p1:3128;;DIRECT" },
+        empty_proxy = { "http://p1:3128|;DIRECT" },
+        invalid = { "http://squid example:3128" },
+        invalid_later = { "DIRECT;http://squid example:3128" },
+    )]
+    fn test_cvmfs_proxy_url_invalid(setting: &str) {
+        assert!(matches!(
+            cvmfs_proxy_url(setting),
+            Err(ScrapeError::ConversionError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_clients_follow_the_proxy_chain() {
+        let port =
+            crate::test_utilities::serve("HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nproxied")
+                .await;
+        let options = ScrapeOptions {
+            cvmfs_proxy: Some(format!(
+                "http://127.0.0.1:{}|http://127.0.0.1:{};http://127.0.0.1:{}",
+                crate::test_utilities::closed_port(),
+                port,
+                port
+            )),
+            ..Default::default()
+        };
+        let clients = options.build_fallback_clients_with_log().unwrap();
+        assert_eq!(clients.len(), 2);
+        let url = "http://cvmfs.invalid/cvmfs/info/v1/repositories.json";
+        let error = clients[0].0.get(url).send().await.unwrap_err();
+        assert!(error.is_connect(), "{:?}", error);
+        let response = clients[1].0.get(url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "proxied");
+
+        assert_eq!(
+            ScrapeOptions::default()
+                .build_fallback_clients_with_log()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_cvmfs_proxy() {
+        let port =
+            crate::test_utilities::serve("HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nproxied")
+                .await;
+        let options = ScrapeOptions {
+            cvmfs_proxy: Some(format!("http://127.0.0.1:{};DIRECT", port)),
+            ..Default::default()
+        };
+        let client = options.build_client().unwrap();
+        let response = client
+            .get("http://cvmfs.invalid/cvmfs/info/v1/repositories.json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "proxied");
+
+        let options = ScrapeOptions {
+            cvmfs_proxy: Some("".to_string()),
+            ..Default::default()
+        };
+        assert!(options.build_client().is_err());
+    }
+
+    #[parameterized(
+        system = { IpPreference::System, None },
+        prefer_ipv4 = { IpPreference::PreferIpv4, Some(IpPreference::PreferIpv4) },
+        prefer_ipv6 = { IpPreference::PreferIpv6, Some(IpPreference::PreferIpv6) },
+    )]
+    fn test_ip_preference_is_applied(preference: IpPreference, expected: Option<IpPreference>) {
+        let options = ScrapeOptions {
+            ip_preference: preference,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.dns_resolver(),
+            expected.map(PreferenceResolver::new)
+        );
+        assert!(options.build_client().is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::FLEET_FILE_SCHEMA_VERSION;
+use crate::errors::ReadError;
+use crate::models::ScrapedServer;
+
+/// The file format of saved scrapes, the servers under a schema version.
+#[derive(Serialize)]
+struct FleetFile<'a> {
+    schema_version: u32,
+    servers: &'a [ScrapedServer],
+}
+
+/// Save scrape results to a file as JSON, e.g. to compare them with a later scrape.
+///
+/// The file is written atomically: the results are written to a temporary file in the same
+/// directory, which is then renamed to `path`. A reader never sees a partially written file, and
+/// an existing file is only replaced once the new one is complete.
+///
+/// The file holds the servers under `FLEET_FILE_SCHEMA_VERSION`, so files written by other
+/// versions of the crate can be detected. Read the file back with `read_fleet_from_file`.
+pub fn write_fleet_to_file(servers: &[ScrapedServer], path: &Path) -> Result<(), std::io::Error> {
+    let json = serde_json::to_vec_pretty(&FleetFile {
+        schema_version: FLEET_FILE_SCHEMA_VERSION,
+        servers,
+    })?;
+
+    let temporary = temporary_path(path)?;
+    let result = std::fs::File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(&json)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temporary, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Load scrape results saved with `write_fleet_to_file`.
+///
+/// Fails with `ReadError::UnsupportedSchemaVersion` if the file was written with another schema
+/// version. The errors of failed servers are restored as their message only, see
+/// `CVMFSScraperError::Restored`.
+pub fn read_fleet_from_file(path: &Path) -> Result<Vec<ScrapedServer>, ReadError> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        schema_version: u32,
+        servers: serde_json::Value,
+    }
+
+    let envelope: Envelope = serde_json::from_slice(&std::fs::read(path)?)?;
+    // Check the version before the servers, so a changed format is reported as such.
+    if envelope.schema_version != FLEET_FILE_SCHEMA_VERSION {
+        return Err(ReadError::UnsupportedSchemaVersion {
+            found: envelope.schema_version,
+            supported: FLEET_FILE_SCHEMA_VERSION,
+        });
+    }
+    Ok(serde_json::from_value(envelope.servers)?)
+}
+
+/// A temporary file next to `path`, unique to this process.
+fn temporary_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    let mut temporary = std::ffi::OsString::from(".");
+    temporary.push(name);
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    Ok(path.with_file_name(temporary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::errors::{CVMFSScraperError, ScrapeError};
+    use crate::models::{Hostname, Server, ServerBackendType, ServerType};
+    use crate::test_utilities::{repository, server};
+
+    fn file_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "cvmfs-server-scraper-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_write_and_read_fleet_file() {
+        let populated = server(
+            "one.example.org",
+            ServerType::Stratum1,
+            vec![repository(
+                "software.eessi.io",
+                7,
+                1_700_000_000,
+                None,
+                None,
+            )],
+        );
+        let failed = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::AutoDetect,
+            Hostname::try_from("two.example.org").unwrap(),
+        )
+        .to_failed_server(ScrapeError::EmptyRepositoryList("two.example.org".to_string()).into());
+        let error = failed.error.to_string();
+        let servers = vec![
+            ScrapedServer::Populated(populated.clone()),
+            ScrapedServer::Failed(failed),
+        ];
+
+        let path = file_path("round-trip");
+        write_fleet_to_file(&servers, &path).unwrap();
+        // Overwriting an existing file replaces it.
+        write_fleet_to_file(&servers, &path).unwrap();
+        let read = read_fleet_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let read = read.unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].clone().get_populated_server().unwrap(), populated);
+        let failed = read[1].clone().get_failed_server().unwrap();
+        assert_eq!(failed.hostname.to_string(), "two.example.org");
+        assert!(matches!(&failed.error, CVMFSScraperError::Restored(message) if *message == error));
+        assert_eq!(failed.error.to_string(), error);
+    }
+
+    #[test]
+    fn test_read_fleet_file_errors() {
+        let path = file_path("unsupported");
+        std::fs::write(&path, r#"{"schema_version": 99, "servers": "anything"}"#).unwrap();
+        let unsupported = read_fleet_from_file(&path);
+        std::fs::write(&path, "not json").unwrap();
+        let invalid = read_fleet_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            unsupported,
+            Err(ReadError::UnsupportedSchemaVersion {
+                found: 99,
+                supported: FLEET_FILE_SCHEMA_VERSION
+            })
+        ));
+        assert!(matches!(invalid, Err(ReadError::Parse(_))));
+        assert!(matches!(read_fleet_from_file(&path), Err(ReadError::Io(_))));
+    }
+}
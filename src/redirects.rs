@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// A single HTTP redirect seen while scraping a server.
+///
+/// Fields:
+///
+/// - from: The URL that was requested.
+/// - to: The URL the server redirected to.
+/// - status: The HTTP status of the redirect response (e.g. 301 or 302).
+/// - followed: Whether the redirect was followed, see `RedirectPolicy` and
+///   `ScrapeOptions::max_redirects`.
+///
+/// Every redirect is recorded, so the chain of a request is the sequence of hops where the `to`
+/// of one hop is the `from` of the next.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub from: String,
+    pub to: String,
+    pub status: u16,
+    pub followed: bool,
+}
+
+impl RedirectHop {
+    /// Returns true if the redirect leads to a different host (or port).
+    pub fn changes_host(&self) -> bool {
+        match (Url::parse(&self.from), Url::parse(&self.to)) {
+            (Ok(from), Ok(to)) => {
+                // The default port changes with the scheme, which is not a change of host.
+                from.host_str() != to.host_str()
+                    || (from.scheme() == to.scheme() && from.port() != to.port())
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns true if the redirect changes the scheme, e.g. from http to https.
+    pub fn changes_scheme(&self) -> bool {
+        match (Url::parse(&self.from), Url::parse(&self.to)) {
+            (Ok(from), Ok(to)) => from.scheme() != to.scheme(),
+            _ => true,
+        }
+    }
+
+    /// Returns true if the redirect changes the host or the scheme.
+    pub fn is_cross_origin(&self) -> bool {
+        self.changes_host() || self.changes_scheme()
+    }
+}
+
+impl std::fmt::Display for RedirectHop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {} ({})", self.from, self.to, self.status)?;
+        if !self.followed {
+            write!(f, " (not followed)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The redirects seen by a client, shared with the redirect policy of the client.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RedirectLog(Arc<Mutex<Vec<RedirectHop>>>);
+
+impl RedirectLog {
+    pub(crate) fn record(&self, hop: RedirectHop) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).push(hop);
+    }
+
+    /// The redirects recorded so far, in the order they were seen.
+    pub(crate) fn hops(&self) -> Vec<RedirectHop> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yare::parameterized;
+
+    fn hop(from: &str, to: &str) -> RedirectHop {
+        RedirectHop {
+            from: from.to_string(),
+            to: to.to_string(),
+            status: 301,
+            followed: true,
+        }
+    }
+
+    #[parameterized(
+        same_origin = { "http://a.example.org/x", "http://a.example.org/y", false, false },
+        other_host = { "http://a.example.org/x", "http://b.example.org/x", true, false },
+        other_port = { "http://a.example.org/x", "http://a.example.org:8080/x", true, false },
+        upgrade = { "http://a.example.org/x", "https://a.example.org/x", false, true },
+        downgrade_other_host = { "https://a.example.org/x", "http://b.example.org/x", true, true },
+        invalid = { "http://a.example.org/x", "not a url", true, true },
+    )]
+    fn test_redirect_hop_origin(from: &str, to: &str, host: bool, scheme: bool) {
+        let hop = hop(from, to);
+        assert_eq!(hop.changes_host(), host);
+        assert_eq!(hop.changes_scheme(), scheme);
+        assert_eq!(hop.is_cross_origin(), host || scheme);
+    }
+
+    #[test]
+    fn test_redirect_hop_display() {
+        let mut hop = hop("http://a.example.org/x", "https://a.example.org/x");
+        assert_eq!(
+            hop.to_string(),
+            "http://a.example.org/x -> https://a.example.org/x (301)"
+        );
+        hop.followed = false;
+        assert!(hop.to_string().ends_with("(not followed)"));
+    }
+}
@@ -0,0 +1,76 @@
+use std::net::SocketAddr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::options::IpPreference;
+
+/// A DNS resolver that orders the resolved addresses by address family.
+///
+/// The connector in hyper tries the address family of the first resolved address first, and
+/// falls back to the other family if connecting does not succeed in time (happy eyeballs). By
+/// putting the preferred family first, the other family is only used as the fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PreferenceResolver {
+    preference: IpPreference,
+}
+
+impl PreferenceResolver {
+    pub(crate) fn new(preference: IpPreference) -> Self {
+        PreferenceResolver { preference }
+    }
+}
+
+impl Resolve for PreferenceResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let preference = self.preference;
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let addrs: Addrs = Box::new(order_addresses(addrs, preference).into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Order addresses so that the preferred family comes first, keeping the resolver's order within
+/// each family.
+pub(crate) fn order_addresses(
+    addrs: impl IntoIterator<Item = SocketAddr>,
+    preference: IpPreference,
+) -> Vec<SocketAddr> {
+    if preference == IpPreference::System {
+        return addrs.into_iter().collect();
+    }
+    let (v4, v6): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|addr| addr.is_ipv4());
+    match preference {
+        IpPreference::System | IpPreference::PreferIpv4 => v4.into_iter().chain(v6).collect(),
+        IpPreference::PreferIpv6 => v6.into_iter().chain(v4).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yare::parameterized;
+
+    fn addrs() -> Vec<SocketAddr> {
+        vec![
+            "[2001:db8::1]:0".parse().unwrap(),
+            "192.0.2.1:0".parse().unwrap(),
+            "[2001:db8::2]:0".parse().unwrap(),
+            "192.0.2.2:0".parse().unwrap(),
+        ]
+    }
+
+    #[parameterized(
+        system = { IpPreference::System, ["2001:db8::1", "192.0.2.1", "2001:db8::2", "192.0.2.2"] },
+        prefer_ipv4 = { IpPreference::PreferIpv4, ["192.0.2.1", "192.0.2.2", "2001:db8::1", "2001:db8::2"] },
+        prefer_ipv6 = { IpPreference::PreferIpv6, ["2001:db8::1", "2001:db8::2", "192.0.2.1", "192.0.2.2"] },
+    )]
+    fn test_order_addresses(preference: IpPreference, expected: [&str; 4]) {
+        let ordered = order_addresses(addrs(), preference)
+            .iter()
+            .map(|addr| addr.ip().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(ordered, expected);
+    }
+}
@@ -1,13 +1,14 @@
 use log::{debug, info, trace, warn};
 use std::convert::TryFrom;
+use std::time::Duration;
 use std::{fmt::Debug, time::Instant};
 
-use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
 use std::marker::PhantomData;
 
-use crate::constants::DEFAULT_GEOAPI_SERVERS;
 use crate::errors::{HostnameError, ScrapeError};
-use crate::models::{Hostname, ScrapedServer, Server, ServerBackendType};
+use crate::models::{Hostname, ScrapedServer, Server, ServerBackendType, ServerType};
+use crate::options::ScrapeOptions;
 
 pub struct WithoutServers;
 pub struct WithServers;
@@ -63,8 +64,7 @@ pub struct ValidatedAndReady;
 pub struct Scraper<State = WithoutServers> {
     servers: Option<Vec<Server>>,
     forced_repos: Vec<String>,
-    ignored_repos: Vec<String>,
-    geoapi_servers: Vec<Hostname>,
+    options: ScrapeOptions,
     _state: PhantomData<State>,
 }
 
@@ -76,6 +76,11 @@ impl Default for Scraper<WithoutServers> {
 }
 
 impl Scraper<WithoutServers> {
+    /// Create a builder that configures a scraper in one place, see `ScraperBuilder`.
+    pub fn builder() -> ScraperBuilder {
+        ScraperBuilder::new()
+    }
+
     /// Create a new Scraper.
     ///
     /// This method creates a new Scraper with no servers added and in the
@@ -84,8 +89,7 @@ impl Scraper<WithoutServers> {
         Scraper {
             servers: None,
             forced_repos: Vec::new(),
-            ignored_repos: Vec::new(),
-            geoapi_servers: DEFAULT_GEOAPI_SERVERS.clone(),
+            options: ScrapeOptions::default(),
             _state: PhantomData,
         }
     }
@@ -98,8 +102,7 @@ impl Scraper<WithoutServers> {
         Scraper {
             servers: Some(servers),
             forced_repos: self.forced_repos,
-            ignored_repos: self.ignored_repos,
-            geoapi_servers: self.geoapi_servers,
+            options: self.options,
             _state: PhantomData,
         }
     }
@@ -170,6 +173,15 @@ pub trait ScraperCommon {
         Hostname: TryFrom<S>,
         <Hostname as TryFrom<S>>::Error: Into<HostnameError>,
         Self: Sized;
+
+    /// Limit the number of servers scraped at the same time.
+    ///
+    /// By default, all servers are scraped at once. With a limit, the next server is scraped as
+    /// soon as one of the servers being scraped completes. The results are in the order of the
+    /// servers either way. A limit of 0 is treated as 1.
+    fn max_concurrent_servers(self, limit: usize) -> Self
+    where
+        Self: Sized;
 }
 
 // Implement common functionality for WithoutServers state
@@ -188,7 +200,7 @@ impl ScraperCommon for Scraper<WithoutServers> {
         I: IntoIterator<Item = S>,
         S: Into<String>,
     {
-        self.ignored_repos = repos.into_iter().map(Into::into).collect();
+        self.options.ignored_repositories = repos.into_iter().map(Into::into).collect();
         self
     }
 
@@ -198,12 +210,17 @@ impl ScraperCommon for Scraper<WithoutServers> {
         Hostname: TryFrom<S>,
         <Hostname as TryFrom<S>>::Error: Into<HostnameError>,
     {
-        self.geoapi_servers = servers
+        self.options.geoapi_servers = servers
             .into_iter()
             .map(|s| Hostname::try_from(s).map_err(Into::into))
             .collect::<Result<Vec<_>, _>>()?;
         Ok(self)
     }
+
+    fn max_concurrent_servers(mut self, limit: usize) -> Self {
+        self.options.max_concurrent_servers = Some(limit);
+        self
+    }
 }
 
 // Implement common functionality for WithServers state
@@ -222,7 +239,7 @@ impl ScraperCommon for Scraper<WithServers> {
         I: IntoIterator<Item = S>,
         S: Into<String>,
     {
-        self.ignored_repos = repos.into_iter().map(Into::into).collect();
+        self.options.ignored_repositories = repos.into_iter().map(Into::into).collect();
         self
     }
 
@@ -232,12 +249,17 @@ impl ScraperCommon for Scraper<WithServers> {
         Hostname: TryFrom<S>,
         <Hostname as TryFrom<S>>::Error: Into<HostnameError>,
     {
-        self.geoapi_servers = servers
+        self.options.geoapi_servers = servers
             .into_iter()
             .map(|s| Hostname::try_from(s).map_err(Into::into))
             .collect::<Result<Vec<_>, _>>()?;
         Ok(self)
     }
+
+    fn max_concurrent_servers(mut self, limit: usize) -> Self {
+        self.options.max_concurrent_servers = Some(limit);
+        self
+    }
 }
 
 // Implementation for WithServers state
@@ -267,8 +289,7 @@ impl Scraper<WithServers> {
         Ok(Scraper {
             servers: self.servers,
             forced_repos: self.forced_repos,
-            ignored_repos: self.ignored_repos,
-            geoapi_servers: self.geoapi_servers,
+            options: self.options,
             _state: PhantomData,
         })
     }
@@ -284,64 +305,330 @@ impl Scraper<ValidatedAndReady> {
     /// scrape was successful or not for that specific server.
     pub async fn scrape(&self) -> Vec<ScrapedServer> {
         let servers = self.servers.as_ref().unwrap();
-        scrape_servers(
+        scrape_servers(servers.clone(), self.forced_repos.clone(), &self.options).await
+    }
+
+    /// Scrape the servers, passing each result to a sink as soon as it is available.
+    ///
+    /// This is useful if you want to process (e.g. persist) each server as it completes,
+    /// rather than waiting for the entire scrape to finish. The sink is called once for
+    /// every server, both for populated and failed servers, in the order the scrapes complete.
+    ///
+    /// To forward the results to a channel, send them from the sink:
+    ///
+    /// ```rust,no_run
+    /// use cvmfs_server_scraper::{Hostname, Scraper, Server, ServerBackendType, ServerType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let servers = vec![Server::new(
+    ///         ServerType::Stratum1,
+    ///         ServerBackendType::CVMFS,
+    ///         Hostname::try_from("azure-us-east-s1.eessi.science").unwrap(),
+    ///     )];
+    ///
+    ///     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    ///     let scraper = Scraper::new().with_servers(servers).validate()?;
+    ///     scraper
+    ///         .scrape_with_sink(|server| {
+    ///             let _ = tx.send(server);
+    ///         })
+    ///         .await;
+    ///
+    ///     while let Ok(server) = rx.try_recv() {
+    ///         println!("Scraped a server, success: {}", server.is_ok());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn scrape_with_sink<F>(&self, mut sink: F)
+    where
+        F: FnMut(ScrapedServer),
+    {
+        let servers = self.servers.as_ref().unwrap();
+        scrape_servers_each(
             servers.clone(),
             self.forced_repos.clone(),
-            self.ignored_repos.clone(),
-            self.geoapi_servers.clone(),
+            &self.options,
+            |_, scraped| sink(scraped),
         )
         .await
     }
 }
 
+/// A builder for a scraper, with all configuration in one place.
+///
+/// `Scraper` takes all its servers in a single `with_servers` call, and only exposes the few
+/// options of `ScraperCommon`. That is awkward when the configuration comes from several places,
+/// e.g. a configuration file and command line flags. The builder has no states: servers,
+/// repositories and options can be set in any order. It ends in the same validated `Scraper`, so
+/// both share the validation and the scrape itself.
+///
+/// Servers can be given as `Server`s, or as hostnames with `hostnames`, in which case the backend
+/// type is the backend hint (AutoDetect unless set with `backend_hint`). To set `ScrapeOptions`
+/// without a method of their own, start from them with `with_options`; the methods then adjust
+/// them.
+///
+/// `build` validates the configuration (see `Scraper::validate`) and gives a scraper ready to
+/// scrape, while `scrape` builds and scrapes in one go.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use cvmfs_server_scraper::{Scraper, ServerBackendType, ServerType};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let results = Scraper::builder()
+///         .hostnames(
+///             ServerType::Stratum1,
+///             ["azure-us-east-s1.eessi.science", "aws-eu-central-s1.eessi.science"],
+///         )?
+///         .backend_hint(ServerBackendType::CVMFS)
+///         .forced_repositories(["software.eessi.io"])
+///         .request_timeout(Duration::from_secs(10))
+///         .max_concurrent_servers(4)
+///         .scrape()
+///         .await?;
+///     println!("Scraped {} servers", results.len());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ScraperBuilder {
+    servers: Vec<Server>,
+    hostnames: Vec<(ServerType, Hostname)>,
+    backend_hint: Option<ServerBackendType>,
+    forced_repos: Vec<String>,
+    options: ScrapeOptions,
+}
+
+impl ScraperBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder starting from the given options.
+    ///
+    /// The other methods adjust these options, e.g. `request_timeout` only replaces the request
+    /// timeout, whatever the order of the calls.
+    pub fn with_options(options: ScrapeOptions) -> Self {
+        ScraperBuilder {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Add a server.
+    pub fn server(mut self, server: Server) -> Self {
+        self.servers.push(server);
+        self
+    }
+
+    /// Add several servers.
+    pub fn servers<I>(mut self, servers: I) -> Self
+    where
+        I: IntoIterator<Item = Server>,
+    {
+        self.servers.extend(servers);
+        self
+    }
+
+    /// Add servers of the given type by hostname, using the backend hint as the backend type.
+    ///
+    /// Fails if any of the hostnames is invalid, in which case none are added.
+    pub fn hostnames<I, S>(
+        mut self,
+        server_type: ServerType,
+        hostnames: I,
+    ) -> Result<Self, HostnameError>
+    where
+        I: IntoIterator<Item = S>,
+        Hostname: TryFrom<S>,
+        <Hostname as TryFrom<S>>::Error: Into<HostnameError>,
+    {
+        let hostnames = hostnames
+            .into_iter()
+            .map(|s| Hostname::try_from(s).map_err(Into::into))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.hostnames.extend(
+            hostnames
+                .into_iter()
+                .map(|hostname| (server_type, hostname)),
+        );
+        Ok(self)
+    }
+
+    /// The backend type of the servers added with `hostnames`, AutoDetect by default.
+    ///
+    /// This applies to every server added by hostname, regardless of the order of the calls.
+    /// Servers added as `Server`s keep their own backend type.
+    pub fn backend_hint(mut self, backend_type: ServerBackendType) -> Self {
+        self.backend_hint = Some(backend_type);
+        self
+    }
+
+    /// The repositories to scrape on every server, see `ScraperCommon::forced_repositories`.
+    pub fn forced_repositories<I, S>(mut self, repos: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.forced_repos = repos.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The repositories never to scrape, see `ScraperCommon::ignored_repositories`.
+    pub fn ignored_repositories<I, S>(mut self, repos: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.ignored_repositories = repos.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The timeout for establishing a connection, see `ScrapeOptions::connect_timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// The timeout for each HTTP request, see `ScrapeOptions::request_timeout`.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.options.request_timeout = Some(timeout);
+        self
+    }
+
+    /// The timeout for each read from a connection, see `ScrapeOptions::read_timeout`.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.options.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Limit the number of servers scraped at the same time, see
+    /// `ScraperCommon::max_concurrent_servers`.
+    pub fn max_concurrent_servers(mut self, limit: usize) -> Self {
+        self.options.max_concurrent_servers = Some(limit);
+        self
+    }
+
+    /// Limit the number of repositories scraped at the same time on each server, see
+    /// `ScrapeOptions::repository_concurrency`.
+    pub fn repository_concurrency(mut self, limit: usize) -> Self {
+        self.options.repository_concurrency = limit;
+        self
+    }
+
+    /// Validate the configuration, giving a scraper ready to scrape.
+    ///
+    /// Fails as `Scraper::validate` does, e.g. if a server uses the S3 backend and there are
+    /// no forced repositories.
+    pub fn build(self) -> Result<Scraper<ValidatedAndReady>, ScrapeError> {
+        let backend_type = self.backend_hint.unwrap_or(ServerBackendType::AutoDetect);
+        let mut servers = self.servers;
+        servers.extend(
+            self.hostnames
+                .into_iter()
+                .map(|(server_type, hostname)| Server::new(server_type, backend_type, hostname)),
+        );
+        Scraper {
+            servers: Some(servers),
+            forced_repos: self.forced_repos,
+            options: self.options,
+            _state: PhantomData::<WithServers>,
+        }
+        .validate()
+    }
+
+    /// Build the scraper and scrape the servers, see `build` and `Scraper::scrape`.
+    pub async fn scrape(self) -> Result<Vec<ScrapedServer>, ScrapeError> {
+        Ok(self.build()?.scrape().await)
+    }
+}
+
 /// Scrape a list of servers in parallel.
 ///
 /// This function scrapes a list of servers in parallel and returns a list of ScrapedServer objects,
-async fn scrape_servers<R>(
+/// in the same order as the servers were given.
+pub(crate) async fn scrape_servers<R>(
     servers: Vec<Server>,
     scrape_repos: Vec<R>,
-    ignored_repos: Vec<R>,
-    geoapi_hosts: Vec<Hostname>,
+    options: &ScrapeOptions,
 ) -> Vec<ScrapedServer>
 where
-    R: AsRef<str> + Debug + std::fmt::Display + Clone,
+    R: AsRef<str>,
+{
+    let mut results: Vec<Option<ScrapedServer>> = servers.iter().map(|_| None).collect();
+    scrape_servers_each(servers, scrape_repos, options, |index, scraped| {
+        results[index] = Some(scraped);
+    })
+    .await;
+
+    let scraped_servers: Vec<ScrapedServer> = results.into_iter().flatten().collect();
+    trace!(
+        "Scraping servers completed with results: {:?}",
+        scraped_servers
+    );
+    scraped_servers
+}
+
+/// Scrape a list of servers in parallel, passing each result to a callback as it completes.
+///
+/// The callback is given the index of the server in the input list along with the result, and
+/// is called in the order the scrapes complete, not the order of the servers.
+pub(crate) async fn scrape_servers_each<R, F>(
+    servers: Vec<Server>,
+    scrape_repos: Vec<R>,
+    options: &ScrapeOptions,
+    mut callback: F,
+) where
+    R: AsRef<str>,
+    F: FnMut(usize, ScrapedServer),
 {
-    let geoapi_servers = if geoapi_hosts.is_empty() {
+    if options.geoapi_servers.is_empty() {
         debug!("No geoapi servers provided to scrape_server, using default servers");
-        DEFAULT_GEOAPI_SERVERS.clone()
-    } else {
-        geoapi_hosts
-    };
+    }
 
+    let scrape_repos = scrape_repos
+        .iter()
+        .map(|repo| repo.as_ref())
+        .collect::<Vec<_>>();
     let start = Instant::now();
     let scrapes_attempted = servers.len();
     trace!(
         "Start of scraping run. Servers: {:?}, repositories: {:?} (ignored: {:?}), geoapi_servers: {:?}",
         servers,
         scrape_repos,
-        ignored_repos,
-        geoapi_servers
+        options.ignored_repositories,
+        options.geoapi_servers
     );
-    let futures = servers.iter().map(|server| {
-        let repolist = scrape_repos.clone();
-        let ignore = ignored_repos.clone();
-        let geoapi_servers = geoapi_servers.clone();
-        async move {
-            server
-                .scrape(
-                    repolist.clone(),
-                    ignore.clone(),
-                    Some(geoapi_servers.clone()),
-                )
-                .await
-        }
-    });
+    let limit = options
+        .max_concurrent_servers
+        .map_or(servers.len(), |limit| limit.max(1));
+    let mut pending = servers.iter().enumerate();
+    let mut futures = FuturesUnordered::new();
 
-    let scraped_servers = join_all(futures).await;
-
-    for server in scraped_servers.iter() {
-        match server {
+    let mut succeeded = 0;
+    loop {
+        while futures.len() < limit {
+            match pending.next() {
+                Some((index, server)) => {
+                    let repolist = scrape_repos.clone();
+                    futures.push(async move {
+                        (index, server.scrape_with_options(repolist, options).await)
+                    });
+                }
+                None => break,
+            }
+        }
+        let Some((index, server)) = futures.next().await else {
+            break;
+        };
+        match &server {
             ScrapedServer::Populated(popserver) => {
+                succeeded += 1;
                 info!(
                     "Scraped server: {} with {} repositories",
                     popserver.hostname,
@@ -355,25 +642,23 @@ where
                 );
             }
         }
+        callback(index, server);
     }
 
     info!(
         "Scraped {} servers ({} succeeded), run duration: {:?}",
         scrapes_attempted,
-        scraped_servers.iter().filter(|s| s.is_ok()).count(),
+        succeeded,
         start.elapsed()
     );
-    trace!(
-        "Scraping servers completed with results: {:?}",
-        scraped_servers
-    );
-    scraped_servers
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::{Hostname, Server, ServerBackendType, ServerType};
+    use crate::test_utilities;
+    use yare::parameterized;
 
     #[tokio::test]
     async fn test_online_cvmfs_servers_using_scan_servers() {
@@ -396,7 +681,11 @@ mod tests {
         ];
 
         let repolist = vec!["software.eessi.io", "dev.eessi.io", "riscv.eessi.io"];
-        let results = scrape_servers(servers, repolist.clone(), vec![], vec![]).await;
+        let options = ScrapeOptions {
+            geoapi_servers: vec![],
+            ..Default::default()
+        };
+        let results = scrape_servers(servers, repolist.clone(), &options).await;
 
         for result in results {
             match result {
@@ -411,4 +700,174 @@ mod tests {
             }
         }
     }
+
+    #[parameterized(
+        unbounded = { None, 4 },
+        one = { Some(1), 1 },
+        zero = { Some(0), 1 },
+        two = { Some(2), 2 },
+    )]
+    #[test_macro(tokio::test)]
+    async fn test_scrape_servers_with_limit(limit: Option<usize>, expected_in_flight: usize) {
+        // Each server fails on its repositories.json, the only request it makes, so the
+        // requests in flight are the servers being scraped.
+        let (port, max_in_flight) = test_utilities::serve_delayed(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            Duration::from_millis(200),
+        )
+        .await;
+        let servers = (0..4)
+            .map(|index| {
+                Server::new(
+                    ServerType::Stratum1,
+                    ServerBackendType::CVMFS,
+                    Hostname::try_from("127.0.0.1").unwrap(),
+                )
+                .with_base_path(format!("server{}", index))
+                .with_port(port)
+            })
+            .collect();
+        let options = ScrapeOptions {
+            max_concurrent_servers: limit,
+            ..Default::default()
+        };
+
+        let results = scrape_servers(servers, Vec::<String>::new(), &options).await;
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|server| server.get_failed_server().unwrap().base_path.unwrap())
+                .collect::<Vec<_>>(),
+            vec!["server0", "server1", "server2", "server3"]
+        );
+        assert_eq!(
+            max_in_flight.load(std::sync::atomic::Ordering::SeqCst),
+            expected_in_flight
+        );
+    }
+
+    #[test]
+    fn test_scraper_builder() {
+        let scraper = Scraper::builder()
+            .hostnames(ServerType::Stratum1, ["s1.example.org", "s2.example.org"])
+            .unwrap()
+            .server(Server::new(
+                ServerType::Stratum0,
+                ServerBackendType::CVMFS,
+                Hostname::try_from("s0.example.org").unwrap(),
+            ))
+            .backend_hint(ServerBackendType::S3)
+            .forced_repositories(["software.eessi.io"])
+            .ignored_repositories(["dev.eessi.io"])
+            .connect_timeout(Duration::from_secs(1))
+            .request_timeout(Duration::from_secs(2))
+            .read_timeout(Duration::from_secs(3))
+            .max_concurrent_servers(3)
+            .repository_concurrency(2)
+            .build()
+            .unwrap();
+
+        let servers = scraper.servers.as_ref().unwrap();
+        assert_eq!(
+            servers
+                .iter()
+                .map(|server| (
+                    server.hostname.to_str(),
+                    server.server_type,
+                    server.backend_type
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "s0.example.org",
+                    ServerType::Stratum0,
+                    ServerBackendType::CVMFS
+                ),
+                (
+                    "s1.example.org",
+                    ServerType::Stratum1,
+                    ServerBackendType::S3
+                ),
+                (
+                    "s2.example.org",
+                    ServerType::Stratum1,
+                    ServerBackendType::S3
+                ),
+            ]
+        );
+        assert_eq!(scraper.forced_repos, vec!["software.eessi.io"]);
+        assert_eq!(scraper.options.ignored_repositories, vec!["dev.eessi.io"]);
+        assert_eq!(
+            scraper.options.connect_timeout,
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            scraper.options.request_timeout,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(scraper.options.read_timeout, Some(Duration::from_secs(3)));
+        assert_eq!(scraper.options.max_concurrent_servers, Some(3));
+        assert_eq!(scraper.options.repository_concurrency, 2);
+
+        // S3 servers need forced repositories.
+        assert!(matches!(
+            Scraper::builder()
+                .hostnames(ServerType::Stratum1, ["s1.example.org"])
+                .unwrap()
+                .backend_hint(ServerBackendType::S3)
+                .build(),
+            Err(ScrapeError::EmptyRepositoryList(_))
+        ));
+        assert!(Scraper::builder()
+            .hostnames(ServerType::Stratum1, ["not a hostname"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_scraper_builder_with_options() {
+        let options = ScrapeOptions {
+            info_path: "custom/info".to_string(),
+            request_timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let scraper = ScraperBuilder::with_options(options)
+            .request_timeout(Duration::from_secs(2))
+            .server(Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::CVMFS,
+                Hostname::try_from("s1.example.org").unwrap(),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(scraper.options.info_path, "custom/info");
+        assert_eq!(
+            scraper.options.request_timeout,
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrape_with_sink_receives_failed_servers() {
+        let servers = vec![
+            Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::CVMFS,
+                Hostname::try_from("localhost").unwrap(),
+            ),
+            Server::new(
+                ServerType::Stratum1,
+                ServerBackendType::CVMFS,
+                Hostname::try_from("127.0.0.1").unwrap(),
+            ),
+        ];
+
+        let scraper = Scraper::new().with_servers(servers).validate().unwrap();
+        let mut received = Vec::new();
+        scraper
+            .scrape_with_sink(|server| received.push(server))
+            .await;
+
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|server| server.is_failed()));
+    }
 }
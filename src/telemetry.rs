@@ -0,0 +1,252 @@
+//! OpenTelemetry spans for scrapes, enabled by the `opentelemetry` feature.
+//!
+//! Spans are created with the global tracer provider (`opentelemetry::global`), so the
+//! application decides where they are exported. `install_otlp_exporter` sets up a provider
+//! exporting them to an OTLP collector (e.g. Jaeger) over HTTP. Without the feature,
+//! `ScrapeSpan` only measures the duration of the scrape, every other method is a no-op.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "opentelemetry")]
+use opentelemetry::{
+    global,
+    trace::{FutureExt, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+#[cfg(feature = "opentelemetry")]
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+#[cfg(feature = "opentelemetry")]
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+
+use crate::models::Server;
+
+/// The name of the tracer creating the spans.
+#[cfg(feature = "opentelemetry")]
+const TRACER_NAME: &str = "cvmfs_server_scraper";
+
+/// Export the spans of scrapes to an OTLP collector over HTTP, setting the global tracer provider.
+///
+/// The spans are sent in batches to the given endpoint, the full URL of the traces endpoint of
+/// the collector (e.g. `http://localhost:4318/v1/traces`). Without an endpoint, the
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` and `OTEL_EXPORTER_OTLP_ENDPOINT` environment variables
+/// apply, defaulting to a collector on localhost. The spans are reported as coming from the
+/// given service.
+///
+/// The returned provider should be shut down before the application exits, which exports the
+/// spans not yet sent. Only with the `opentelemetry` feature.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use cvmfs_server_scraper::{install_otlp_exporter, Hostname, Server, ServerBackendType, ServerType};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let provider = install_otlp_exporter(Some("http://localhost:4318/v1/traces"), "monitor")?;
+///
+///     let server = Server::new(
+///         ServerType::Stratum1,
+///         ServerBackendType::AutoDetect,
+///         Hostname::try_from("aws-eu-central-s1.eessi.science")?,
+///     );
+///     let scraped = server.scrape_with_options(Vec::<String>::new(), &Default::default()).await;
+///     println!("{}", scraped.is_failed());
+///
+///     provider.shutdown()?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "opentelemetry")]
+pub fn install_otlp_exporter(
+    endpoint: Option<&str>,
+    service_name: &str,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let mut exporter = SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter.build()?)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+/// A span covering the scrape of a server or of a single repository.
+///
+/// The span ends when `finish` is called, which returns the duration of the scrape (see
+/// `ScrapeMetrics`). A span that is dropped without being finished is ended by the tracer without
+/// the result attributes.
+pub(crate) struct ScrapeSpan {
+    #[cfg(feature = "opentelemetry")]
+    cx: Context,
+    started: Instant,
+}
+
+#[cfg(feature = "opentelemetry")]
+impl ScrapeSpan {
+    fn start(name: &'static str, attributes: Vec<KeyValue>) -> Self {
+        let tracer = global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder(name)
+            .with_attributes(attributes)
+            .start(&tracer);
+        ScrapeSpan {
+            cx: Context::current_with_span(span),
+            started: Instant::now(),
+        }
+    }
+
+    /// Start a span for the scrape of a server.
+    pub(crate) fn server(server: &Server) -> Self {
+        Self::start(
+            "Server::scrape",
+            vec![
+                KeyValue::new("server.hostname", server.hostname.to_string()),
+                KeyValue::new("server.type", server.server_type.to_string()),
+            ],
+        )
+    }
+
+    /// Start a span for the scrape of a repository, as a child of the current span (if any).
+    pub(crate) fn repository(name: &str) -> Self {
+        Self::start(
+            "RepositoryOrReplica::scrape",
+            vec![KeyValue::new("repo.name", name.to_string())],
+        )
+    }
+
+    /// Run the future with this span as the current span, so spans started by it are children.
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        future.with_context(self.cx.clone())
+    }
+
+    /// Record the revision of a scraped repository.
+    pub(crate) fn set_revision(&self, revision: u64) {
+        self.cx.span().set_attribute(KeyValue::new(
+            "repo.revision",
+            i64::try_from(revision).unwrap_or(i64::MAX),
+        ));
+    }
+
+    /// Record the outcome and duration of the scrape, and end the span.
+    pub(crate) fn finish(self, error: Option<String>) -> Duration {
+        let duration = self.started.elapsed();
+        let span = self.cx.span();
+        let duration_ms = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+        span.set_attribute(KeyValue::new("scrape.duration_ms", duration_ms));
+        span.set_attribute(KeyValue::new("scrape.success", error.is_none()));
+        if let Some(error) = error {
+            span.set_status(Status::error(error));
+        }
+        span.end();
+        duration
+    }
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+impl ScrapeSpan {
+    #[inline]
+    pub(crate) fn server(_server: &Server) -> Self {
+        ScrapeSpan {
+            started: Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn repository(_name: &str) -> Self {
+        ScrapeSpan {
+            started: Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        future
+    }
+
+    #[inline]
+    pub(crate) fn set_revision(&self, _revision: u64) {}
+
+    #[inline]
+    pub(crate) fn finish(self, _error: Option<String>) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[cfg(all(test, feature = "opentelemetry"))]
+mod tests {
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+
+    use crate::models::{Hostname, ServerBackendType, ServerType};
+    use crate::options::ScrapeOptions;
+
+    use super::*;
+
+    fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    #[tokio::test]
+    async fn test_scrape_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        global::set_tracer_provider(provider);
+
+        let server = Server::new(
+            ServerType::Stratum1,
+            ServerBackendType::S3,
+            Hostname::try_from("localhost").unwrap(),
+        );
+        let result = server
+            .scrape_with_options(vec!["software.eessi.io"], &ScrapeOptions::default())
+            .await;
+        assert!(result.is_failed());
+
+        let spans = exporter.get_finished_spans().unwrap();
+        // The tracer provider is global, so other tests may add spans of their own. Start from
+        // the repository span of this scrape and find its parent.
+        let repo_span = spans
+            .iter()
+            .find(|span| {
+                span.name == "RepositoryOrReplica::scrape"
+                    && attribute(span, "repo.name") == Some(Value::from("software.eessi.io"))
+            })
+            .unwrap();
+        assert_eq!(
+            attribute(repo_span, "scrape.success"),
+            Some(Value::from(false))
+        );
+
+        let server_span = spans
+            .iter()
+            .find(|span| span.span_context.span_id() == repo_span.parent_span_id)
+            .unwrap();
+        assert_eq!(server_span.name, "Server::scrape");
+        assert_eq!(
+            attribute(server_span, "server.hostname"),
+            Some(Value::from("localhost"))
+        );
+        assert_eq!(
+            attribute(server_span, "server.type"),
+            Some(Value::from("stratum1"))
+        );
+        assert_eq!(
+            attribute(server_span, "scrape.success"),
+            Some(Value::from(false))
+        );
+        assert!(attribute(server_span, "scrape.duration_ms").is_some());
+    }
+}
@@ -0,0 +1,142 @@
+//! Helpers for building scrape results in unit tests.
+
+use chrono::DateTime;
+
+use crate::models::{
+    Hostname, MaybeRfc2822DateTime, PopulatedRepositoryOrReplica, PopulatedServer, ServerType,
+};
+
+/// Build a populated repository, with optional last_snapshot and last_gc date strings.
+pub fn repository(
+    name: &str,
+    revision: u64,
+    timestamp: i64,
+    last_snapshot: Option<&str>,
+    last_gc: Option<&str>,
+) -> PopulatedRepositoryOrReplica {
+    PopulatedRepositoryOrReplica {
+        last_snapshot: last_snapshot.map(|s| MaybeRfc2822DateTime(Some(s.to_string()))),
+        last_gc: last_gc.map(|s| MaybeRfc2822DateTime(Some(s.to_string()))),
+        ..PopulatedRepositoryOrReplica::mock(
+            name,
+            revision,
+            DateTime::from_timestamp(timestamp, 0).unwrap(),
+        )
+    }
+}
+
+/// Build a populated CVMFS server with the given repositories and empty metadata.
+pub fn server(
+    hostname: &str,
+    server_type: ServerType,
+    repositories: Vec<PopulatedRepositoryOrReplica>,
+) -> PopulatedServer {
+    PopulatedServer::mock(
+        Hostname::try_from(hostname).unwrap(),
+        server_type,
+        repositories,
+    )
+}
+
+/// As `serve`, holding each response back for `delay`, and tracking the requests in flight.
+///
+/// Returns the port and the highest number of requests that were in flight at the same time,
+/// e.g. to check a concurrency limit. Each connection carries a single request.
+pub async fn serve_delayed(
+    response: &str,
+    delay: std::time::Duration,
+) -> (u16, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let response = Arc::new(response.to_string());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let max = max_in_flight.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let response = response.clone();
+            let in_flight = in_flight.clone();
+            let max = max.clone();
+            tokio::spawn(async move {
+                let mut buffer = [0; 4096];
+                match stream.read(&mut buffer).await {
+                    Ok(n) if n > 0 => {}
+                    _ => return,
+                }
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(delay).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    (port, max_in_flight)
+}
+
+/// A local port nothing listens on, for tests of refused connections.
+///
+/// The port is bound and released again, so it is free unless something else grabs it in the
+/// meantime.
+pub fn closed_port() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// Serve the given raw HTTP response to every connection on a local port, returning the port.
+///
+/// Any `{port}` in the response is replaced by the port, e.g. for redirects back to the server.
+/// The request is read and discarded, so this is only useful for tests that need a fixed
+/// response. Must be called from within a Tokio runtime.
+pub async fn serve(response: &str) -> u16 {
+    let response = response.to_string();
+    serve_with(move |_| response.clone()).await
+}
+
+/// As `serve`, with the raw HTTP response to each request given by `respond`.
+///
+/// `respond` is called with the request as received (request line and headers), e.g. to answer
+/// by path or to check the headers sent.
+pub async fn serve_with<F>(respond: F) -> u16
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let respond = std::sync::Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                // Respond after the first read, which holds the request headers for the small
+                // requests in tests. Waiting for the end of the headers would hang on clients
+                // that do not speak plain HTTP, e.g. a TLS handshake.
+                let mut buffer = [0; 4096];
+                let request = match stream.read(&mut buffer).await {
+                    Ok(n) if n > 0 => String::from_utf8_lossy(&buffer[..n]).to_string(),
+                    _ => return,
+                };
+                let response = respond(&request).replace("{port}", &port.to_string());
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    port
+}
+
+/// A 200 OK response with the given body, for `serve` and `serve_with`.
+pub fn ok_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
@@ -1,7 +1,8 @@
 use log::trace;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
-use reqwest::Client;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION};
+use reqwest::{Client, Response, StatusCode, Url};
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::fmt::Display;
@@ -9,8 +10,18 @@ use std::num::ParseIntError;
 
 use chrono::{DateTime, Utc};
 
-use crate::errors::{ManifestError, ScrapeError};
-use crate::models::HexString;
+use crate::errors::{DocumentKind, ManifestError, ScrapeError};
+use crate::models::{CacheValidators, HexString};
+
+/// Parse a semver version requirement, e.g. ">=2.11" from a configuration file.
+///
+/// The requirement can be used with `ServerMetadata::meets_minimum_version` and
+/// `all_servers_meet_version`.
+pub fn parse_version_requirement(requirement: &str) -> Result<semver::VersionReq, ScrapeError> {
+    semver::VersionReq::parse(requirement.trim()).map_err(|e| {
+        ScrapeError::conversion(requirement, format!("Invalid version requirement: {}", e))
+    })
+}
 
 /// Parse a boolean field from a manifest.
 ///
@@ -63,19 +74,87 @@ pub fn parse_timestamp_field(
         })
 }
 
+/// Check the status of a response, turning redirects that were not followed into errors.
+pub fn check_response(response: Response) -> Result<Response, ScrapeError> {
+    if response.status().is_redirection() {
+        if let Some(location) = response.headers().get(LOCATION) {
+            return Err(ScrapeError::UnexpectedRedirect {
+                url: response.url().to_string(),
+                location: location.to_str().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    Ok(response.error_for_status()?)
+}
+
+/// The local path of a `file://` URL.
+fn local_path(url: &str) -> Result<std::path::PathBuf, ScrapeError> {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| ScrapeError::LocalFileError(format!("Invalid file URL: {}", url)))
+}
+
+/// Fetch a document as text.
+///
+/// `file://` URLs are read from the local filesystem (see `Server::with_local_root`), everything
+/// else is fetched over HTTP with the given client.
+async fn get_text(client: &Client, url: &str) -> Result<String, ScrapeError> {
+    Ok(get_text_and_url(client, url).await?.0)
+}
+
+/// As `get_text`, but also returning the URL the document was fetched from after any redirects.
+async fn get_text_and_url(client: &Client, url: &str) -> Result<(String, String), ScrapeError> {
+    if url.starts_with("file://") {
+        return Ok((read_local_file(url).await?, url.to_string()));
+    }
+    let response = check_response(client.get(url).send().await?)?;
+    let final_url = response.url().to_string();
+    Ok((response.text().await?, final_url))
+}
+
+/// The error for a local file that could not be read, telling a missing file apart.
+fn local_file_error(path: &std::path::Path, error: std::io::Error) -> ScrapeError {
+    let message = format!("{}: {}", path.display(), error);
+    match error.kind() {
+        std::io::ErrorKind::NotFound => ScrapeError::LocalFileNotFound(message),
+        _ => ScrapeError::LocalFileError(message),
+    }
+}
+
+async fn read_local_file(url: &str) -> Result<String, ScrapeError> {
+    let path = local_path(url)?;
+    tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| local_file_error(&path, e))
+}
+
 pub async fn fetch_text<T>(client: &Client, url: T) -> Result<String, ScrapeError>
 where
     T: Display,
 {
     trace!("Fetching text from {}", url);
-    let response = client
-        .get(url.to_string())
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
-    Ok(response)
+    get_text(client, &url.to_string()).await
+}
+
+/// Fetch a binary document, e.g. a tag history database.
+///
+/// As with text, `file://` URLs are read from the local filesystem.
+#[cfg(feature = "tags")]
+pub async fn fetch_bytes<T>(client: &Client, url: T) -> Result<Vec<u8>, ScrapeError>
+where
+    T: Display,
+{
+    trace!("Fetching bytes from {}", url);
+    let url = url.to_string();
+    if url.starts_with("file://") {
+        let path = local_path(&url)?;
+        return tokio::fs::read(&path)
+            .await
+            .map_err(|e| local_file_error(&path, e));
+    }
+    let response = check_response(client.get(url).send().await?)?;
+    Ok(response.bytes().await?.to_vec())
 }
 
 pub async fn fetch_json<T, U>(client: &Client, url: T) -> Result<U, ScrapeError>
@@ -84,18 +163,152 @@ where
     U: DeserializeOwned,
 {
     trace!("Fetching JSON from {}", url);
-    let response = client
-        .get(url.to_string())
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let response = get_text(client, &url.to_string()).await?;
 
     let json: U = serde_json::from_str(&response)?;
     Ok(json)
 }
 
+/// Fetch text, with the validators of the response, see `fetch_text_if_changed`.
+///
+/// `file://` URLs have no validators.
+pub async fn fetch_text_with_validators<T>(
+    client: &Client,
+    url: T,
+) -> Result<(String, CacheValidators), ScrapeError>
+where
+    T: Display,
+{
+    trace!("Fetching text from {}", url);
+    let url = url.to_string();
+    if url.starts_with("file://") {
+        return Ok((read_local_file(&url).await?, CacheValidators::default()));
+    }
+    let response = check_response(client.get(url).send().await?)?;
+    let validators = validators_of(response.headers());
+    Ok((response.text().await?, validators))
+}
+
+/// Fetch text only if it changed since it was fetched with the given validators, using a
+/// conditional request.
+///
+/// Returns None if the server responds with 304 Not Modified. A server that does not support
+/// conditional requests returns the full document, which is then returned as normal, with its
+/// new validators.
+pub async fn fetch_text_if_changed<T>(
+    client: &Client,
+    url: T,
+    validators: &CacheValidators,
+) -> Result<Option<(String, CacheValidators)>, ScrapeError>
+where
+    T: Display,
+{
+    trace!("Fetching text from {} if changed ({:?})", url, validators);
+    let url = url.to_string();
+    if url.starts_with("file://") {
+        return fetch_text_with_validators(client, url).await.map(Some);
+    }
+    let mut request = client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let response = check_response(response)?;
+    let validators = validators_of(response.headers());
+    Ok(Some((response.text().await?, validators)))
+}
+
+/// The validators in the headers of a response.
+fn validators_of(headers: &HeaderMap) -> CacheValidators {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    CacheValidators {
+        last_modified: header(LAST_MODIFIED),
+        etag: header(ETAG),
+    }
+}
+
+/// Check that a URL can be fetched, without fetching the document itself.
+///
+/// HTTP URLs are checked with a HEAD request, `file://` URLs by checking that the file exists.
+pub async fn probe<T>(client: &Client, url: T) -> Result<(), ScrapeError>
+where
+    T: Display,
+{
+    trace!("Probing {}", url);
+    let url = url.to_string();
+    if url.starts_with("file://") {
+        let path = local_path(&url)?;
+        return tokio::fs::metadata(&path)
+            .await
+            .map(|_| ())
+            .map_err(|e| local_file_error(&path, e));
+    }
+    check_response(client.head(url).send().await?)?;
+    Ok(())
+}
+
+/// Fetch a JSON document, adding the document kind and context to any error.
+pub async fn fetch_document<T, U>(
+    client: &Client,
+    url: T,
+    kind: DocumentKind,
+    context: &str,
+) -> Result<U, ScrapeError>
+where
+    T: Display,
+    U: DeserializeOwned,
+{
+    fetch_json(client, url)
+        .await
+        .map_err(|error| error.in_document(kind, context))
+}
+
+/// A document fetched with `fetch_document_and_url`.
+pub struct Fetched<U> {
+    pub document: U,
+    /// The URL the document was fetched from, after any redirects.
+    pub url: String,
+    /// The size of the document, in bytes.
+    pub bytes: usize,
+}
+
+/// As `fetch_document`, but also returning the URL the document was fetched from after any
+/// redirects and the size of the document.
+pub async fn fetch_document_and_url<T, U>(
+    client: &Client,
+    url: T,
+    kind: DocumentKind,
+    context: &str,
+) -> Result<Fetched<U>, ScrapeError>
+where
+    T: Display,
+    U: DeserializeOwned,
+{
+    trace!("Fetching JSON from {}", url);
+    let fetched = async {
+        let (text, url) = get_text_and_url(client, &url.to_string()).await?;
+        Ok::<_, ScrapeError>(Fetched {
+            document: serde_json::from_str(&text)?,
+            url,
+            bytes: text.len(),
+        })
+    };
+    fetched
+        .await
+        .map_err(|error| error.in_document(kind, context))
+}
+
 pub fn generate_random_string(length: usize) -> String {
     thread_rng()
         .sample_iter(&Alphanumeric)
@@ -109,6 +322,54 @@ mod tests {
     use super::*;
     use yare::parameterized;
 
+    #[tokio::test]
+    async fn test_missing_local_file_is_not_found() {
+        let client = Client::new();
+        let dir = std::env::temp_dir().join(format!("cvmfs-missing-{}", std::process::id()));
+        let url = Url::from_file_path(dir.join(".cvmfs_status.json"))
+            .unwrap()
+            .to_string();
+
+        let error = fetch_text(&client, &url).await.unwrap_err();
+        assert!(
+            matches!(error, ScrapeError::LocalFileNotFound(_)),
+            "{}",
+            error
+        );
+        assert!(error.is_not_found());
+        assert!(error.is_fetch_error());
+        assert!(probe(&client, &url).await.unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn test_validators_of() {
+        let mut headers = HeaderMap::new();
+        assert!(validators_of(&headers).is_empty());
+        headers.insert(ETAG, "\"5e1f-61b\"".parse().unwrap());
+        headers.insert(
+            LAST_MODIFIED,
+            "Tue, 18 Jun 2024 13:40:04 GMT".parse().unwrap(),
+        );
+        assert_eq!(
+            validators_of(&headers),
+            CacheValidators {
+                last_modified: Some("Tue, 18 Jun 2024 13:40:04 GMT".to_string()),
+                etag: Some("\"5e1f-61b\"".to_string()),
+            }
+        );
+    }
+
+    #[parameterized(
+        minimum = { ">=2.11", true },
+        whitespace = { " >=2.11.0 ", true },
+        caret = { "^2.10", true },
+        invalid = { "two point eleven", false },
+        empty_operator = { ">=", false },
+    )]
+    fn test_parse_version_requirement(requirement: &str, valid: bool) {
+        assert_eq!(parse_version_requirement(requirement).is_ok(), valid);
+    }
+
     #[parameterized(
         yes = { "yes", true },        
         no = { "no", false },
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+/// A non-fatal problem found while scraping a server.
+///
+/// Warnings are attached to a populated server, and describe conditions that did not prevent
+/// the scrape from succeeding, but which may still be of interest to the operator. A scrape
+/// without any such conditions has no warnings.
+///
+/// - ServerTypeMismatch: The contents of repositories.json do not match the server type, but
+///   the mismatch was configured to be non-fatal (see `ScrapeOptions::sync_server_lenient`).
+/// - NoRepositoriesPublished: repositories.json is valid, but lists no repositories or replicas
+///   (see `ScrapeOptions::empty_repositories`).
+/// - MetadataConversion: A metadata field in repositories.json could not be parsed. The raw value
+///   is kept in the server metadata, see `ServerMetadata`.
+/// - UnsupportedSchema: repositories.json advertises a schema version other than the one this
+///   crate supports (`SUPPORTED_REPOSITORIES_JSON_SCHEMA`), so some data may be missing or
+///   misread. Updating the crate may help.
+/// - ClockSkew: The manifest publication time or last snapshot of a repository is in the future
+///   relative to the local clock, by `skew`. See `PopulatedRepositoryOrReplica::clock_skew`.
+/// - GcAfterSnapshot: Garbage collection of a repository ran after its last snapshot, see
+///   `PopulatedRepositoryOrReplica::gc_after_snapshot`.
+/// - CrossOriginRedirect: A redirect that changes the host or the scheme was followed. See
+///   `PopulatedServer::redirects` for the full list of redirects.
+///
+/// New kinds of warnings will be added over time, so the enum is non-exhaustive. Warnings
+/// serialize with the kind in `kind` and any details in `details`, e.g.
+/// `{"kind": "server_type_mismatch", "details": "..."}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ScrapeWarning {
+    ServerTypeMismatch(String),
+    NoRepositoriesPublished,
+    MetadataConversion {
+        field: String,
+        value: String,
+        error: String,
+    },
+    UnsupportedSchema {
+        advertised: u32,
+        supported: u32,
+    },
+    ClockSkew {
+        repository: String,
+        skew: std::time::Duration,
+    },
+    GcAfterSnapshot {
+        repository: String,
+    },
+    CrossOriginRedirect {
+        from: String,
+        to: String,
+    },
+}
+
+impl std::fmt::Display for ScrapeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrapeWarning::ServerTypeMismatch(message) => {
+                write!(f, "Server type mismatch: {}", message)
+            }
+            ScrapeWarning::NoRepositoriesPublished => {
+                write!(f, "No repositories published in repositories.json")
+            }
+            ScrapeWarning::MetadataConversion {
+                field,
+                value,
+                error,
+            } => write!(
+                f,
+                "Unable to parse metadata field {} ({:?}): {}",
+                field, value, error
+            ),
+            ScrapeWarning::UnsupportedSchema {
+                advertised,
+                supported,
+            } => write!(
+                f,
+                "repositories.json has schema {}, but only schema {} is supported",
+                advertised, supported
+            ),
+            ScrapeWarning::ClockSkew { repository, skew } => write!(
+                f,
+                "Timestamps of {} are {}s in the future",
+                repository,
+                skew.as_secs()
+            ),
+            ScrapeWarning::GcAfterSnapshot { repository } => write!(
+                f,
+                "Garbage collection of {} ran after the last snapshot",
+                repository
+            ),
+            ScrapeWarning::CrossOriginRedirect { from, to } => {
+                write!(
+                    f,
+                    "Followed a cross-origin redirect from {} to {}",
+                    from, to
+                )
+            }
+        }
+    }
+}
+
+impl ScrapeWarning {
+    /// The repository the warning is about, if it is about a single repository.
+    pub(crate) fn repository(&self) -> Option<&str> {
+        match self {
+            ScrapeWarning::ClockSkew { repository, .. }
+            | ScrapeWarning::GcAfterSnapshot { repository } => Some(repository),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use yare::parameterized;
+
+    #[parameterized(
+        mismatch = {
+            ScrapeWarning::ServerTypeMismatch("no replicas".to_string()),
+            json!({"kind": "server_type_mismatch", "details": "no replicas"})
+        },
+        no_repositories = {
+            ScrapeWarning::NoRepositoriesPublished,
+            json!({"kind": "no_repositories_published"})
+        },
+        metadata = {
+            ScrapeWarning::MetadataConversion {
+                field: "cvmfs_version".to_string(),
+                value: "latest".to_string(),
+                error: "unexpected character".to_string(),
+            },
+            json!({
+                "kind": "metadata_conversion",
+                "details": {"field": "cvmfs_version", "value": "latest", "error": "unexpected character"}
+            })
+        },
+        unsupported_schema = {
+            ScrapeWarning::UnsupportedSchema { advertised: 2, supported: 1 },
+            json!({"kind": "unsupported_schema", "details": {"advertised": 2, "supported": 1}})
+        },
+        clock_skew = {
+            ScrapeWarning::ClockSkew {
+                repository: "software.eessi.io".to_string(),
+                skew: std::time::Duration::from_secs(90),
+            },
+            json!({
+                "kind": "clock_skew",
+                "details": {"repository": "software.eessi.io", "skew": {"secs": 90, "nanos": 0}}
+            })
+        },
+        gc_after_snapshot = {
+            ScrapeWarning::GcAfterSnapshot {
+                repository: "software.eessi.io".to_string(),
+            },
+            json!({"kind": "gc_after_snapshot", "details": {"repository": "software.eessi.io"}})
+        },
+        cross_origin_redirect = {
+            ScrapeWarning::CrossOriginRedirect {
+                from: "http://a.example.org/x".to_string(),
+                to: "https://b.example.org/x".to_string(),
+            },
+            json!({
+                "kind": "cross_origin_redirect",
+                "details": {"from": "http://a.example.org/x", "to": "https://b.example.org/x"}
+            })
+        },
+    )]
+    fn test_warning_serde_roundtrip(warning: ScrapeWarning, expected: serde_json::Value) {
+        let value = serde_json::to_value(&warning).unwrap();
+        assert_eq!(value, expected);
+        let parsed: ScrapeWarning = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, warning);
+    }
+
+    #[test]
+    fn test_warning_display() {
+        let warning = ScrapeWarning::MetadataConversion {
+            field: "schema".to_string(),
+            value: "v1".to_string(),
+            error: "not a non-negative integer".to_string(),
+        };
+        assert_eq!(
+            warning.to_string(),
+            "Unable to parse metadata field schema (\"v1\"): not a non-negative integer"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Round-trip tests for the JSON documents served by CVMFS servers.
+//!
+//! Each fixture in tests/fixtures is parsed, serialized back to JSON, and parsed again. The two
+//! parsed values must be equal, which catches changes to field names or serde attributes that
+//! would break compatibility with real server output.
+
+use std::fs;
+use std::path::PathBuf;
+
+use cvmfs_server_scraper::{MetaJSON, RepositoriesJSON};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use yare::parameterized;
+
+fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e))
+}
+
+fn roundtrip<T>(name: &str) -> T
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let parsed: T = serde_json::from_str(&fixture(name)).unwrap();
+    let serialized = serde_json::to_string(&parsed).unwrap();
+    let reparsed: T = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(parsed, reparsed);
+    parsed
+}
+
+#[test]
+fn test_stratum0_repositories_json_roundtrip() {
+    let repos: RepositoriesJSON = roundtrip("stratum0_repositories.json");
+    assert_eq!(repos.repositories.len(), 3);
+    assert!(repos.replicas.is_empty());
+    assert!(repos.last_geodb_update.is_none());
+}
+
+#[test]
+fn test_stratum1_repositories_json_roundtrip() {
+    let repos: RepositoriesJSON = roundtrip("stratum1_repositories.json");
+    assert!(repos.repositories.is_empty());
+    assert_eq!(repos.replicas.len(), 3);
+    assert!(repos.last_geodb_update.is_some());
+}
+
+#[test]
+fn test_meta_json_roundtrip() {
+    let meta: MetaJSON = roundtrip("meta.json");
+    assert_eq!(meta.organisation, "EESSI");
+    assert!(meta.custom.get("_comment").is_some());
+}
+
+#[parameterized(
+    repositories_json = { "stratum0_repositories.json" },
+    meta_json = { "meta.json" },
+)]
+fn test_roundtrip_preserves_json(name: &str) {
+    let original: serde_json::Value = serde_json::from_str(&fixture(name)).unwrap();
+    let roundtripped = match name {
+        "meta.json" => serde_json::to_value(roundtrip::<MetaJSON>(name)).unwrap(),
+        _ => serde_json::to_value(roundtrip::<RepositoriesJSON>(name)).unwrap(),
+    };
+    for (key, value) in original.as_object().unwrap() {
+        assert_eq!(
+            roundtripped.get(key),
+            Some(value),
+            "Mismatch for key {}",
+            key
+        );
+    }
+}
+
+#[test]
+fn test_s3_server_has_no_json_endpoint() {
+    // S3 backends serve an XML error document instead of repositories.json.
+    let body = fixture("s3_repositories.xml");
+    assert!(serde_json::from_str::<RepositoriesJSON>(&body).is_err());
+}
@@ -0,0 +1,239 @@
+//! End-to-end scrapes of servers read from local directories (`file://`).
+//!
+//! Each directory in tests/fixtures/local is the web root of a server, laid out as it would be
+//! served over HTTP. This exercises the full scrape flow, including manifest parsing, offline.
+//! The stratum1 directory is a CVMFS server, while s3 has no repositories.json, like an S3 bucket.
+//! The stratum1 repository also has a (zlib compressed) tag history database under data/, as
+//! referenced by the H field of its manifest, and a .cvmfswhitelist.
+
+use std::path::PathBuf;
+
+use cvmfs_server_scraper::{
+    scrape_url, scrape_url_with_name, Hostname, ScrapeMode, ScrapeOptions, Scraper, ScraperCommon,
+    Server, ServerBackendType, ServerType,
+};
+
+fn local_root(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("local")
+        .join(name)
+}
+
+fn local_server(name: &str, backend_type: ServerBackendType) -> Server {
+    Server::new(
+        ServerType::Stratum1,
+        backend_type,
+        Hostname::try_from(format!("{}.example.org", name).as_str()).unwrap(),
+    )
+    .with_local_root(local_root(name))
+}
+
+#[tokio::test]
+async fn test_scrape_local_stratum1() {
+    let server = local_server("stratum1", ServerBackendType::CVMFS);
+    let options = ScrapeOptions {
+        fetch_whitelist: true,
+        ..Default::default()
+    };
+    let populated = server
+        .scrape_with_options(Vec::<String>::new(), &options)
+        .await
+        .get_populated_server()
+        .unwrap();
+
+    assert_eq!(populated.repositories.len(), 1);
+    let repo = &populated.repositories[0];
+    assert_eq!(repo.name, "software.eessi.io");
+    assert_eq!(repo.revision(), 12345);
+    assert!(repo.last_gc.is_some());
+    let whitelist = repo.whitelist.as_ref().unwrap();
+    assert_eq!(whitelist.repository.as_deref(), Some("software.eessi.io"));
+    assert!(repo.whitelist_remaining().unwrap() > chrono::Duration::zero());
+    assert_eq!(
+        populated.metadata.cvmfs_version,
+        Some(semver::Version::parse("2.11.3-1").unwrap())
+    );
+    assert!(populated.metadata.administrator.is_some());
+    // Fields the scraper does not know about are kept.
+    assert_eq!(
+        populated.metadata.extra.get("future_field"),
+        Some(&serde_json::json!("kept"))
+    );
+    assert!(populated.geoapi.response.is_empty());
+}
+
+#[tokio::test]
+async fn test_scrape_local_autodetect() {
+    let server = local_server("stratum1", ServerBackendType::AutoDetect);
+    let populated = server
+        .scrape_with_options(Vec::<String>::new(), &ScrapeOptions::default())
+        .await
+        .get_populated_server()
+        .unwrap();
+    assert_eq!(populated.backend_detected, ServerBackendType::CVMFS);
+    assert!(populated.has_repository("software.eessi.io"));
+}
+
+#[tokio::test]
+async fn test_scrape_local_missing_repository_fails() {
+    let server = local_server("stratum1", ServerBackendType::CVMFS);
+    let result = server
+        .scrape_with_options(vec!["missing.eessi.io"], &ScrapeOptions::default())
+        .await;
+    assert!(result.is_failed());
+}
+
+#[tokio::test]
+async fn test_scrape_mixed_backends() {
+    // Every server in a batch is scraped with its own backend type.
+    let servers = vec![
+        local_server("stratum1", ServerBackendType::CVMFS),
+        local_server("s3", ServerBackendType::S3),
+        local_server("stratum1", ServerBackendType::AutoDetect),
+        local_server("s3", ServerBackendType::AutoDetect),
+    ];
+    let scraper = Scraper::new()
+        .with_servers(servers)
+        .forced_repositories(vec!["software.eessi.io"])
+        .validate()
+        .unwrap();
+    let results = scraper.scrape().await;
+    assert_eq!(results.len(), 4);
+
+    let expected = [
+        (ServerBackendType::CVMFS, ServerBackendType::CVMFS, false),
+        (ServerBackendType::S3, ServerBackendType::S3, false),
+        (
+            ServerBackendType::AutoDetect,
+            ServerBackendType::CVMFS,
+            true,
+        ),
+        (ServerBackendType::AutoDetect, ServerBackendType::S3, true),
+    ];
+    for (result, (backend_type, detected, attempted)) in results.into_iter().zip(expected) {
+        let populated = result.get_populated_server().unwrap();
+        assert_eq!(populated.backend_type, backend_type);
+        assert_eq!(populated.backend_detected, detected);
+        assert_eq!(populated.detection.attempted, attempted);
+        assert_eq!(
+            populated.detection.fell_back(),
+            detected == ServerBackendType::S3 && attempted
+        );
+        assert!(populated.has_repository("software.eessi.io"));
+        // Only servers with repositories.json have metadata.
+        assert_eq!(
+            populated.metadata.cvmfs_version.is_some(),
+            detected == ServerBackendType::CVMFS
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_scrape_local_metadata_only() {
+    let server = local_server("stratum1", ServerBackendType::CVMFS);
+    let options = ScrapeOptions {
+        mode: ScrapeMode::MetadataOnly,
+        ..Default::default()
+    };
+    let populated = server
+        .scrape_with_options(Vec::<String>::new(), &options)
+        .await
+        .get_populated_server()
+        .unwrap();
+    assert_eq!(populated.mode, ScrapeMode::MetadataOnly);
+    assert!(populated.repositories.is_empty());
+    assert_eq!(populated.discovered_repositories, vec!["software.eessi.io"]);
+    assert!(populated.metadata.cvmfs_version.is_some());
+    assert!(populated.metadata.administrator.is_some());
+}
+
+#[tokio::test]
+async fn test_scrape_url() {
+    let repository = local_root("stratum1")
+        .join("cvmfs")
+        .join("software.eessi.io");
+    let manifest_url = format!("file://{}", repository.join(".cvmfspublished").display());
+    let status_json_url = format!("file://{}", repository.join(".cvmfs_status.json").display());
+    let client = reqwest::Client::new();
+
+    let repo = scrape_url(&manifest_url, &status_json_url, &client)
+        .await
+        .unwrap();
+    assert_eq!(repo.name, "software.eessi.io");
+    assert_eq!(repo.revision(), 12345);
+    assert!(repo.last_gc.is_some());
+
+    let repo = scrape_url_with_name("other.eessi.io", &manifest_url, &status_json_url, &client)
+        .await
+        .unwrap();
+    assert_eq!(repo.name, "other.eessi.io");
+
+    assert!(
+        scrape_url_with_name("not-qualified", &manifest_url, &status_json_url, &client)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_scrape_local_with_metrics() {
+    let server = local_server("stratum1", ServerBackendType::CVMFS);
+    let (result, metrics) = server
+        .scrape_with_metrics(Vec::<String>::new(), &ScrapeOptions::default())
+        .await;
+    assert!(result.is_ok());
+    assert!(metrics.repos_json_fetch_duration.is_some());
+    assert!(metrics.meta_json_fetch_duration.is_some());
+    assert_eq!(metrics.per_repo_durations.len(), 1);
+    assert_eq!(metrics.per_repo_durations[0].0, "software.eessi.io");
+    assert_eq!(
+        metrics.slowest_repo.as_ref(),
+        metrics.per_repo_durations.first()
+    );
+    assert!(metrics.total_duration >= metrics.per_repo_durations[0].1);
+
+    // S3 backends have no repositories.json to time.
+    let server = local_server("s3", ServerBackendType::S3);
+    let (_, metrics) = server
+        .scrape_with_metrics(vec!["software.eessi.io"], &ScrapeOptions::default())
+        .await;
+    assert_eq!(metrics.repos_json_fetch_duration, None);
+    assert_eq!(metrics.per_repo_durations.len(), 1);
+}
+
+#[cfg(feature = "tags")]
+#[tokio::test]
+async fn test_scrape_local_tags() {
+    let server = local_server("stratum1", ServerBackendType::CVMFS);
+    let options = ScrapeOptions {
+        fetch_tags: true,
+        ..Default::default()
+    };
+    let populated = server
+        .scrape_with_options(Vec::<String>::new(), &options)
+        .await
+        .get_populated_server()
+        .unwrap();
+    let repo = &populated.repositories[0];
+    let tags = repo.tags.as_ref().unwrap();
+    assert_eq!(tags.len(), 3);
+    let latest = repo.latest_tag().unwrap();
+    assert_eq!(latest.name, "trunk");
+    assert_eq!(latest.revision, repo.revision());
+
+    let tags = cvmfs_server_scraper::RepositoryOrReplica::new(&repo.name, &server)
+        .fetch_tag_list(&reqwest::Client::new())
+        .await
+        .unwrap();
+    assert_eq!(tags.as_slice(), repo.tags.as_deref().unwrap());
+
+    // Tags are opt-in.
+    let populated = server
+        .scrape_with_options(Vec::<String>::new(), &ScrapeOptions::default())
+        .await
+        .get_populated_server()
+        .unwrap();
+    assert_eq!(populated.repositories[0].tags, None);
+}
@@ -0,0 +1,39 @@
+//! Parsing tests for .cvmfspublished files with formatting quirks seen on real servers.
+//!
+//! Every fixture in tests/fixtures/manifests holds the same manifest, differing only in
+//! encoding details (BOM, line endings, trailing whitespace), and must parse to the same result.
+
+use std::fs;
+use std::path::PathBuf;
+
+use cvmfs_server_scraper::Manifest;
+use yare::parameterized;
+
+fn fixture(name: &str) -> Manifest {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("manifests")
+        .join(name);
+    let content =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e));
+    content
+        .parse()
+        .unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", path, e))
+}
+
+#[parameterized(
+    bom = { "bom.cvmfspublished" },
+    crlf = { "crlf.cvmfspublished" },
+    mixed_endings = { "mixed_endings.cvmfspublished" },
+    trailing_whitespace = { "trailing_whitespace.cvmfspublished" },
+)]
+fn test_manifest_quirks(name: &str) {
+    let expected = fixture("plain.cvmfspublished");
+    let manifest = fixture(name);
+    assert_eq!(manifest.n, "software.eessi.io");
+    assert_eq!(manifest.s, 12345);
+    assert_eq!(manifest.t, 1718991602);
+    assert_eq!(manifest.signature, "signature");
+    assert_eq!(manifest, expected);
+}
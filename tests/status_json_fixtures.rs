@@ -0,0 +1,83 @@
+//! Parsing tests for .cvmfs_status.json files as served by real servers.
+//!
+//! The fixtures in tests/fixtures/status cover a Stratum0 (garbage collection only), a Stratum1
+//! replica (snapshots), and replicas without garbage collection. The Stratum0 and Stratum1
+//! fixtures also report the last `cvmfs_server check`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use cvmfs_server_scraper::{MaybeRfc2822DateTime, StatusJSON};
+use yare::parameterized;
+
+fn fixture(name: &str) -> StatusJSON {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("status")
+        .join(name);
+    let content =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e));
+    serde_json::from_str(&content).unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", path, e))
+}
+
+fn datetime(field: Option<MaybeRfc2822DateTime>) -> Option<DateTime<Utc>> {
+    field.and_then(|field| field.try_into_datetime().unwrap())
+}
+
+fn utc(rfc3339: &str) -> Option<DateTime<Utc>> {
+    Some(DateTime::parse_from_rfc3339(rfc3339).unwrap().into())
+}
+
+#[parameterized(
+    stratum0 = { "stratum0.cvmfs_status.json", None, None, utc("2024-06-16T00:00:59Z") },
+    stratum1 = {
+        "stratum1.cvmfs_status.json",
+        utc("2024-06-21T17:40:02Z"),
+        utc("2024-06-21T17:35:00Z"),
+        utc("2024-06-16T00:00:59Z")
+    },
+    null_gc = { "null_gc.cvmfs_status.json", utc("2024-06-21T17:40:02Z"), None, None },
+    no_gc = { "no_gc.cvmfs_status.json", utc("2024-06-21T17:40:02Z"), None, None },
+)]
+fn test_status_json_fixture(
+    name: &str,
+    last_snapshot: Option<DateTime<Utc>>,
+    last_snapshot_start: Option<DateTime<Utc>>,
+    last_gc: Option<DateTime<Utc>>,
+) {
+    let status = fixture(name);
+    assert_eq!(datetime(status.last_snapshot), last_snapshot);
+    assert_eq!(datetime(status.last_snapshot_start), last_snapshot_start);
+    assert_eq!(datetime(status.last_gc), last_gc);
+}
+
+#[parameterized(
+    stratum0 = { "stratum0.cvmfs_status.json", utc("2024-06-15T04:12:31Z"), Some("succeeded") },
+    stratum1 = { "stratum1.cvmfs_status.json", utc("2024-06-20T03:02:11Z"), Some("succeeded") },
+    no_gc = { "no_gc.cvmfs_status.json", None, None },
+)]
+fn test_status_json_fixture_check(
+    name: &str,
+    last_check: Option<DateTime<Utc>>,
+    check_status: Option<&str>,
+) {
+    let status = fixture(name);
+    assert_eq!(datetime(status.last_check), last_check);
+    assert_eq!(status.check_status.as_deref(), check_status);
+    assert!(status.extra.is_empty());
+}
+
+#[test]
+fn test_status_json_unknown_fields() {
+    let status: StatusJSON = serde_json::from_str(
+        r#"{"last_snapshot": "Fri Jun 21 17:40:02 UTC 2024", "snapshot_status": "running",
+            "future_field": {"nested": [1, 2, 3]}}"#,
+    )
+    .unwrap();
+    assert_eq!(datetime(status.last_snapshot), utc("2024-06-21T17:40:02Z"));
+    assert!(status.last_gc.is_none());
+    assert_eq!(status.extra["snapshot_status"], "running");
+    assert_eq!(status.extra["future_field"]["nested"][2], 3);
+}